@@ -0,0 +1,74 @@
+//! Benchmark of HashLife on random Life soups.
+//!
+//! Run with `cargo bench --bench soup -- [SIZE]`, where `SIZE` is the side
+//! length of the soup (256 by default). Each step size is timed on a fresh
+//! copy of the soup with a new simulation, so no results are shared between
+//! them. On Linux, the peak resident set size of the process is also printed.
+
+use ndcell_core::*;
+use num::BigInt;
+use std::time::Instant;
+
+/// Step sizes to time separately.
+const STEP_SIZES: [usize; 3] = [1, 64, 256];
+
+/// Returns a soup with side length `size` in which about one third of the cells
+/// are live, using a fixed seed so that every run uses the same soup.
+fn make_soup(size: isize) -> NdTree<u8, Dim2D> {
+    let mut seed: u64 = 12345;
+    let mut tree = NdTree::new();
+    for pos in IRect2D::span(NdVec::origin(), NdVec::repeat(size - 1)).iter() {
+        // Linear congruential generator from Knuth's MMIX.
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        if (seed >> 33) % 3 == 0 {
+            tree.set_cell(&pos.convert(), 1);
+        }
+    }
+    tree
+}
+
+/// Returns the peak resident set size of this process in kilobytes, if it can
+/// be read from `/proc`.
+fn peak_rss_kb() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn main() {
+    let size = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .map(|arg| arg.parse().expect("Soup size must be an integer"))
+        .unwrap_or(256);
+    println!("{0}x{0} random Life soup", size);
+
+    for &step_size in &STEP_SIZES {
+        let mut tree = make_soup(size);
+        let mut sim = Simulation::from(rule::LIFE);
+        let start = Instant::now();
+        sim.step(&mut tree, &BigInt::from(step_size));
+        println!("step {}: {:?}", step_size, start.elapsed());
+    }
+
+    // Step by 1, 1, 2, 4, ... 64 generations using the same simulation, like
+    // doubling the step size in the UI.
+    let mut tree = make_soup(size);
+    let mut sim = Simulation::from(rule::LIFE).with_memory_limit(None);
+    let start = Instant::now();
+    sim.step(&mut tree, &BigInt::from(1));
+    for k in 0..=6 {
+        sim.step(&mut tree, &(BigInt::from(1) << k));
+    }
+    let stats = sim.memory_stats(&tree.cache);
+    println!("steps 1, 1, 2, ... 64: {:?}", start.elapsed());
+    println!("  nodes: {}", stats.node_count);
+    println!("  results: {}", stats.result_count);
+    println!("  estimated bytes: {}", stats.estimated_bytes);
+
+    if let Some(kb) = peak_rss_kb() {
+        println!("peak RSS: {} MB", kb / 1024);
+    }
+}
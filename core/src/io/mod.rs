@@ -1,3 +1,4 @@
 use super::*;
 
 pub mod rle;
+pub mod rule_file;
@@ -9,6 +9,7 @@
 
 use num::{BigInt, ToPrimitive, Zero};
 use pest::Parser;
+use std::convert::TryFrom;
 
 use super::*;
 
@@ -47,7 +48,7 @@ impl ToString for CxrleHeader {
     fn to_string(&self) -> String {
         let mut ret = format!("#CXRLE Pos={},{}", self.pos[X], self.pos[Y]);
         if !self.gen.is_zero() {
-            ret.push_str(&format!(", Gen={}", self.gen));
+            ret.push_str(&format!(" Gen={}", self.gen));
         }
         ret
     }
@@ -78,6 +79,9 @@ impl<C: RleCellType> RleItem<C> {
 
 type TokenPair<'a> = pest::iterators::Pair<'a, Rule>;
 
+/// The header, CXRLE header, and rows of cells of an RLE pattern.
+type ParsedRle = (RleHeader, Option<CxrleHeader>, Vec<Vec<u8>>);
+
 /// Methods for encoding/decoding patterns to/from Golly Extended RLE.
 pub trait RleEncode: std::marker::Sized {
     /// Encodes the pattern in Extended RLE, which can encode extra information
@@ -90,6 +94,10 @@ pub trait RleEncode: std::marker::Sized {
     fn to_rle(&self) -> String;
     /// Decodes a Golly Extended RLE pattern.
     fn from_rle(s: &str) -> Result<Self, String>;
+    /// Decodes a Golly Extended RLE pattern as a tile that repeats in every
+    /// direction to fill the background of an otherwise empty pattern. The
+    /// width and height of the tile must be powers of two.
+    fn from_agar_rle(s: &str) -> Result<Self, String>;
 }
 impl RleEncode for Automaton2D {
     fn to_cxrle(&self) -> String {
@@ -105,12 +113,26 @@ impl RleEncode for Automaton2D {
     }
     fn to_rle(&self) -> String {
         let root = &self.tree.slice.root;
+        let (rule, info) = {
+            let sim = self.sim.lock().unwrap();
+            let mut rule = sim.rule().to_string();
+            // Bounded grids are given as a suffix on the rule, as in Golly.
+            if !sim.topology().is_infinite() {
+                rule.push_str(&format!(":{}", sim.topology()));
+            }
+            (rule, sim.rule().info())
+        };
         let header = RleHeader {
             x: root.len(),
             y: root.len(),
-            // TODO: Actually use a proper rulestring.
-            rule: Some("Life".to_owned()),
+            rule: Some(rule),
         };
+        // Like Golly, use 'b' and 'o' for rules with only two states.
+        let two_state = info.state_count() <= 2;
+        // Cells that are left out are read as zero when inside the pattern,
+        // but as the background when outside it, so only leave out zeros if
+        // those are the same.
+        let keep_zeros = self.tree.get_background().leaf() != Some(0);
         let cell_array = NdArray::from(root);
         let mut items: Vec<(usize, RleItem<u8>)> = vec![];
         for mut pos in cell_array.rect().iter() {
@@ -118,11 +140,15 @@ impl RleEncode for Automaton2D {
             // reflect over the Y axis.
             pos[Y] = root.len().to_usize().unwrap() - pos[Y] - 1;
             let cell = cell_array[&pos.as_ivec()];
-            if pos[X] == 0 && pos[Y] != 0 {
+            // The CXRLE position is one row above the top of the root node, so
+            // every row (including the first) starts with a row end.
+            if pos[X] == 0 {
                 // We're at the beginning of a new row. Remove trailing
                 // zeros.
-                if let Some((_, RleItem::Cell(0))) = items.last() {
-                    items.pop();
+                if !keep_zeros {
+                    if let Some((_, RleItem::Cell(0))) = items.last() {
+                        items.pop();
+                    }
                 }
                 if let Some((ref mut n, RleItem::EndRow)) = items.last_mut() {
                     // Combine with an existing item if possible ...
@@ -146,8 +172,10 @@ impl RleEncode for Automaton2D {
             }
         }
         // Remove trailing zeros.
-        if let Some((_, RleItem::Cell(0))) = items.last() {
-            items.pop();
+        if !keep_zeros {
+            if let Some((_, RleItem::Cell(0))) = items.last() {
+                items.pop();
+            }
         }
         // Remve trailing row ends.
         if let Some((_, RleItem::EndRow)) = items.last() {
@@ -157,7 +185,12 @@ impl RleEncode for Automaton2D {
         ret.push_str(&header.to_string());
         let mut line_len = MAX_LINE_LEN;
         for (repeat_count, item) in items {
-            let item_str = item.repeated(repeat_count);
+            let item_str = match item {
+                RleItem::Cell(cell) if two_state && cell <= 1 => {
+                    RleItem::Cell(cell == 1).repeated(repeat_count)
+                }
+                _ => item.repeated(repeat_count),
+            };
             line_len += item_str.len();
             if line_len > MAX_LINE_LEN {
                 ret.push('\n');
@@ -173,61 +206,8 @@ impl RleEncode for Automaton2D {
         ret
     }
     fn from_rle(s: &str) -> Result<Self, String> {
-        let mut header: Option<RleHeader> = None;
-        let mut cxrle: Option<CxrleHeader> = None;
-        let mut notes: Vec<String> = vec![];
-        let mut cell_array: Vec<Vec<u8>> = vec![vec![]];
-
-        let main = Grammar::parse(Rule::main, s)
-            .map_err(|e| e.to_string())?
-            .next()
-            .ok_or("No main token pair".to_owned())?;
-        for pair in main.into_inner() {
-            match pair.as_rule() {
-                Rule::EOI => (),
-                Rule::header => {
-                    if header.is_some() {
-                        Err("Multiple RLE headers")?;
-                    } else {
-                        header = Some(parse_header(pair)?);
-                    }
-                }
-                Rule::notes => {
-                    let inner = pair.into_inner().next().ok_or("Invalid note")?;
-                    match inner.as_rule() {
-                        Rule::comment => notes.push(inner.as_str().to_string()),
-                        Rule::cxrle => {
-                            if cxrle.is_some() {
-                                Err("Multiple CXRLE headers")?
-                            } else {
-                                cxrle = Some(parse_cxrle(inner)?);
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-                Rule::content => {
-                    for content_item in pair.into_inner() {
-                        if content_item.as_rule() == Rule::content_item {
-                            let (n, state): (usize, RleItem<u8>) =
-                                parse_content_item(content_item)?;
-                            for _ in 0..n {
-                                match state {
-                                    RleItem::Cell(cell_state) => {
-                                        cell_array.last_mut().unwrap().push(cell_state)
-                                    }
-                                    RleItem::EndRow => cell_array.push(vec![]),
-                                }
-                            }
-                        }
-                    }
-                }
-                _ => Err("Unexpected token pair")?,
-            }
-        }
-        let header = header.ok_or("Missing RLE header")?;
-
-        let mut ret = NdAutomaton::default();
+        let (header, cxrle, cell_array) = parse_rle(s)?;
+        let mut ret = new_automaton(&header)?;
         let mut pos;
         if let Some(cxrle) = cxrle {
             ret.generations = cxrle.gen;
@@ -235,6 +215,13 @@ impl RleEncode for Automaton2D {
         } else {
             pos = NdVec([-header.x / 2, -header.y / 2]);
         }
+        // Rules with "B0" behavior may have a different background after some
+        // number of generations.
+        let rule = ret.sim.lock().unwrap().rule().clone();
+        let background = Simulation::new(rule).advance_background(0, &ret.generations);
+        if background != 0 {
+            ret.tree = NdTree::with_background(background);
+        }
         let x_start = pos[X].clone();
         // Y coordinates increase upwards in NDCell, but downwards in RLE, so
         // reflect over the Y axis.
@@ -251,6 +238,116 @@ impl RleEncode for Automaton2D {
 
         Ok(ret)
     }
+    fn from_agar_rle(s: &str) -> Result<Self, String> {
+        let (header, cxrle, cell_array) = parse_rle(s)?;
+        let mut ret = new_automaton(&header)?;
+        // Cells beyond the edges of a bounded grid are always in the default
+        // state, so they can't be part of a periodic background.
+        if !ret.sim.lock().unwrap().topology().is_infinite() {
+            Err("Agar patterns cannot have a bounded topology")?;
+        }
+        let tile_len = |len: &BigInt| len.to_usize().filter(|len| len.is_power_of_two());
+        let size: UVec2D = match (tile_len(&header.x), tile_len(&header.y)) {
+            (Some(x), Some(y)) => NdVec([x, y]),
+            _ => Err("Agar width and height must be powers of two")?,
+        };
+        // Y coordinates increase upwards in NDCell, but downwards in RLE, so
+        // reflect over the Y axis.
+        let tile = NdArray::from_fn(size, |pos| {
+            cell_array
+                .get(size[Y] - 1 - pos[Y] as usize)
+                .and_then(|row| row.get(pos[X] as usize))
+                .copied()
+                .unwrap_or(0)
+        });
+        ret.tree = NdTree::with_background_tile(&tile);
+        if let Some(cxrle) = cxrle {
+            ret.generations = cxrle.gen;
+            // Move the bottom-left corner of the tile to the position of the
+            // bottom-left corner of the pattern.
+            let mut corner = cxrle.pos;
+            corner[Y] = -&corner[Y] - &header.y + 1;
+            ret.tree.recenter(-corner);
+        }
+        Ok(ret)
+    }
+}
+
+/// Parses the header, CXRLE header, and rows of cells of an RLE pattern.
+fn parse_rle(s: &str) -> Result<ParsedRle, String> {
+    let mut header: Option<RleHeader> = None;
+    let mut cxrle: Option<CxrleHeader> = None;
+    let mut notes: Vec<String> = vec![];
+    let mut cell_array: Vec<Vec<u8>> = vec![vec![]];
+
+    let main = Grammar::parse(Rule::main, s)
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or("No main token pair".to_owned())?;
+    for pair in main.into_inner() {
+        match pair.as_rule() {
+            Rule::EOI => (),
+            Rule::header => {
+                if header.is_some() {
+                    Err("Multiple RLE headers")?;
+                } else {
+                    header = Some(parse_header(pair)?);
+                }
+            }
+            Rule::notes => {
+                let inner = pair.into_inner().next().ok_or("Invalid note")?;
+                match inner.as_rule() {
+                    Rule::comment => notes.push(inner.as_str().to_string()),
+                    Rule::cxrle => {
+                        if cxrle.is_some() {
+                            Err("Multiple CXRLE headers")?
+                        } else {
+                            cxrle = Some(parse_cxrle(inner)?);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Rule::content => {
+                for content_item in pair.into_inner() {
+                    if content_item.as_rule() == Rule::content_item {
+                        let (n, state): (usize, RleItem<u8>) = parse_content_item(content_item)?;
+                        for _ in 0..n {
+                            match state {
+                                RleItem::Cell(cell_state) => {
+                                    cell_array.last_mut().unwrap().push(cell_state)
+                                }
+                                RleItem::EndRow => cell_array.push(vec![]),
+                            }
+                        }
+                    }
+                }
+            }
+            _ => Err("Unexpected token pair")?,
+        }
+    }
+    let header = header.ok_or("Missing RLE header")?;
+    Ok((header, cxrle, cell_array))
+}
+
+/// Constructs an empty automaton using the rule in the given RLE header.
+fn new_automaton(header: &RleHeader) -> Result<Automaton2D, String> {
+    let mut ret = NdAutomaton::default();
+    let sim = match &header.rule {
+        Some(rulestring) => {
+            // A bounded grid is given as a suffix on the rule, such as
+            // "B3/S23:T100,80".
+            let mut parts = rulestring.splitn(2, ':');
+            let sim = Simulation::new(rule::from_rulestring(parts.next().unwrap())?);
+            match parts.next() {
+                Some(topology) => sim.with_topology(Topology::try_from(topology)?),
+                None => sim,
+            }
+        }
+        None => Simulation::from(rule::LIFE),
+    };
+    ret.set_sim(sim);
+    Ok(ret)
 }
 
 fn parse_header(pair: TokenPair) -> Result<RleHeader, String> {
@@ -477,7 +574,10 @@ o$3o!
 ",
         )
         .unwrap();
-        assert_eq!(BigInt::from(5), imported.tree.get_root().population);
+        assert_eq!(
+            BigInt::from(5),
+            imported.tree.get_root().population().to_bigint()
+        );
         assert_eq!(1, imported.tree.get_cell(&NdVec::big([11, 14])));
         assert_eq!(1, imported.tree.get_cell(&NdVec::big([12, 13])));
         assert_eq!(1, imported.tree.get_cell(&NdVec::big([10, 12])));
@@ -490,8 +590,8 @@ o$3o!
         assert_eq!(
             "\
 #CXRLE Pos=-16,-16
-x = 32, y = 32, rule = Life
-2$27.A$28.A$26.3A!
+x = 32, y = 32, rule = B3/S23
+2$27bo$28bo$26b3o!
 ",
             exported
         );
@@ -499,4 +599,87 @@ x = 32, y = 32, rule = Life
             RleEncode::from_rle(&exported).expect("Could not parse RLE output");
         assert_eq!(imported.tree, reimported.tree);
     }
+
+    /// Tests that cells on the bottom row of the root node are saved on their
+    /// own row.
+    #[test]
+    fn test_cxrle_bottom_row() {
+        let mut automaton: Automaton2D =
+            RleEncode::from_rle("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        let bottom_left = automaton.tree.slice.min();
+        automaton.tree.set_cell(&bottom_left, 1);
+        automaton
+            .tree
+            .set_cell(&(bottom_left + NdVec::big([1, 1])), 1);
+        let exported = RleEncode::to_cxrle(&automaton);
+        let reimported: Automaton2D =
+            RleEncode::from_rle(&exported).expect("Could not parse RLE output");
+        assert_eq!(automaton.tree, reimported.tree);
+    }
+
+    /// Tests that the generation count is saved in a form that can be loaded.
+    #[test]
+    fn test_cxrle_gen() {
+        let mut automaton: Automaton2D =
+            RleEncode::from_rle("#CXRLE Pos=0,0 Gen=42\nx = 3, y = 1, rule = B3/S23\n3o!").unwrap();
+        assert_eq!(&BigInt::from(42), automaton.get_generation_count());
+        automaton.step(&BigInt::from(1));
+        let exported = RleEncode::to_cxrle(&automaton);
+        assert!(exported.starts_with("#CXRLE Pos=-2,-2 Gen=43\n"));
+        let reimported: Automaton2D =
+            RleEncode::from_rle(&exported).expect("Could not parse RLE output");
+        assert_eq!(&BigInt::from(43), reimported.get_generation_count());
+    }
+
+    /// Tests saving and loading a pattern whose background is not zero.
+    #[test]
+    fn test_b0_cxrle() {
+        let mut automaton: Automaton2D =
+            RleEncode::from_rle("x = 3, y = 1, rule = B03/S23\n3o!").unwrap();
+        automaton.step(&BigInt::from(1));
+        assert_eq!(Some(1), automaton.tree.get_background().leaf());
+        let exported = RleEncode::to_cxrle(&automaton);
+        let reimported: Automaton2D =
+            RleEncode::from_rle(&exported).expect("Could not parse RLE output");
+        assert_eq!(Some(1), reimported.tree.get_background().leaf());
+        assert_eq!(automaton.get_population(), reimported.get_population());
+        for pos in IRect2D::moore(8).iter() {
+            let pos: BigVec2D = pos.convert();
+            assert_eq!(
+                automaton.tree.get_cell(&pos),
+                reimported.tree.get_cell(&pos)
+            );
+        }
+    }
+
+    /// Tests loading a periodic background from an RLE.
+    #[test]
+    fn test_agar_rle() {
+        // Without a CXRLE header, the bottom-left corner of the tile is at the
+        // origin.
+        let agar: Automaton2D = RleEncode::from_agar_rle("x = 2, y = 2, rule = B3/S23\nob$bo!")
+            .expect("Could not parse agar");
+        assert_eq!(BigInt::from(0), agar.get_population());
+        for pos in IRect2D::moore(4).iter() {
+            let expected = (pos[X] + pos[Y]).rem_euclid(2) as u8;
+            assert_eq!(expected, agar.tree.get_cell(&pos.convert()));
+        }
+        // Moving the top-left cell to (1, -1) flips the checkerboard.
+        let agar: Automaton2D =
+            RleEncode::from_agar_rle("#CXRLE Pos=1,1\nx = 2, y = 2, rule = B3/S23\nob$bo!")
+                .expect("Could not parse agar");
+        for pos in IRect2D::moore(4).iter() {
+            let expected = (pos[X] + pos[Y] + 1).rem_euclid(2) as u8;
+            assert_eq!(expected, agar.tree.get_cell(&pos.convert()));
+        }
+        assert!(
+            <Automaton2D as RleEncode>::from_agar_rle("x = 3, y = 2, rule = B3/S23\nobo$bob!")
+                .is_err()
+        );
+        // Periodic backgrounds are not supported in bounded grids.
+        for topology in &["T16,16", "T16,0", "P0,16", "K16,16"] {
+            let rle = format!("x = 2, y = 2, rule = B3/S23:{}\nob$bo!", topology);
+            assert!(<Automaton2D as RleEncode>::from_agar_rle(&rle).is_err());
+        }
+    }
 }
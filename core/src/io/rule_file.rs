@@ -0,0 +1,927 @@
+//! Code for reading Golly's `.rule` format, described here:
+//! http://golly.sourceforge.net/Help/formats.html#rule
+//!
+//! Only the `@RULE`, `@TABLE`, `@TREE`, `@COLORS` and `@ICONS` sections are
+//! currently supported; any other sections are ignored.
+//!
+//! Note that Golly has Y values increasing downwards, while NDCell has Y values
+//! increasing upwards, so "north" in a rule file is the +Y direction in NDCell
+//! (which is consistent with the reflection done when loading RLE).
+
+#![allow(missing_docs)]
+
+use pest::error::{Error as PestError, ErrorVariant};
+use pest::Parser;
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+#[derive(Parser)]
+#[grammar = "io/rule_file.pest"]
+struct Grammar;
+
+type TokenPair<'a> = pest::iterators::Pair<'a, Rule>;
+/// The set of allowed states for each cell in a transition, and the next
+/// state.
+type Transition = (Vec<Vec<u8>>, u8);
+
+/// Loads a rule from a Golly `.rule` file.
+///
+/// As in Golly, if the file has both a `@TABLE` and a `@TREE` section, then
+/// the `@TABLE` is used.
+pub fn load_rule<D: Dim>(s: &str) -> Result<Arc<dyn rule::Rule<u8, D>>, String> {
+    let main = Grammar::parse(Rule::main, s)
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or("No main token pair")?;
+    let mut name = None;
+    let mut table = None;
+    let mut tree = None;
+    let mut colors = None;
+    let mut icons = None;
+    for section in main.into_inner() {
+        match section.as_rule() {
+            Rule::rule_section => {
+                let inner = section.into_inner().next().ok_or("Missing rule name")?;
+                name = Some(inner.as_str().to_owned());
+            }
+            Rule::table_section => {
+                if table.is_some() {
+                    return Err(error_at(&section, "Multiple @TABLE sections"));
+                }
+                table = Some(section);
+            }
+            Rule::tree_section => {
+                if tree.is_some() {
+                    return Err(error_at(&section, "Multiple @TREE sections"));
+                }
+                tree = Some(section);
+            }
+            Rule::colors_section => {
+                if colors.is_some() {
+                    return Err(error_at(&section, "Multiple @COLORS sections"));
+                }
+                colors = Some(section);
+            }
+            Rule::icons_section => {
+                if icons.is_some() {
+                    return Err(error_at(&section, "Multiple @ICONS sections"));
+                }
+                icons = Some(section);
+            }
+            _ => (),
+        }
+    }
+    let name = name.ok_or("Missing @RULE section")?;
+    let with_info = |info: rule::RuleInfo| parse_info(info, colors, icons);
+    match (table, tree) {
+        (Some(table), _) => {
+            let table = parse_table::<D>(name, table)?;
+            let info = with_info(rule::Rule::<u8, D>::info(&table))?;
+            Ok(Arc::new(table.with_info(info)))
+        }
+        (None, Some(tree)) => {
+            let tree = parse_tree::<D>(name, tree)?;
+            let info = with_info(rule::Rule::<u8, D>::info(&tree))?;
+            Ok(Arc::new(tree.with_info(info)))
+        }
+        (None, None) => Err(format!("Rule '{}' has no @TABLE or @TREE section", name)),
+    }
+}
+
+/// Returns an error message (including the line and column numbers) for the
+/// given token pair.
+fn error_at(pair: &TokenPair, message: impl Into<String>) -> String {
+    PestError::<Rule>::new_from_span(
+        ErrorVariant::CustomError {
+            message: message.into(),
+        },
+        pair.as_span(),
+    )
+    .to_string()
+}
+
+/// A neighborhood that can be used in a Golly rule table, as the positions of
+/// the cells in the order that they appear in a transition (starting with the
+/// center cell). The neighbors are listed clockwise, so rotating the
+/// neighborhood is the same as rotating the list.
+struct TableNeighborhood {
+    ndim: usize,
+    cells: &'static [[isize; 2]],
+}
+
+impl TableNeighborhood {
+    fn from_name(name: &str) -> Option<Self> {
+        let (ndim, cells): (usize, &[[isize; 2]]) = match name {
+            // C, N, E, S, W
+            "vonNeumann" => (2, &[[0, 0], [0, 1], [1, 0], [0, -1], [-1, 0]]),
+            // C, N, NE, E, SE, S, SW, W, NW
+            "Moore" => (
+                2,
+                &[
+                    [0, 0],
+                    [0, 1],
+                    [1, 1],
+                    [1, 0],
+                    [1, -1],
+                    [0, -1],
+                    [-1, -1],
+                    [-1, 0],
+                    [-1, 1],
+                ],
+            ),
+            // C, N, E, SE, S, W, NW
+            "hexagonal" => (
+                2,
+                &[[0, 0], [0, 1], [1, 0], [1, -1], [0, -1], [-1, 0], [-1, 1]],
+            ),
+            // C, W, E
+            "oneDimensional" => (1, &[[0, 0], [-1, 0], [1, 0]]),
+            _ => return None,
+        };
+        Some(Self { ndim, cells })
+    }
+
+    /// Returns the permutations of the cells in a transition that are
+    /// equivalent under the given symmetry (including the identity), or
+    /// `None` if the symmetry is not valid for this neighborhood. Each
+    /// permutation gives the index of the source cell for each cell.
+    ///
+    /// `permute` is handled separately, since it allows any permutation.
+    fn symmetries(&self, name: &str) -> Option<Vec<Vec<usize>>> {
+        let n = self.cells.len() - 1;
+        let rotate = |steps: usize| -> Vec<usize> {
+            let mut ret = vec![0];
+            ret.extend((0..n).map(|i| (i + steps) % n + 1));
+            ret
+        };
+        let reflect = || -> Vec<usize> {
+            let mut ret = vec![0];
+            if self.ndim == 1 {
+                // Swap west and east.
+                ret.extend((0..n).rev().map(|i| i + 1));
+            } else {
+                // North stays in place.
+                ret.extend((0..n).map(|i| (n - i) % n + 1));
+            }
+            ret
+        };
+        let generators = match (self.ndim, n, name) {
+            (_, _, "none") => vec![],
+            (1, _, "reflect") => vec![reflect()],
+            (2, 4, "rotate4") | (2, 6, "rotate6") | (2, 8, "rotate8") => vec![rotate(1)],
+            (2, 4, "rotate4reflect") | (2, 6, "rotate6reflect") | (2, 8, "rotate8reflect") => {
+                vec![rotate(1), reflect()]
+            }
+            (2, 8, "rotate4") => vec![rotate(2)],
+            (2, 8, "rotate4reflect") => vec![rotate(2), reflect()],
+            (2, 6, "rotate2") => vec![rotate(3)],
+            (2, 6, "rotate3") => vec![rotate(2)],
+            (2, 4, "reflect_horizontal") | (2, 8, "reflect_horizontal") => vec![reflect()],
+            _ => return None,
+        };
+        // Find every combination of the generators.
+        let mut ret = vec![(0..=n).collect::<Vec<_>>()];
+        let mut i = 0;
+        while i < ret.len() {
+            for generator in &generators {
+                let next: Vec<usize> = generator.iter().map(|&j| ret[i][j]).collect();
+                if !ret.contains(&next) {
+                    ret.push(next);
+                }
+            }
+            i += 1;
+        }
+        Some(ret)
+    }
+}
+
+/// Parses the `@TABLE` section of a rule file.
+fn parse_table<D: Dim>(name: String, section: TokenPair) -> Result<rule::NdTable<D>, String> {
+    let mut n_states: Option<usize> = None;
+    let mut neighborhood: Option<TableNeighborhood> = None;
+    let mut symmetries = "none".to_owned();
+    let mut vars: HashMap<&str, Vec<u8>> = HashMap::new();
+    // The transitions, as the set of allowed states for each cell and the
+    // next state.
+    let mut transitions: Vec<Transition> = vec![];
+    // Whether the transitions have started, after which the directives cannot
+    // be changed.
+    let mut started = false;
+
+    for line in section.into_inner() {
+        match line.as_rule() {
+            Rule::directive => {
+                let mut inner = line.clone().into_inner();
+                let key = inner.next().unwrap().as_str();
+                let value = inner.next().unwrap().as_str();
+                if started {
+                    return Err(error_at(
+                        &line,
+                        format!("'{}' must come before all transitions", key),
+                    ));
+                }
+                match key {
+                    "n_states" => match value.parse() {
+                        Ok(n @ 2..=256) => n_states = Some(n),
+                        _ => return Err(error_at(&line, "n_states must be from 2 to 256")),
+                    },
+                    "neighborhood" => {
+                        let nbhd = TableNeighborhood::from_name(value).ok_or_else(|| {
+                            error_at(&line, format!("Unknown neighborhood '{}'", value))
+                        })?;
+                        if nbhd.ndim != D::NDIM {
+                            return Err(error_at(
+                                &line,
+                                format!(
+                                    "Neighborhood '{}' is {}D, but the automaton is {}D",
+                                    value,
+                                    nbhd.ndim,
+                                    D::NDIM,
+                                ),
+                            ));
+                        }
+                        neighborhood = Some(nbhd);
+                    }
+                    "symmetries" => symmetries = value.to_owned(),
+                    _ => unreachable!(),
+                }
+            }
+            Rule::var => {
+                let n_states = n_states
+                    .ok_or_else(|| error_at(&line, "n_states must come before variables"))?;
+                let mut inner = line.into_inner();
+                let var_name = inner.next().unwrap().as_str();
+                let mut values = vec![];
+                for term in inner {
+                    values.extend(
+                        resolve_term(term.as_str(), n_states, &vars)
+                            .map_err(|e| error_at(&term, e))?,
+                    );
+                }
+                values.sort();
+                values.dedup();
+                vars.insert(var_name, values);
+            }
+            Rule::transition => {
+                started = true;
+                let n_states = n_states
+                    .ok_or_else(|| error_at(&line, "n_states must come before transitions"))?;
+                let nbhd = neighborhood
+                    .as_ref()
+                    .ok_or_else(|| error_at(&line, "neighborhood must come before transitions"))?;
+                let symmetry_perms = if symmetries == "permute" {
+                    None
+                } else {
+                    Some(nbhd.symmetries(&symmetries).ok_or_else(|| {
+                        error_at(
+                            &line,
+                            format!("Invalid symmetries '{}' for this neighborhood", symmetries),
+                        )
+                    })?)
+                };
+                let expanded = parse_transition(&line, nbhd.cells.len(), n_states, &vars)?;
+                for (config, next) in expanded {
+                    let configs = match &symmetry_perms {
+                        Some(perms) => perms
+                            .iter()
+                            .map(|perm| perm.iter().map(|&i| config[i].clone()).collect())
+                            .collect(),
+                        None => permutations(config),
+                    };
+                    transitions.extend(configs.into_iter().map(|config| (config, next)));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let nbhd = neighborhood.ok_or("Missing neighborhood in @TABLE")?;
+    let cells = nbhd
+        .cells
+        .iter()
+        .map(|pos| IVec::from_fn(|ax| pos[ax as usize]))
+        .collect();
+    // Remove duplicates created by symmetries.
+    let mut seen = HashSet::new();
+    transitions.retain(|transition| seen.insert(transition.clone()));
+    let table = rule::NdTable::new(name, rule::Neighborhood::Custom(cells), transitions);
+    Ok(match n_states {
+        Some(n) => table.with_info(rule::RuleInfo::new(n)),
+        None => table,
+    })
+}
+
+/// Returns the set of states that a term (a state or the name of a variable)
+/// represents.
+fn resolve_term(
+    s: &str,
+    n_states: usize,
+    vars: &HashMap<&str, Vec<u8>>,
+) -> Result<Vec<u8>, String> {
+    if let Some(values) = vars.get(s) {
+        return Ok(values.clone());
+    }
+    match s.parse::<usize>() {
+        Ok(state) if state < n_states => Ok(vec![state as u8]),
+        Ok(_) => Err(format!("State {} is out of range", s)),
+        Err(_) => Err(format!("Unknown variable '{}'", s)),
+    }
+}
+
+/// Parses a single transition, returning the set of allowed states for each
+/// cell and the next state.
+///
+/// Variables that appear more than once in a transition are "bound": every
+/// occurrence must have the same value. The transition is expanded into one
+/// for each combination of values of its bound variables.
+fn parse_transition(
+    line: &TokenPair,
+    cell_count: usize,
+    n_states: usize,
+    vars: &HashMap<&str, Vec<u8>>,
+) -> Result<Vec<Transition>, String> {
+    let terms: Vec<TokenPair> = line.clone().into_inner().collect();
+    // Allow compact transitions such as `012345`.
+    let term_strings: Vec<String> = match terms.as_slice() {
+        [term] if term.as_str().chars().all(|ch| ch.is_ascii_digit()) => {
+            term.as_str().chars().map(|ch| ch.to_string()).collect()
+        }
+        _ => terms.iter().map(|term| term.as_str().to_owned()).collect(),
+    };
+    if term_strings.len() != cell_count + 1 {
+        return Err(error_at(
+            line,
+            format!(
+                "Expected {} states in transition; got {}",
+                cell_count + 1,
+                term_strings.len(),
+            ),
+        ));
+    }
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    for s in &term_strings {
+        if vars.contains_key(s.as_str()) {
+            *occurrences.entry(s).or_default() += 1;
+        }
+    }
+    let output = term_strings.last().unwrap();
+    if vars.contains_key(output.as_str()) && occurrences[output.as_str()] < 2 {
+        return Err(error_at(
+            line,
+            format!("Output variable '{}' does not appear in the inputs", output),
+        ));
+    }
+    let mut bound: Vec<&str> = occurrences
+        .into_iter()
+        .filter(|&(_, count)| count > 1)
+        .map(|(var, _)| var)
+        .collect();
+    bound.sort();
+
+    // Resolve each term to a set of states, with the values of the bound
+    // variables given by `binding`.
+    let resolve = |s: &str, binding: &[u8]| -> Result<Vec<u8>, String> {
+        match bound.iter().position(|&var| var == s) {
+            Some(i) => Ok(vec![binding[i]]),
+            None => resolve_term(s, n_states, vars).map_err(|e| error_at(line, e)),
+        }
+    };
+
+    // Expand every combination of values of bound variables.
+    let mut bindings: Vec<Vec<u8>> = vec![vec![]];
+    for var in &bound {
+        bindings = bindings
+            .into_iter()
+            .flat_map(|binding| {
+                vars[var].iter().map(move |&value| {
+                    let mut binding = binding.clone();
+                    binding.push(value);
+                    binding
+                })
+            })
+            .collect();
+    }
+    let mut ret = vec![];
+    for binding in bindings {
+        let mut config = term_strings
+            .iter()
+            .map(|s| resolve(s, &binding))
+            .collect::<Result<Vec<_>, _>>()?;
+        let output = config.pop().unwrap();
+        if output.len() != 1 {
+            return Err(error_at(line, "Output must be a single state"));
+        }
+        ret.push((config, output[0]));
+    }
+    Ok(ret)
+}
+
+/// Parses the `@TREE` section of a rule file.
+fn parse_tree<D: Dim>(name: String, section: TokenPair) -> Result<rule::NdRuleTree<D>, String> {
+    let mut num_states: Option<usize> = None;
+    let mut num_neighbors: Option<usize> = None;
+    let mut num_nodes: Option<usize> = None;
+    let mut nodes: Vec<(usize, Vec<usize>)> = vec![];
+    let parse_int = |pair: TokenPair| -> Result<usize, String> {
+        pair.as_str()
+            .parse()
+            .map_err(|_| error_at(&pair, "Number is too large"))
+    };
+
+    for line in section.clone().into_inner() {
+        match line.as_rule() {
+            Rule::tree_param => {
+                let mut inner = line.clone().into_inner();
+                let key = inner.next().unwrap().as_str();
+                let value = parse_int(inner.next().unwrap())?;
+                if !nodes.is_empty() {
+                    return Err(error_at(
+                        &line,
+                        format!("'{}' must come before all nodes", key),
+                    ));
+                }
+                match key {
+                    "num_states" if (2..=256).contains(&value) => num_states = Some(value),
+                    "num_states" => {
+                        return Err(error_at(&line, "num_states must be from 2 to 256"));
+                    }
+                    "num_neighbors" if value == 4 || value == 8 => num_neighbors = Some(value),
+                    "num_neighbors" => return Err(error_at(&line, "num_neighbors must be 4 or 8")),
+                    "num_nodes" => num_nodes = Some(value),
+                    _ => unreachable!(),
+                }
+            }
+            Rule::tree_node => {
+                let num_states = num_states
+                    .ok_or_else(|| error_at(&line, "num_states must come before nodes"))?;
+                let num_neighbors = num_neighbors
+                    .ok_or_else(|| error_at(&line, "num_neighbors must come before nodes"))?;
+                let mut values = line
+                    .clone()
+                    .into_inner()
+                    .map(parse_int)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let level = values.remove(0);
+                if values.len() != num_states {
+                    return Err(error_at(
+                        &line,
+                        format!("Expected {} children; got {}", num_states, values.len()),
+                    ));
+                }
+                if level < 1 || level > num_neighbors + 1 {
+                    return Err(error_at(&line, format!("Invalid node level {}", level)));
+                }
+                for &child in &values {
+                    if level == 1 && child >= num_states {
+                        return Err(error_at(&line, format!("State {} is out of range", child)));
+                    }
+                    if level > 1 && nodes.get(child).map(|node| node.0) != Some(level - 1) {
+                        return Err(error_at(
+                            &line,
+                            format!("Node {} is not a valid child of this node", child),
+                        ));
+                    }
+                }
+                nodes.push((level, values));
+            }
+            _ => (),
+        }
+    }
+
+    let num_states = num_states.ok_or_else(|| error_at(&section, "Missing num_states"))?;
+    let num_neighbors = num_neighbors.ok_or_else(|| error_at(&section, "Missing num_neighbors"))?;
+    let num_nodes = num_nodes.ok_or_else(|| error_at(&section, "Missing num_nodes"))?;
+    if nodes.len() != num_nodes {
+        return Err(error_at(
+            &section,
+            format!("Expected {} nodes; got {}", num_nodes, nodes.len()),
+        ));
+    }
+    if nodes.last().map(|node| node.0) != Some(num_neighbors + 1) {
+        return Err(error_at(&section, "Last node must be the root of the tree"));
+    }
+    if D::NDIM != 2 {
+        return Err(error_at(
+            &section,
+            format!("Rule trees are 2D, but the automaton is {}D", D::NDIM),
+        ));
+    }
+    // The cells in the order that they are checked, starting from the root.
+    let cells: &[[isize; 2]] = match num_neighbors {
+        // N, W, E, S, C
+        4 => &[[0, 1], [-1, 0], [1, 0], [0, -1], [0, 0]],
+        // NW, NE, SW, SE, N, W, E, S, C
+        _ => &[
+            [-1, 1],
+            [1, 1],
+            [-1, -1],
+            [1, -1],
+            [0, 1],
+            [-1, 0],
+            [1, 0],
+            [0, -1],
+            [0, 0],
+        ],
+    };
+    let cells = cells
+        .iter()
+        .map(|pos| IVec::from_fn(|ax| pos[ax as usize]))
+        .collect();
+    Ok(rule::NdRuleTree::new(
+        name,
+        num_states,
+        rule::Neighborhood::Custom(cells),
+        &nodes,
+    ))
+}
+
+/// Fills in the colors and icons of a rule from its `@COLORS` and `@ICONS`
+/// sections.
+fn parse_info(
+    mut info: rule::RuleInfo,
+    colors: Option<TokenPair>,
+    icons: Option<TokenPair>,
+) -> Result<rule::RuleInfo, String> {
+    if let Some(section) = colors {
+        for line in section.into_inner() {
+            let values: Vec<usize> = line
+                .clone()
+                .into_inner()
+                .map(|int| int.as_str().parse().unwrap_or(usize::MAX))
+                .collect();
+            let color = |rgb: &[usize]| -> Result<rule::Color, String> {
+                match rgb {
+                    &[r, g, b] if r < 256 && g < 256 && b < 256 => {
+                        Ok([r as u8, g as u8, b as u8, 255])
+                    }
+                    _ => Err(error_at(&line, "Color components must be from 0 to 255")),
+                }
+            };
+            match values.len() {
+                // A single state.
+                4 => {
+                    let color = color(&values[1..])?;
+                    info.states
+                        .get_mut(values[0])
+                        .ok_or_else(|| {
+                            error_at(&line, format!("State {} is out of range", values[0]))
+                        })?
+                        .color = color;
+                }
+                // A gradient across all live states.
+                6 => info.set_gradient(color(&values[..3])?, color(&values[3..])?),
+                _ => {
+                    return Err(error_at(
+                        &line,
+                        "Expected a state and its color, or two colors for a gradient",
+                    ))
+                }
+            }
+        }
+    }
+
+    if let Some(section) = icons {
+        let mut strings = vec![];
+        for item in section.into_inner() {
+            match item.as_rule() {
+                Rule::xpm_string => strings.push(item),
+                // Triangle icons point up or down depending on the position of
+                // each cell, which an icon cannot express, so those cells are
+                // drawn as solid squares.
+                Rule::icon_style if item.as_str() == "triangles" => (),
+                Rule::icon_style => {
+                    for &size in &[7, 15, 31] {
+                        for state in info.states.iter_mut().skip(1) {
+                            let icon = builtin_icon(item.as_str(), size, state.color)
+                                .ok_or_else(|| error_at(&item, "Unsupported icon style"))?;
+                            state.icons.push(icon);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        let mut strings = strings.into_iter();
+        while let Some(header) = strings.next() {
+            // Icons are given for every state except #0.
+            let icons = parse_xpm(header, &mut strings)?;
+            for (state, icon) in info.states.iter_mut().skip(1).zip(icons) {
+                state.icons.push(icon);
+            }
+        }
+        for state in &mut info.states {
+            state.icons.sort_by_key(|icon| icon.size);
+        }
+    }
+
+    Ok(info)
+}
+
+/// Parses an XPM image given its header string and the strings following it,
+/// and splits it into square icons from top to bottom.
+fn parse_xpm<'a>(
+    header: TokenPair<'a>,
+    strings: &mut impl Iterator<Item = TokenPair<'a>>,
+) -> Result<Vec<rule::Icon>, String> {
+    let contents = |pair: &TokenPair<'a>| {
+        let s = pair.as_str();
+        &s[1..s.len() - 1]
+    };
+    let params: Vec<usize> = contents(&header)
+        .split_whitespace()
+        .map(|param| param.parse().unwrap_or(0))
+        .collect();
+    let (width, height, color_count, chars_per_pixel) = match params[..] {
+        [w, h, n, c] if w > 0 && c > 0 => (w, h, n, c),
+        _ => {
+            return Err(error_at(
+                &header,
+                "Expected XPM width, height, number of colors, and characters per pixel",
+            ))
+        }
+    };
+    if height % width != 0 {
+        return Err(error_at(
+            &header,
+            "XPM height must be a multiple of its width",
+        ));
+    }
+
+    let mut colors: HashMap<&str, rule::Color> = HashMap::new();
+    for _ in 0..color_count {
+        let line = strings
+            .next()
+            .ok_or_else(|| error_at(&header, "Missing XPM colors"))?;
+        let s = contents(&line);
+        let key = s
+            .get(..chars_per_pixel)
+            .ok_or_else(|| error_at(&line, "Invalid XPM color"))?;
+        let value = match s[chars_per_pixel..].split_whitespace().collect::<Vec<_>>()[..] {
+            ["c", value] => value,
+            _ => return Err(error_at(&line, "Invalid XPM color")),
+        };
+        let rgb = value
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+        let color = match rgb {
+            Some(rgb) => [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255],
+            None if value.eq_ignore_ascii_case("none") => [0, 0, 0, 0],
+            None => return Err(error_at(&line, format!("Invalid XPM color '{}'", value))),
+        };
+        colors.insert(key, color);
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for _ in 0..height {
+        let row = strings
+            .next()
+            .ok_or_else(|| error_at(&header, "Missing XPM rows"))?;
+        let s = contents(&row);
+        if s.len() != width * chars_per_pixel {
+            return Err(error_at(&row, format!("Expected {} pixels", width)));
+        }
+        for x in 0..width {
+            let key = s.get(x * chars_per_pixel..(x + 1) * chars_per_pixel);
+            pixels.push(
+                *key.and_then(|key| colors.get(key))
+                    .ok_or_else(|| error_at(&row, "Unknown XPM color"))?,
+            );
+        }
+    }
+    Ok(pixels
+        .chunks(width * width)
+        .map(|pixels| rule::Icon {
+            size: width,
+            pixels: pixels.to_vec(),
+        })
+        .collect())
+}
+
+/// Returns one of Golly's built-in icons ("circles", "diamonds" or
+/// "hexagons") with the given size and color, or `None` if the style is not
+/// supported.
+fn builtin_icon(style: &str, size: usize, color: rule::Color) -> Option<rule::Icon> {
+    let r = (size / 2) as isize;
+    let contains: fn(isize, isize, isize) -> bool = match style {
+        "circles" => |dx, dy, r| dx * dx + dy * dy <= r * r + r,
+        "diamonds" => |dx, dy, r| dx.abs() + dy.abs() <= r,
+        // The hexagonal neighborhood is skewed so that the top-right and
+        // bottom-left corners are cut off.
+        "hexagons" => |dx, dy, r| (dx - dy).abs() <= r,
+        _ => return None,
+    };
+    let pixels = (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| {
+            if contains(dx, dy, r) {
+                color
+            } else {
+                [0, 0, 0, 0]
+            }
+        })
+        .collect();
+    Some(rule::Icon { size, pixels })
+}
+
+/// Returns every distinct arrangement of the neighbors in a configuration,
+/// keeping the center cell in place.
+fn permutations(config: Vec<Vec<u8>>) -> Vec<Vec<Vec<u8>>> {
+    let mut neighbors = config[1..].to_vec();
+    neighbors.sort();
+    let mut ret = vec![];
+    loop {
+        let mut next = vec![config[0].clone()];
+        next.extend(neighbors.iter().cloned());
+        ret.push(next);
+        // Advance to the next permutation in lexicographic order.
+        let i = match (1..neighbors.len())
+            .rev()
+            .find(|&i| neighbors[i - 1] < neighbors[i])
+        {
+            Some(i) => i,
+            None => return ret,
+        };
+        let j = (i..neighbors.len())
+            .rev()
+            .find(|&j| neighbors[i - 1] < neighbors[j])
+            .unwrap();
+        neighbors.swap(i - 1, j);
+        neighbors[i..].reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the symmetries of each neighborhood.
+    #[test]
+    fn test_table_symmetries() {
+        let count = |nbhd: &str, symmetries: &str| {
+            TableNeighborhood::from_name(nbhd)
+                .unwrap()
+                .symmetries(symmetries)
+                .map(|perms| perms.len())
+        };
+        assert_eq!(Some(1), count("Moore", "none"));
+        assert_eq!(Some(4), count("Moore", "rotate4"));
+        assert_eq!(Some(8), count("Moore", "rotate8"));
+        assert_eq!(Some(8), count("Moore", "rotate4reflect"));
+        assert_eq!(Some(16), count("Moore", "rotate8reflect"));
+        assert_eq!(Some(2), count("Moore", "reflect_horizontal"));
+        assert_eq!(Some(4), count("vonNeumann", "rotate4"));
+        assert_eq!(Some(8), count("vonNeumann", "rotate4reflect"));
+        assert_eq!(Some(2), count("hexagonal", "rotate2"));
+        assert_eq!(Some(3), count("hexagonal", "rotate3"));
+        assert_eq!(Some(12), count("hexagonal", "rotate6reflect"));
+        assert_eq!(Some(2), count("oneDimensional", "reflect"));
+        assert_eq!(None, count("vonNeumann", "rotate8"));
+        assert_eq!(None, count("oneDimensional", "rotate4"));
+
+        // Reflecting the Moore neighborhood swaps east and west.
+        let perms = TableNeighborhood::from_name("Moore")
+            .unwrap()
+            .symmetries("reflect_horizontal")
+            .unwrap();
+        assert_eq!(vec![0, 1, 8, 7, 6, 5, 4, 3, 2], perms[1]);
+    }
+
+    /// Tests that `permute` generates each distinct arrangement once.
+    #[test]
+    fn test_table_permutations() {
+        let config = vec![vec![0], vec![1], vec![0], vec![1], vec![0]];
+        assert_eq!(6, permutations(config).len());
+        let config = vec![vec![0], vec![0, 1], vec![0, 1], vec![0, 1]];
+        assert_eq!(1, permutations(config).len());
+    }
+
+    /// Tests that parse errors include line numbers.
+    #[test]
+    fn test_table_errors() {
+        let load = |s: &str| load_rule::<Dim2D>(s).map(|_| ());
+        let expect_error = |s: &str, line: usize, message: &str| {
+            let e = load(s).unwrap_err();
+            assert!(e.contains(&format!("--> {}:", line)), "{}", e);
+            assert!(e.contains(message), "{}", e);
+        };
+        let header = "@RULE Test\n@TABLE\nn_states:3\nneighborhood:vonNeumann\n";
+        assert!(load(&format!("{}0,1,2,0,0,1\n", header)).is_ok());
+        expect_error(
+            &format!("{}0,1,2,0,0,1\n0,1,2,0,1\n", header),
+            6,
+            "Expected 6 states",
+        );
+        expect_error(&format!("{}0,1,2,0,0,3\n", header), 5, "out of range");
+        expect_error(&format!("{}0,1,x,0,0,1\n", header), 5, "Unknown variable");
+        expect_error(
+            &format!("{}var a={{0,1}}\n\n0,a,0,0,0,a\n0,1,0,0,0,a\n", header),
+            8,
+            "Output variable",
+        );
+        expect_error(
+            &format!("{}symmetries:rotate8\n0,1,2,0,0,1\n", header),
+            6,
+            "Invalid symmetries",
+        );
+        expect_error(
+            "@RULE Test\n@TABLE\nn_states:3\nneighborhood:oneDimensional\n",
+            4,
+            "1D",
+        );
+        expect_error(
+            "@RULE Test\n@TABLE\nn_states:3\nneighborhood:Moore\n0,1,2:3\n",
+            5,
+            "",
+        );
+        assert!(load("@RULE Test\n@TREE\n").is_err());
+    }
+
+    /// Tests loading colors and icons.
+    #[test]
+    fn test_colors_and_icons() {
+        let rule = load_rule::<Dim2D>(
+            "@RULE Test
+@TABLE
+n_states:4
+neighborhood:vonNeumann
+0,1,0,0,0,1
+
+@COLORS
+0 48 48 48  # background
+255 0 0 0 0 255
+3 0,255,0
+
+@ICONS
+XPM
+/* width height num_colors chars_per_pixel */
+\"3 9 2 1\"
+/* colors */
+\". c #000000\"
+\"A c #FFFFFF\"
+/* icon for state 1 */
+\"A.A\"
+\".A.\"
+\"A.A\"
+/* icon for state 2 */
+\"...\"
+\".A.\"
+\"...\"
+/* icon for state 3 */
+\"AAA\"
+\"A.A\"
+\"AAA\"
+",
+        )
+        .unwrap();
+        let info = rule.info();
+        assert_eq!(4, info.state_count());
+        assert_eq!([48, 48, 48, 255], info.color(0));
+        assert_eq!([255, 0, 0, 255], info.color(1));
+        assert_eq!([128, 0, 128, 255], info.color(2));
+        assert_eq!([0, 255, 0, 255], info.color(3));
+        assert!(info.states[0].icons.is_empty());
+        let icon = &info.states[2].icons[0];
+        assert_eq!(3, icon.size);
+        assert_eq!([0, 0, 0, 255], icon.pixels[0]);
+        assert_eq!([255, 255, 255, 255], icon.pixels[4]);
+
+        // Built-in icons are generated in several sizes.
+        let rule = load_rule::<Dim2D>(
+            "@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\n@ICONS\ncircles\n",
+        )
+        .unwrap();
+        let icons = &rule.info().states[1].icons;
+        assert_eq!(
+            vec![7, 15, 31],
+            icons.iter().map(|icon| icon.size).collect::<Vec<_>>()
+        );
+        assert_eq!([0, 0, 0, 0], icons[0].pixels[0]);
+        assert_eq!([255, 255, 255, 255], icons[0].pixels[3]);
+        // Triangle icons fall back to solid colors.
+        let rule = load_rule::<Dim2D>(
+            "@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\n@ICONS\ntriangles\n",
+        )
+        .unwrap();
+        assert!(rule.info().states[1].icons.is_empty());
+
+        let load = |s: &str| {
+            load_rule::<Dim2D>(&format!(
+                "@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\n{}",
+                s
+            ))
+        };
+        assert!(load("@COLORS\n2 0 0 0\n")
+            .unwrap_err()
+            .contains("out of range"));
+        assert!(load("@COLORS\n1 0 0 256\n")
+            .unwrap_err()
+            .contains("0 to 255"));
+        assert!(
+            load("@ICONS\nXPM\n\"2 2 1 1\"\n\"A c #FFFFFF\"\n\"AA\"\n\"AB\"\n")
+                .unwrap_err()
+                .contains("Unknown XPM color")
+        );
+    }
+}
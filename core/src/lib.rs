@@ -27,7 +27,7 @@ pub use io::*;
 pub use ndsimulate::*;
 pub use projection::*;
 pub use rle::RleEncode;
-pub use rule::{DummyRule, Rule, TransitionFunction};
+pub use rule::{DummyRule, LifeLike, Rule, RuleInfo, TransitionFunction};
 pub use simulation::*;
 pub use space::*;
 
@@ -43,6 +43,8 @@ pub trait NdProjectedAutomatonTrait<P: Dim> {
     fn set_projection_params(&mut self, params: ProjectionParams) -> Result<(), NdProjectionError>;
     /// Sets a cell using projected coordinates.
     fn set_cell(&mut self, pos: &BigVec<P>, state: u8);
+    /// Returns information about the cell states of the automaton's rule.
+    fn get_rule_info(&self) -> RuleInfo;
 }
 
 /// A cellular automaton projected to 1D.
@@ -192,23 +194,35 @@ impl<D: Dim, P: Dim> NdProjectedAutomatonTrait<P> for NdProjectedAutomaton<D, P>
             .tree
             .set_cell(&self.projection.unproject_pos(pos), state);
     }
+    fn get_rule_info(&self) -> RuleInfo {
+        self.automaton.sim.lock().unwrap().rule().info()
+    }
 }
 
-/// A fully-fledged cellular automaton, including a grid (NdTree), rule
-/// (Simulation), and generation count.
+/// A fully-fledged cellular automaton, including a grid (NdTree), rule and
+/// simulation algorithm (Algorithm), and generation count.
 #[allow(missing_docs)]
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct NdAutomaton<D: Dim> {
     pub tree: NdTree<u8, D>,
-    pub sim: Arc<Mutex<Simulation<u8, D>>>,
+    pub sim: Arc<Mutex<Box<dyn Algorithm<u8, D>>>>,
     pub generations: BigInt,
 }
+impl<D: Dim> Default for NdAutomaton<D> {
+    fn default() -> Self {
+        Self {
+            tree: NdTree::default(),
+            sim: Arc::new(Mutex::new(Box::new(Simulation::default()))),
+            generations: BigInt::default(),
+        }
+    }
+}
 impl<D: Dim> NdSimulate for NdAutomaton<D> {
     fn get_ndim(&self) -> usize {
         D::NDIM
     }
-    fn get_population(&self) -> &BigInt {
-        &self.tree.get_root().population
+    fn get_population(&self) -> BigInt {
+        self.tree.get_population()
     }
     fn get_generation_count(&self) -> &BigInt {
         &self.generations
@@ -216,15 +230,45 @@ impl<D: Dim> NdSimulate for NdAutomaton<D> {
     fn set_generation_count(&mut self, generations: BigInt) {
         self.generations = generations;
     }
+    fn get_algorithm(&self) -> AlgorithmKind {
+        self.sim.lock().unwrap().kind()
+    }
+    fn set_algorithm(&mut self, algorithm: AlgorithmKind) {
+        if algorithm == self.get_algorithm() {
+            return;
+        }
+        let sim = self.sim.lock().unwrap();
+        let mut new_sim = algorithm.new_algorithm(sim.rule().clone(), sim.topology().clone());
+        new_sim.set_memory_limit(sim.memory_limit());
+        drop(sim);
+        self.sim = Arc::new(Mutex::new(new_sim));
+    }
+    fn get_memory_stats(&self) -> MemoryStats {
+        self.sim.lock().unwrap().memory_stats(&self.tree.cache)
+    }
+    fn get_memory_limit(&self) -> Option<usize> {
+        self.sim.lock().unwrap().memory_limit()
+    }
+    fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.sim.lock().unwrap().set_memory_limit(memory_limit);
+    }
     fn step(&mut self, step_size: &BigInt) {
         self.sim.lock().unwrap().step(&mut self.tree, step_size);
         self.generations += step_size;
     }
+    fn try_step(&mut self, step_size: &BigInt, control: &StepControl) -> Result<(), StepError> {
+        self.sim
+            .lock()
+            .unwrap()
+            .try_step(&mut self.tree, step_size, control)?;
+        self.generations += step_size;
+        Ok(())
+    }
 }
 impl<D: Dim> NdAutomaton<D> {
     /// Sets the simulation of this automaton.
-    pub fn set_sim(&mut self, new_sim: Simulation<u8, D>) {
-        self.sim = Arc::new(Mutex::new(new_sim));
+    pub fn set_sim(&mut self, new_sim: impl 'static + Algorithm<u8, D>) {
+        self.sim = Arc::new(Mutex::new(Box::new(new_sim)));
     }
 }
 
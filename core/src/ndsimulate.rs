@@ -1,18 +1,39 @@
 use num::BigInt;
 
+use super::{AlgorithmKind, MemoryStats, StepControl, StepError};
+
 /// Simulation-related methods whose type signatures are the same for all
 /// automata, regardless of dimensionality.
 pub trait NdSimulate {
     /// Returns the number of dimensions of the underlying automaton.
     fn get_ndim(&self) -> usize;
-    /// Returns the number of live cells in the simulation.
-    fn get_population(&self) -> &BigInt;
+    /// Returns the number of cells in the simulation whose state is different
+    /// from the background.
+    fn get_population(&self) -> BigInt;
     /// Returns the number of generations that have elapsed in the simulation.
     fn get_generation_count(&self) -> &BigInt;
     /// Sets the number of generations that have elapsed in the simulation.
     fn set_generation_count(&mut self, generations: BigInt);
+    /// Returns the algorithm used to simulate the automaton.
+    fn get_algorithm(&self) -> AlgorithmKind;
+    /// Switches to a different simulation algorithm, keeping the same pattern,
+    /// rule, topology, and memory limit.
+    fn set_algorithm(&mut self, algorithm: AlgorithmKind);
+    /// Returns statistics about the memory used by the simulation.
+    fn get_memory_stats(&self) -> MemoryStats;
+    /// Returns the estimated number of bytes of memory beyond which garbage is
+    /// collected after each step, or None if there is no limit.
+    fn get_memory_limit(&self) -> Option<usize>;
+    /// Sets the estimated number of bytes of memory beyond which garbage is
+    /// collected after each step, or None for no limit.
+    fn set_memory_limit(&mut self, memory_limit: Option<usize>);
     /// Steps forward in the simulation by the given number of generations.
     fn step(&mut self, step_size: &BigInt);
+    /// Steps forward in the simulation by the given number of generations,
+    /// stopping early if the step is cancelled using `control` or the pattern
+    /// is too large for the algorithm. If the step does not finish, nothing is
+    /// changed.
+    fn try_step(&mut self, step_size: &BigInt, control: &StepControl) -> Result<(), StepError>;
 }
 
 /// A proxy trait for NdSimulate.
@@ -34,7 +55,7 @@ where
     fn get_ndim(&self) -> usize {
         self.ndsim().get_ndim()
     }
-    fn get_population(&self) -> &BigInt {
+    fn get_population(&self) -> BigInt {
         self.ndsim().get_population()
     }
     fn get_generation_count(&self) -> &BigInt {
@@ -43,7 +64,25 @@ where
     fn set_generation_count(&mut self, generations: BigInt) {
         self.ndsim_mut().set_generation_count(generations);
     }
+    fn get_algorithm(&self) -> AlgorithmKind {
+        self.ndsim().get_algorithm()
+    }
+    fn set_algorithm(&mut self, algorithm: AlgorithmKind) {
+        self.ndsim_mut().set_algorithm(algorithm);
+    }
+    fn get_memory_stats(&self) -> MemoryStats {
+        self.ndsim().get_memory_stats()
+    }
+    fn get_memory_limit(&self) -> Option<usize> {
+        self.ndsim().get_memory_limit()
+    }
+    fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.ndsim_mut().set_memory_limit(memory_limit);
+    }
     fn step(&mut self, step_size: &BigInt) {
         self.ndsim_mut().step(step_size);
     }
+    fn try_step(&mut self, step_size: &BigInt, control: &StepControl) -> Result<(), StepError> {
+        self.ndsim_mut().try_step(step_size, control)
+    }
 }
@@ -43,6 +43,9 @@ impl<C: CellType, D: Dim, P: Dim> NdProjector<C, D, P> for NdProjection<C, D, P>
 /// D-dimensional automaton.
 pub trait NdProjector<C: CellType, D: Dim, P: Dim>: Send {
     /// Projects a D-dimensional NdTree into a P-dimensional NdTree.
+    ///
+    /// The projected tree shares the node cache of the original one, if they
+    /// have the same number of dimensions.
     fn project(&self, tree: &NdTree<C, D>) -> NdTree<C, P>;
     /// Unprojects a P-dimensional point back into D-dimensional space.
     fn unproject_pos(&self, pos: &BigVec<P>) -> BigVec<D>;
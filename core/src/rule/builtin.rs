@@ -0,0 +1,176 @@
+//! Built-in rules that can be looked up by name.
+
+use std::sync::Arc;
+
+use super::*;
+
+/// Rules and rulestring formats that only exist in a particular
+/// dimensionality.
+pub trait DimRules: Dim {
+    /// Returns the built-in rule with the given name (lowercase, without spaces
+    /// or punctuation) that only exists in this dimensionality, if there is
+    /// one.
+    fn builtin_rule(_name: &str) -> Option<Arc<dyn Rule<u8, Self>>> {
+        None
+    }
+    /// Parses a rulestring in a format that only exists in this
+    /// dimensionality, returning `None` if the rulestring does not appear to
+    /// use any such format.
+    fn parse_rulestring(_s: &str) -> Option<Result<Arc<dyn Rule<u8, Self>>, String>> {
+        None
+    }
+}
+
+impl DimRules for Dim1D {
+    fn builtin_rule(name: &str) -> Option<Arc<dyn Rule<u8, Self>>> {
+        match name {
+            "rule110" => Some(Arc::new(Wolfram1D::elementary(110))),
+            _ => None,
+        }
+    }
+    fn parse_rulestring(s: &str) -> Option<Result<Arc<dyn Rule<u8, Self>>, String>> {
+        match Wolfram1D::try_from(s) {
+            Ok(rule) => Some(Ok(Arc::new(rule))),
+            // Only report errors from the Wolfram code parser if the
+            // rulestring contains a Wolfram code.
+            Err(e) if s.contains(|ch| "WwTt".contains(ch)) => Some(Err(e)),
+            Err(_) => None,
+        }
+    }
+}
+impl DimRules for Dim2D {
+    fn builtin_rule(name: &str) -> Option<Arc<dyn Rule<u8, Self>>> {
+        match name {
+            "life" | "conwayslife" | "gameoflife" => Some(Arc::new(LIFE)),
+            "briansbrain" => Some(Arc::new(
+                Totalistic2D::try_from("B2/S/C3").expect("Invalid built-in rule"),
+            )),
+            "langtonsloops" => Some(Arc::new(langtons_loops())),
+            _ => None,
+        }
+    }
+    fn parse_rulestring(s: &str) -> Option<Result<Arc<dyn Rule<u8, Self>>, String>> {
+        match Isotropic2D::try_from(s) {
+            Ok(rule) => Some(Ok(Arc::new(rule))),
+            // Only report errors from the isotropic rule parser if the
+            // rulestring uses letters from Hensel notation.
+            Err(e) if s.contains(|ch: char| "cekainyqjrtwz".contains(ch)) => Some(Err(e)),
+            Err(_) => None,
+        }
+    }
+}
+impl DimRules for Dim3D {}
+impl DimRules for Dim4D {}
+impl DimRules for Dim5D {}
+impl DimRules for Dim6D {}
+
+/// Returns the built-in rule with the given name, if there is one with this
+/// dimensionality.
+///
+/// Names are case-insensitive, and spaces and punctuation are ignored, so
+/// "Langton's Loops" and "Langtons-Loops" refer to the same rule. Some rules
+/// (such as Wireworld) exist in any number of dimensions.
+pub fn by_name<D: DimRules>(name: &str) -> Option<Arc<dyn Rule<u8, D>>> {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|ch| ch.to_ascii_lowercase())
+        .collect();
+    match &*name {
+        "wireworld" => Some(Arc::new(Wireworld::<D>::default())),
+        _ => D::builtin_rule(&name),
+    }
+}
+
+/// Transitions for Langton's Loops, from Langton's 1984 paper. Each
+/// transition lists the states of the center, top, right, bottom, and left
+/// cells, followed by the next state of the center cell. All four rotations
+/// of each transition apply.
+const LANGTONS_LOOPS_TRANSITIONS: &str = "
+    000000 000012 000020 000030 000050 000063 000071 000112 000122 000132
+    000212 000220 000230 000262 000272 000320 000525 000622 000722 001022
+    001120 002020 002030 002050 002125 002220 002322 005222 012321 012421
+    012525 012621 012721 012751 014221 014321 014421 014721 016251 017221
+    017255 017521 017621 017721 025271 100011 100061 100077 100111 100121
+    100211 100244 100277 100511 101011 101111 101244 101277 102026 102121
+    102211 102244 102263 102277 102327 102424 102626 102644 102677 102710
+    102727 105427 111121 111221 111244 111251 111261 111277 111522 112121
+    112221 112244 112251 112277 112321 112424 112621 112727 113221 122244
+    122277 122434 122547 123244 123277 124255 124267 125275 200012 200022
+    200042 200071 200122 200152 200212 200222 200232 200242 200250 200262
+    200272 200326 200423 200517 200522 200575 200722 201022 201122 201222
+    201422 201722 202022 202032 202052 202073 202122 202152 202212 202222
+    202272 202321 202422 202452 202520 202552 202622 202722 203122 203216
+    203226 203422 204222 205122 205212 205222 205521 205725 206222 206722
+    207122 207222 207422 207722 211222 211261 212222 212242 212262 212272
+    214222 215222 216222 217222 222272 222442 222462 222762 222772 300013
+    300022 300041 300076 300123 300421 300622 301021 301220 302511 401120
+    401220 401250 402120 402221 402326 402520 403221 500022 500215 500225
+    500232 500272 500520 502022 502122 502152 502220 502244 502722 512122
+    512220 512422 512722 600011 600021 602120 612125 612131 612225 700077
+    701120 701220 701250 702120 702221 702251 702321 702525 702720
+";
+
+/// Returns Langton's Loops, an 8-state von Neumann neighborhood rule that
+/// supports self-replicating loops.
+fn langtons_loops() -> NdTable<Dim2D> {
+    // Center, north, east, south, west
+    let neighborhood = [[0, 0], [0, 1], [1, 0], [0, -1], [-1, 0]];
+    let neighborhood = Neighborhood::Custom(neighborhood.iter().map(|&pos| NdVec(pos)).collect());
+    let transitions = LANGTONS_LOOPS_TRANSITIONS
+        .split_whitespace()
+        .flat_map(|transition| {
+            let states: Vec<u8> = transition.bytes().map(|ch| ch - b'0').collect();
+            let center = states[0];
+            let neighbors = states[1..5].to_vec();
+            let next = states[5];
+            (0..4).map(move |rotation| {
+                let mut config = vec![vec![center]];
+                config.extend(
+                    neighbors
+                        .iter()
+                        .cycle()
+                        .skip(rotation)
+                        .take(4)
+                        .map(|&state| vec![state]),
+                );
+                (config, next)
+            })
+        });
+    let mut info = RuleInfo::new(8);
+    let colors = [
+        [0, 0, 0],
+        [0, 0, 255],
+        [255, 0, 0],
+        [0, 255, 0],
+        [255, 255, 0],
+        [255, 0, 255],
+        [255, 255, 255],
+        [0, 255, 255],
+    ];
+    for (state, [r, g, b]) in info.states.iter_mut().zip(&colors) {
+        state.color = [*r, *g, *b, 255];
+    }
+    NdTable::new("Langtons-Loops".to_owned(), neighborhood, transitions).with_info(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that built-in rules are found by name, and only for the correct
+    /// dimensionality.
+    #[test]
+    fn test_by_name() {
+        assert_eq!("B3/S23", by_name::<Dim2D>("Life").unwrap().to_string());
+        assert!(by_name::<Dim3D>("Life").is_none());
+        assert!(by_name::<Dim2D>("Wireworld").is_some());
+        assert!(by_name::<Dim3D>("WireWorld").is_some());
+        assert_eq!("W110", by_name::<Dim1D>("Rule 110").unwrap().to_string());
+        assert!(by_name::<Dim2D>("Rule 110").is_none());
+        let rule = by_name::<Dim2D>("Langton's Loops").unwrap();
+        assert_eq!("Langtons-Loops", rule.to_string());
+        assert!(by_name::<Dim2D>(&rule.to_string()).is_some());
+        assert!(by_name::<Dim2D>("Not a rule").is_none());
+    }
+}
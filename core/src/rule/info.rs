@@ -0,0 +1,169 @@
+use crate::space::*;
+
+/// A color, as red, green, blue and alpha components.
+pub type Color = [u8; 4];
+
+/// The color used for states that a rule does not describe.
+const UNKNOWN_COLOR: Color = [255, 0, 255, 255];
+
+/// Information about the cell states of a rule, used for displaying and saving
+/// patterns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleInfo {
+    /// Information about each state, starting with state #0.
+    pub states: Vec<StateInfo>,
+}
+
+/// Information about a single cell state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateInfo {
+    /// Human-readable name of the state.
+    pub name: String,
+    /// Color used to draw cells in this state.
+    pub color: Color,
+    /// Character used to display cells in this state as text.
+    pub symbol: char,
+    /// Icons used to draw cells in this state when zoomed in, one for each size
+    /// given, from smallest to largest. This may be empty.
+    pub icons: Vec<Icon>,
+}
+
+/// A square image used to draw a single cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    /// Width and height of the icon in pixels.
+    pub size: usize,
+    /// Colors of the pixels in rows from top to bottom, each from left to
+    /// right.
+    pub pixels: Vec<Color>,
+}
+
+impl RuleInfo {
+    /// Returns the default information for a rule with the given number of
+    /// states.
+    ///
+    /// As in Golly, state #0 is black. In a 2-state rule, state #1 is white;
+    /// otherwise the live states form a gradient from red to yellow.
+    pub fn new(state_count: usize) -> Self {
+        let mut ret = Self {
+            states: (0..state_count)
+                .map(|i| StateInfo {
+                    name: match (state_count, i) {
+                        (2, 0) => "Dead".to_owned(),
+                        (2, 1) => "Alive".to_owned(),
+                        _ => format!("State {}", i),
+                    },
+                    color: [0, 0, 0, 255],
+                    symbol: (i as u8).cell_char(),
+                    icons: vec![],
+                })
+                .collect(),
+        };
+        if state_count == 2 {
+            ret.states[1].color = [255, 255, 255, 255];
+        } else {
+            ret.set_gradient([255, 0, 0, 255], [255, 255, 0, 255]);
+        }
+        ret
+    }
+
+    /// Returns the number of states.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+    /// Returns the color of the given state.
+    pub fn color(&self, state: u8) -> Color {
+        self.states
+            .get(state as usize)
+            .map(|info| info.color)
+            .unwrap_or(UNKNOWN_COLOR)
+    }
+    /// Returns the largest icon for the given state that fits in a square of
+    /// the given size, or `None` if there is none.
+    pub fn icon(&self, state: u8, max_size: usize) -> Option<&Icon> {
+        self.states
+            .get(state as usize)?
+            .icons
+            .iter()
+            .rev()
+            .find(|icon| icon.size <= max_size)
+    }
+    /// Returns true if any state has an icon.
+    pub fn has_icons(&self) -> bool {
+        self.states.iter().any(|state| !state.icons.is_empty())
+    }
+    /// Returns the character used to display the given state as text.
+    pub fn symbol(&self, state: u8) -> char {
+        self.states
+            .get(state as usize)
+            .map(|info| info.symbol)
+            .unwrap_or('?')
+    }
+
+    /// Sets the colors of all live states (every state except #0) to a
+    /// gradient between the two given colors.
+    pub fn set_gradient(&mut self, start: Color, end: Color) {
+        let live_states = self.states.len().saturating_sub(1);
+        for (i, state) in self.states.iter_mut().skip(1).enumerate() {
+            let t = if live_states > 1 {
+                i as f64 / (live_states - 1) as f64
+            } else {
+                0.0
+            };
+            for channel in 0..4 {
+                let (a, b) = (start[channel] as f64, end[channel] as f64);
+                state.color[channel] = (a + (b - a) * t).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the default information for rules with different numbers of
+    /// states.
+    #[test]
+    fn test_default_rule_info() {
+        let info = RuleInfo::new(2);
+        assert_eq!("Alive", info.states[1].name);
+        assert_eq!([0, 0, 0, 255], info.color(0));
+        assert_eq!([255, 255, 255, 255], info.color(1));
+        assert_eq!(UNKNOWN_COLOR, info.color(2));
+        assert_eq!(
+            ['.', '#', '?'],
+            [info.symbol(0), info.symbol(1), info.symbol(2)]
+        );
+
+        let info = RuleInfo::new(40);
+        assert_eq!(40, info.state_count());
+        assert_eq!([255, 0, 0, 255], info.color(1));
+        assert_eq!([255, 128, 0, 255], info.color(20));
+        assert_eq!([255, 255, 0, 255], info.color(39));
+        assert_eq!(
+            ['9', 'A', 'a'],
+            [info.symbol(9), info.symbol(10), info.symbol(36)]
+        );
+    }
+
+    /// Tests choosing an icon for the size of a cell.
+    #[test]
+    fn test_icon_size() {
+        let mut info = RuleInfo::new(3);
+        assert!(!info.has_icons());
+        info.states[1].icons = [3, 7]
+            .iter()
+            .map(|&size| Icon {
+                size,
+                pixels: vec![[255; 4]; size * size],
+            })
+            .collect();
+        assert!(info.has_icons());
+        assert_eq!(None, info.icon(1, 2));
+        assert_eq!(Some(3), info.icon(1, 6).map(|icon| icon.size));
+        assert_eq!(Some(7), info.icon(1, 100).map(|icon| icon.size));
+        assert_eq!(None, info.icon(2, 100));
+        assert_eq!(None, info.icon(5, 100));
+    }
+}
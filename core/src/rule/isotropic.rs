@@ -0,0 +1,300 @@
+use super::{Rule, TransitionFunction};
+use crate::space::*;
+use core::fmt;
+use std::convert::TryFrom;
+
+/// The offsets of the eight neighbors in the Moore neighborhood, in clockwise
+/// order starting from north. The neighbor at index `i` corresponds to bit `i`
+/// of a neighborhood configuration.
+const NEIGHBORS: [[isize; 2]; 8] = [
+    [0, 1],   // N
+    [1, 1],   // NE
+    [1, 0],   // E
+    [1, -1],  // SE
+    [0, -1],  // S
+    [-1, -1], // SW
+    [-1, 0],  // W
+    [-1, 1],  // NW
+];
+
+/// A representative configuration for each letter of Hensel notation, grouped
+/// by neighbor count. Each configuration is a list of indices into
+/// `NEIGHBORS`. Configurations with more than four neighbors are the
+/// complements of those with fewer.
+const HENSEL_LETTERS: [&[(char, &[usize])]; 5] = [
+    &[],
+    &[('c', &[1]), ('e', &[0])],
+    &[
+        ('c', &[1, 3]),
+        ('e', &[0, 2]),
+        ('k', &[0, 3]),
+        ('a', &[0, 1]),
+        ('i', &[0, 4]),
+        ('n', &[1, 5]),
+    ],
+    &[
+        ('c', &[1, 3, 5]),
+        ('e', &[0, 2, 4]),
+        ('k', &[0, 2, 5]),
+        ('a', &[0, 1, 2]),
+        ('i', &[0, 1, 7]),
+        ('n', &[0, 1, 3]),
+        ('y', &[0, 3, 5]),
+        ('q', &[0, 1, 5]),
+        ('j', &[0, 1, 6]),
+        ('r', &[0, 1, 4]),
+    ],
+    &[
+        ('c', &[1, 3, 5, 7]),
+        ('e', &[0, 2, 4, 6]),
+        ('k', &[0, 1, 3, 6]),
+        ('a', &[0, 1, 2, 3]),
+        ('i', &[0, 1, 3, 4]),
+        ('n', &[0, 1, 3, 7]),
+        ('y', &[0, 1, 3, 5]),
+        ('q', &[0, 1, 2, 5]),
+        ('j', &[0, 1, 4, 6]),
+        ('r', &[0, 1, 2, 4]),
+        ('t', &[0, 1, 4, 7]),
+        ('w', &[0, 1, 5, 6]),
+        ('z', &[0, 1, 4, 5]),
+    ],
+];
+
+/// Returns the letters of Hensel notation and the set of neighborhood
+/// configurations (as bitmasks) that each one represents, for the given
+/// neighbor count.
+fn hensel_classes(count: usize) -> Vec<(char, Vec<u8>)> {
+    let (letters, complement) = if count <= 4 {
+        (HENSEL_LETTERS[count], false)
+    } else {
+        (HENSEL_LETTERS[8 - count], true)
+    };
+    if letters.is_empty() {
+        // 0 or 8 neighbors
+        return vec![(' ', vec![if complement { 0xFF } else { 0x00 }])];
+    }
+    letters
+        .iter()
+        .map(|&(letter, neighbors)| {
+            let mut mask = neighbors.iter().fold(0_u8, |m, &i| m | 1 << i);
+            if complement {
+                mask = !mask;
+            }
+            (letter, symmetries(mask))
+        })
+        .collect()
+}
+
+/// Returns all the configurations equivalent to the given one under rotation
+/// and reflection.
+fn symmetries(mask: u8) -> Vec<u8> {
+    let mut ret = vec![];
+    for &reflected in &[mask, mask.reverse_bits().rotate_left(1)] {
+        for rotation in 0..4 {
+            // Rotating the neighborhood 90 degrees moves each neighbor two
+            // places clockwise.
+            ret.push(reflected.rotate_left(rotation * 2));
+        }
+    }
+    ret.sort();
+    ret.dedup();
+    ret
+}
+
+/// A 2-state isotropic non-totalistic 2D range-1 Moore-neighborhood rule,
+/// described using Hensel notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Isotropic2D {
+    /// The next state of a dead cell, indexed by neighborhood configuration.
+    birth: [u8; 256],
+    /// The next state of a live cell, indexed by neighborhood configuration.
+    survival: [u8; 256],
+}
+
+impl Isotropic2D {
+    /// Returns true if a dead cell with the given neighborhood configuration
+    /// (as a bitmask; see `NEIGHBORS`) becomes live.
+    fn birth(&self, mask: u8) -> bool {
+        self.birth[mask as usize] != 0
+    }
+    /// Returns true if a live cell with the given neighborhood configuration
+    /// (as a bitmask; see `NEIGHBORS`) stays live.
+    fn survival(&self, mask: u8) -> bool {
+        self.survival[mask as usize] != 0
+    }
+}
+
+impl Rule<u8, Dim2D> for Isotropic2D {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn get_transition_function(&self) -> TransitionFunction<'_, u8, Dim2D> {
+        Box::new(move |napkin| {
+            let mut mask = 0_usize;
+            for (i, &pos) in NEIGHBORS.iter().enumerate() {
+                if napkin[&NdVec(pos)] != 0 {
+                    mask |= 1 << i;
+                }
+            }
+            if napkin[&NdVec::origin()] != 0 {
+                self.survival[mask]
+            } else {
+                self.birth[mask]
+            }
+        })
+    }
+}
+
+impl fmt::Display for Isotropic2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        format_conditions(f, |mask| self.birth(mask))?;
+        write!(f, "/S")?;
+        format_conditions(f, |mask| self.survival(mask))
+    }
+}
+
+/// Writes the Hensel notation for a set of neighborhood configurations,
+/// preferring whichever of the positive or negated form is shorter.
+fn format_conditions(f: &mut fmt::Formatter, condition: impl Fn(u8) -> bool) -> fmt::Result {
+    for count in 0..=8 {
+        let classes = hensel_classes(count);
+        let (present, absent): (Vec<_>, Vec<_>) =
+            classes.iter().partition(|(_, masks)| condition(masks[0]));
+        if present.is_empty() {
+            continue;
+        }
+        write!(f, "{}", count)?;
+        if absent.is_empty() {
+            continue;
+        }
+        if present.len() > absent.len() {
+            write!(f, "-")?;
+            absent
+                .iter()
+                .try_for_each(|(letter, _)| write!(f, "{}", letter))?;
+        } else {
+            present
+                .iter()
+                .try_for_each(|(letter, _)| write!(f, "{}", letter))?;
+        }
+    }
+    Ok(())
+}
+
+impl TryFrom<&str> for Isotropic2D {
+    type Error = String;
+    /// Parses a rulestring in Hensel notation, such as `B2-a/S12` or
+    /// `b2ak3y4jn5jy78s12-k3-cqy4`.
+    fn try_from(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let split_idx = s
+            .char_indices()
+            .skip(1)
+            .find(|&(_, ch)| ch == 'S' || ch == 's' || ch == 'B' || ch == 'b')
+            .map(|(i, _)| i)
+            .ok_or_else(|| format!("Invalid rulestring '{}'", s))?;
+        let (first, second) = s.split_at(split_idx);
+        let first = first.strip_suffix('/').unwrap_or(first);
+        let (birth, survival) = match (first.chars().next(), second.chars().next()) {
+            (Some('B'), Some('S')) | (Some('b'), Some('s')) => (&first[1..], &second[1..]),
+            (Some('S'), Some('B')) | (Some('s'), Some('b')) => (&second[1..], &first[1..]),
+            _ => return Err(format!("Invalid rulestring '{}'", s)),
+        };
+        let birth = parse_conditions(birth)?;
+        let survival = parse_conditions(survival)?;
+        Ok(Self { birth, survival })
+    }
+}
+
+/// Parses the Hensel notation for a set of neighborhood configurations, such
+/// as `2-ak3y4jn5jy78`.
+fn parse_conditions(s: &str) -> Result<[u8; 256], String> {
+    let mut table = [0; 256];
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let count = ch
+            .to_digit(10)
+            .filter(|&n| n <= 8)
+            .ok_or_else(|| format!("Expected neighbor count; got '{}'", ch))?
+            as usize;
+        let classes = hensel_classes(count);
+        let negate = chars.peek() == Some(&'-');
+        if negate {
+            chars.next();
+        }
+        let mut letters = vec![];
+        while let Some(&letter) = chars.peek().filter(|ch| ch.is_ascii_lowercase()) {
+            chars.next();
+            if !classes.iter().any(|&(l, _)| l == letter) {
+                return Err(format!(
+                    "Invalid letter '{}' for neighbor count {}",
+                    letter, count,
+                ));
+            }
+            letters.push(letter);
+        }
+        if negate && letters.is_empty() {
+            return Err(format!("Expected letters after '{}-'", count));
+        }
+        for (letter, masks) in classes {
+            if letters.is_empty() || letters.contains(&letter) != negate {
+                for mask in masks {
+                    table[mask as usize] = 1;
+                }
+            }
+        }
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the letters for each neighbor count partition all the
+    /// configurations with that many neighbors.
+    #[test]
+    fn test_hensel_classes() {
+        let mut seen = [false; 256];
+        for count in 0..=8 {
+            let classes = hensel_classes(count);
+            assert_eq!([1, 2, 6, 10, 13, 10, 6, 2, 1][count], classes.len());
+            for (_, masks) in classes {
+                for mask in masks {
+                    assert_eq!(count as u32, mask.count_ones());
+                    assert!(!seen[mask as usize], "Duplicate configuration");
+                    seen[mask as usize] = true;
+                }
+            }
+        }
+        assert!(seen.iter().all(|&x| x));
+    }
+
+    /// Tests parsing and formatting Hensel notation.
+    #[test]
+    fn test_isotropic_parse() {
+        let rule = Isotropic2D::try_from("B2-ak3y4jn5jy78/S12-k3-cqy4").unwrap();
+        assert_eq!("B2-ka3y4nj5yj78/S12-k3-cyq4", rule.to_string());
+        assert_eq!(
+            rule,
+            Isotropic2D::try_from("b2cein3y4jn5jy78s12acein3aeijknr4").unwrap(),
+        );
+        assert_eq!(
+            rule,
+            Isotropic2D::try_from("S12-k3-cqy4/B2-ak3y4jn5jy78").unwrap(),
+        );
+
+        let rule = Isotropic2D::try_from("B3/S23").unwrap();
+        assert_eq!("B3/S23", rule.to_string());
+        assert_eq!(rule.birth[0b0000_0111], 1);
+        assert_eq!(rule.birth[0b0000_0011], 0);
+        assert_eq!(rule.survival[0b0000_0011], 1);
+
+        // Invalid letter for neighbor count
+        assert!(Isotropic2D::try_from("B2-ak3y4jn5jy78/S12-k2m3-cqy4").is_err());
+        assert!(Isotropic2D::try_from("B1k/S").is_err());
+        assert!(Isotropic2D::try_from("B9/S").is_err());
+    }
+}
@@ -3,20 +3,51 @@
 
 use crate::space::*;
 use core::fmt;
+use std::convert::TryFrom;
+use std::sync::Arc;
 
+mod builtin;
+mod info;
+mod isotropic;
+mod neighborhood;
+mod table;
 mod totalistic;
+mod tree;
+mod wireworld;
+mod wolfram;
 
+pub use builtin::{by_name, DimRules};
+pub use info::*;
+pub use isotropic::*;
+pub use neighborhood::*;
+pub use table::*;
 pub use totalistic::*;
+pub use tree::*;
+pub use wireworld::*;
+pub use wolfram::*;
 
 pub type TransitionFunction<'a, C, D> = Box<dyn 'a + FnMut(NdArrayView<C, D>) -> C>;
 
 /// A cellular automaton rule.
-pub trait Rule<C: CellType, D: Dim>: fmt::Debug + Send + Sync {
+///
+/// The `Display` implementation should produce the rule's name or rulestring,
+/// in a form that `from_rulestring()` can parse.
+pub trait Rule<C: CellType, D: Dim>: fmt::Debug + fmt::Display + Send + Sync {
     /// Returns the maximum distance away that a cell may need to see in order
     /// to compute its next state.
     fn radius(&self) -> usize;
     /// Returns a function that can be used to compute cell transitions.
     fn get_transition_function(&self) -> TransitionFunction<C, D>;
+    /// Returns information about the cell states of this rule, such as their
+    /// names and colors.
+    fn info(&self) -> RuleInfo {
+        RuleInfo::new(2)
+    }
+    /// Returns this rule as a "Life-like" rule, if it is one, so that it can
+    /// be simulated using bitwise operations on many cells at once.
+    fn as_life_like(&self) -> Option<LifeLike<C>> {
+        None
+    }
 }
 
 /// A basic rule that never changes any cell states.
@@ -30,3 +61,26 @@ impl<C: CellType, D: Dim> Rule<C, D> for DummyRule {
         Box::new(|napkin| napkin[&NdVec::origin()])
     }
 }
+impl fmt::Display for DummyRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dummy")
+    }
+}
+
+/// Returns the rule with the given name or rulestring.
+///
+/// Built-in rules (see `by_name()`) are checked first, followed by each of the
+/// rulestring formats that apply to this dimensionality.
+pub fn from_rulestring<D: DimRules>(s: &str) -> Result<Arc<dyn Rule<u8, D>>, String> {
+    if let Some(rule) = by_name(s) {
+        return Ok(rule);
+    }
+    let totalistic_err = match NdTotalistic::<D>::try_from(s) {
+        Ok(rule) => return Ok(Arc::new(rule)),
+        Err(e) => e,
+    };
+    match D::parse_rulestring(s) {
+        Some(result) => result,
+        None => Err(totalistic_err),
+    }
+}
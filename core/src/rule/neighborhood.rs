@@ -0,0 +1,124 @@
+use crate::space::*;
+
+/// The set of cells that a cell can see when computing its next state, given
+/// as offsets from that cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Neighborhood<D: Dim> {
+    /// Every cell within the given Chebyshev distance (a hypercube).
+    Moore(usize),
+    /// Every cell within the given Manhattan distance (a cross-polytope).
+    VonNeumann(usize),
+    /// A hexagonal neighborhood with the given radius, skewed to fit on a
+    /// square grid.
+    ///
+    /// In 2D, this is the Moore neighborhood with the northeast and southwest
+    /// corners cut off, which matches Golly. In general, this is every cell in
+    /// the Moore neighborhood whose coordinates sum to no more than the radius
+    /// (in absolute value).
+    Hexagonal(usize),
+    /// Every cell within the given distance along any one axis.
+    Cross(usize),
+    /// Every cell whose Euclidean distance is less than the given distance
+    /// plus one half (an approximate hypersphere).
+    Circular(usize),
+    /// An arbitrary list of cells.
+    Custom(Vec<IVec<D>>),
+}
+
+impl<D: Dim> Neighborhood<D> {
+    /// Returns the maximum distance along any axis of any cell in this
+    /// neighborhood.
+    pub fn radius(&self) -> usize {
+        match self {
+            Self::Moore(r)
+            | Self::VonNeumann(r)
+            | Self::Hexagonal(r)
+            | Self::Cross(r)
+            | Self::Circular(r) => *r,
+            Self::Custom(cells) => cells
+                .iter()
+                .flat_map(|pos| D::axes().iter().map(move |&ax| pos[ax].unsigned_abs()))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Returns true if the given offset is in this neighborhood.
+    pub fn contains(&self, pos: &IVec<D>) -> bool {
+        if let Self::Custom(cells) = self {
+            return cells.contains(pos);
+        }
+        let r = self.radius() as isize;
+        let coords = D::axes().iter().map(|&ax| pos[ax]);
+        if !coords.clone().all(|x| x.abs() <= r) {
+            return false;
+        }
+        match self {
+            Self::Moore(_) => true,
+            Self::VonNeumann(_) => coords.map(isize::abs).sum::<isize>() <= r,
+            Self::Hexagonal(_) => coords.sum::<isize>().abs() <= r,
+            Self::Cross(_) => coords.filter(|&x| x != 0).count() <= 1,
+            // (x^2 + y^2 + ...) < (r + 1/2)^2, which simplifies to this for
+            // integers.
+            Self::Circular(_) => coords.map(|x| x * x).sum::<isize>() <= r * r + r,
+            Self::Custom(_) => unreachable!(),
+        }
+    }
+
+    /// Returns the offsets of all the cells in this neighborhood.
+    ///
+    /// For a custom neighborhood, these are in the order they were given.
+    /// Otherwise they include the origin and are sorted with the X axis
+    /// changing fastest.
+    pub fn cells(&self) -> Vec<IVec<D>> {
+        if let Self::Custom(cells) = self {
+            return cells.clone();
+        }
+        let r = self.radius() as isize;
+        IRect::span(IVec::repeat(-r), IVec::repeat(r))
+            .iter()
+            .filter(|pos| self.contains(pos))
+            .collect()
+    }
+
+    /// Returns the offsets of all the cells in this neighborhood except the
+    /// origin.
+    pub fn neighbors(&self) -> Vec<IVec<D>> {
+        let mut ret = self.cells();
+        ret.retain(|pos| !pos.is_zero());
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the size and radius of each kind of neighborhood.
+    #[test]
+    fn test_neighborhood_shapes() {
+        let count = |n: Neighborhood<Dim2D>| n.neighbors().len();
+        assert_eq!(8, count(Neighborhood::Moore(1)));
+        assert_eq!(24, count(Neighborhood::Moore(2)));
+        assert_eq!(4, count(Neighborhood::VonNeumann(1)));
+        assert_eq!(12, count(Neighborhood::VonNeumann(2)));
+        assert_eq!(6, count(Neighborhood::Hexagonal(1)));
+        assert_eq!(18, count(Neighborhood::Hexagonal(2)));
+        assert_eq!(8, count(Neighborhood::Cross(2)));
+        assert_eq!(8, count(Neighborhood::Circular(1)));
+        assert_eq!(20, count(Neighborhood::Circular(2)));
+        assert_eq!(26, Neighborhood::<Dim3D>::Moore(1).neighbors().len());
+        assert_eq!(18, Neighborhood::<Dim3D>::Circular(1).neighbors().len());
+
+        // Golly's hexagonal neighborhood excludes NE and SW.
+        let hex = Neighborhood::<Dim2D>::Hexagonal(1);
+        assert!(!hex.contains(&NdVec([1, 1])));
+        assert!(!hex.contains(&NdVec([-1, -1])));
+        assert!(hex.contains(&NdVec([1, -1])));
+        assert!(hex.contains(&NdVec([-1, 1])));
+
+        let custom = Neighborhood::<Dim2D>::Custom(vec![NdVec([0, 0]), NdVec([3, -1])]);
+        assert_eq!(3, custom.radius());
+        assert_eq!(vec![NdVec([3, -1])], custom.neighbors());
+    }
+}
@@ -0,0 +1,128 @@
+use super::{Neighborhood, Rule, RuleInfo, TransitionFunction};
+use crate::space::*;
+use core::fmt;
+
+/// A rule given by an ordered list of transitions, each of which maps a set
+/// of configurations of a cell's neighborhood to the cell's next state.
+///
+/// The first transition that matches a cell's neighborhood determines its next
+/// state. Cells whose neighborhood does not match any transition keep their
+/// current state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NdTable<D: Dim> {
+    name: String,
+    neighborhood: Neighborhood<D>,
+    /// The positions of the cells that make up a neighborhood configuration,
+    /// in order.
+    cells: Vec<IVec<D>>,
+    /// For each cell in the neighborhood and each state, the set of
+    /// transitions that match when that cell is in that state, as a bitset.
+    matches: Vec<Vec<Vec<u64>>>,
+    /// The next state of a cell for each transition.
+    outputs: Vec<u8>,
+    info: RuleInfo,
+}
+
+impl<D: Dim> NdTable<D> {
+    /// Constructs a new table rule with the given name, neighborhood, and
+    /// transitions.
+    ///
+    /// Each transition is a list of the allowed states for each cell in the
+    /// neighborhood (in the order given by `Neighborhood::cells()`) and the
+    /// next state for matching configurations. Transitions earlier in the
+    /// list take precedence. The number of states is the highest state that
+    /// appears in any transition, plus one.
+    pub fn new(
+        name: String,
+        neighborhood: Neighborhood<D>,
+        transitions: impl IntoIterator<Item = (Vec<Vec<u8>>, u8)>,
+    ) -> Self {
+        let cells = neighborhood.cells();
+        let transitions: Vec<_> = transitions.into_iter().collect();
+        let words = transitions.len().div_ceil(64);
+        let mut matches = vec![vec![]; cells.len()];
+        let mut outputs = Vec::with_capacity(transitions.len());
+        for (i, (config, next)) in transitions.into_iter().enumerate() {
+            assert_eq!(
+                cells.len(),
+                config.len(),
+                "Transition does not match neighborhood size",
+            );
+            for (cell_matches, states) in matches.iter_mut().zip(config) {
+                for state in states {
+                    let state = state as usize;
+                    if cell_matches.len() <= state {
+                        cell_matches.resize(state + 1, vec![0; words]);
+                    }
+                    cell_matches[state][i / 64] |= 1 << (i % 64);
+                }
+            }
+            outputs.push(next);
+        }
+        let states = matches
+            .iter()
+            .map(Vec::len)
+            .chain(outputs.iter().map(|&next| next as usize + 1))
+            .max()
+            .unwrap_or(0)
+            .max(2);
+        Self {
+            name,
+            neighborhood,
+            cells,
+            matches,
+            outputs,
+            info: RuleInfo::new(states),
+        }
+    }
+    /// Returns this rule with different information about its states.
+    pub fn with_info(self, info: RuleInfo) -> Self {
+        Self { info, ..self }
+    }
+
+    /// Returns the number of transitions in the table.
+    pub fn transition_count(&self) -> usize {
+        self.outputs.len()
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for NdTable<D> {
+    fn radius(&self) -> usize {
+        self.neighborhood.radius()
+    }
+    fn get_transition_function(&self) -> TransitionFunction<'_, u8, D> {
+        let mut matching = vec![0_u64; self.outputs.len().div_ceil(64)];
+        Box::new(move |napkin| {
+            let current = napkin[&NdVec::origin()];
+            // Intersect the sets of transitions matching each cell.
+            matching.iter_mut().for_each(|bits| *bits = !0);
+            for (pos, cell_matches) in self.cells.iter().zip(&self.matches) {
+                match cell_matches.get(napkin[pos] as usize) {
+                    Some(bits) => matching
+                        .iter_mut()
+                        .zip(bits)
+                        .for_each(|(matching, bits)| *matching &= bits),
+                    // No transition allows this state here.
+                    None => return current,
+                }
+            }
+            // Find the first transition that matches.
+            matching
+                .iter()
+                .enumerate()
+                .find(|(_, &bits)| bits != 0)
+                .and_then(|(i, bits)| self.outputs.get(i * 64 + bits.trailing_zeros() as usize))
+                .copied()
+                .unwrap_or(current)
+        })
+    }
+    fn info(&self) -> RuleInfo {
+        self.info.clone()
+    }
+}
+
+impl<D: Dim> fmt::Display for NdTable<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
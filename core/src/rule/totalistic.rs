@@ -1,7 +1,9 @@
-use super::{Rule, TransitionFunction};
+use super::{Neighborhood, Rule, RuleInfo, TransitionFunction};
 use crate::space::*;
+use core::fmt;
 use regex::Regex;
 use std::convert::TryFrom;
+use std::rc::Rc;
 
 /// A 2-state totalistic 2D range-1 Moore-neighborhood algorithm.
 #[derive(Debug)]
@@ -17,13 +19,14 @@ impl TryFrom<&str> for MooreTotalistic2D {
         let captures = regex.captures(s).ok_or(())?;
         let mut conditions = [[0; 9]; 2];
         for i in 0..2 {
-            for ch in captures[i].chars() {
+            // Group 0 is the whole match.
+            for ch in captures[i + 1].chars() {
                 conditions[i][ch.to_string().parse::<usize>().unwrap()] = 1;
             }
         }
         Ok(Self {
             birth: conditions[0],
-            survival: conditions[0],
+            survival: conditions[1],
         })
     }
 }
@@ -32,12 +35,15 @@ impl Rule<u8, Dim2D> for MooreTotalistic2D {
     fn radius(&self) -> usize {
         1
     }
+    fn as_life_like(&self) -> Option<LifeLike<u8>> {
+        Some(LifeLike::from_tables(1, &self.birth, &self.survival))
+    }
     fn get_transition_function(&self) -> TransitionFunction<u8, Dim2D> {
+        let nbhood = Neighborhood::Moore(self.radius()).cells();
         Box::new(move |napkin| {
             // Count live neighbors.
-            let nbhood = Rect2D::moore(self.radius() as isize);
             let mut live_neighbors = 0;
-            for cell_coords in nbhood.iter() {
+            for cell_coords in &nbhood {
                 if napkin[&cell_coords] != 0 {
                     live_neighbors += 1;
                 }
@@ -53,9 +59,672 @@ impl Rule<u8, Dim2D> for MooreTotalistic2D {
     }
 }
 
+impl fmt::Display for MooreTotalistic2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "B{}/S{}",
+            format_digits(&self.birth),
+            format_digits(&self.survival),
+        )
+    }
+}
+
 /// Conway's Game of Life, simulated using a general 2-state totalistic
 /// 2D range-1 Moore-neighborhood algorithm.
 pub const LIFE: MooreTotalistic2D = MooreTotalistic2D {
     birth: [0, 0, 0, 1, 0, 0, 0, 0, 0],
     survival: [0, 0, 1, 1, 0, 0, 0, 0, 0],
 };
+
+/// An outer-totalistic rule of any dimensionality and range.
+///
+/// The next state of a cell depends only on its own state and the number of
+/// live cells (cells in state 1) in its neighborhood.
+///
+/// Large neighborhoods (such as those of "Larger than Life" rules) are
+/// supported efficiently: rather than checking every neighbor of every cell,
+/// live cells are counted along each row of the neighborhood using running
+/// totals.
+///
+/// If there are more than two states, this is a "Generations" rule: instead of
+/// dying immediately, a live cell that does not survive steps through each of
+/// the states from 2 up to the last one, then dies. Cells in these "dying"
+/// states do not count as live neighbors and cannot be born.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NdTotalistic<D: Dim> {
+    states: usize,
+    neighborhood: Neighborhood<D>,
+    include_middle: bool,
+    /// The positions of the neighbors of the origin (excluding the origin
+    /// itself, even if `include_middle` is true).
+    neighbors: Vec<IVec<D>>,
+    /// The neighborhood (including the origin) split into runs of cells along
+    /// the X axis, as pairs of the first position and the length of the run.
+    rows: Vec<(IVec<D>, usize)>,
+    /// The next state of a dead cell, indexed by neighbor count.
+    birth: Vec<u8>,
+    /// The next state of a live cell, indexed by neighbor count.
+    survival: Vec<u8>,
+}
+
+impl<D: Dim> NdTotalistic<D> {
+    /// Constructs a new outer-totalistic rule with the given number of states,
+    /// neighborhood, and lists of neighbor counts for birth and survival. The
+    /// origin is never counted as part of the neighborhood.
+    ///
+    /// If `include_middle` is true, a live cell counts itself as one of its
+    /// neighbors.
+    pub fn new(
+        states: usize,
+        neighborhood: Neighborhood<D>,
+        include_middle: bool,
+        birth: &[usize],
+        survival: &[usize],
+    ) -> Result<Self, String> {
+        if !(2..=256).contains(&states) {
+            return Err(format!("{} states are not supported", states));
+        }
+        let neighbors = neighborhood.neighbors();
+        let rows = split_rows(neighbors.iter().cloned().chain(Some(IVec::origin())));
+        let max_count = neighbors.len() + include_middle as usize;
+        let make_table = |counts: &[usize]| -> Result<Vec<u8>, String> {
+            let mut table = vec![0; max_count + 1];
+            for &count in counts {
+                *table.get_mut(count).ok_or_else(|| {
+                    format!(
+                        "Neighbor count {} is larger than the neighborhood ({} cells)",
+                        count, max_count,
+                    )
+                })? = 1;
+            }
+            Ok(table)
+        };
+        let birth = make_table(birth)?;
+        let survival = make_table(survival)?;
+        Ok(Self {
+            states,
+            neighborhood,
+            include_middle,
+            neighbors,
+            rows,
+            birth,
+            survival,
+        })
+    }
+
+    /// Returns true if live neighbors should be counted using running totals
+    /// along each row rather than by checking each neighbor.
+    fn use_row_sums(&self) -> bool {
+        // Computing the running totals takes time too, so this is only faster
+        // when there are much fewer rows than cells.
+        self.rows.len() * 4 <= self.neighbors.len()
+    }
+
+    /// Returns the number of states (2 unless this is a "Generations" rule).
+    pub fn states(&self) -> usize {
+        self.states
+    }
+    /// Returns the number of cells in the neighborhood of each cell.
+    pub fn neighbor_count(&self) -> usize {
+        self.neighbors.len()
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for NdTotalistic<D> {
+    fn radius(&self) -> usize {
+        self.neighborhood.radius()
+    }
+    fn get_transition_function(&self) -> TransitionFunction<'_, u8, D> {
+        let mut row_sums: Option<RowSums<D>> = None;
+        Box::new(move |napkin| {
+            let live_neighbors = if self.use_row_sums() {
+                // Reuse the running totals for every cell in the same array.
+                let array = napkin.array();
+                if !matches!(&row_sums, Some(sums) if Rc::ptr_eq(&sums.array, array)) {
+                    row_sums = Some(RowSums::new(array.clone()));
+                }
+                let row_sums = row_sums.as_ref().unwrap();
+                // The rows include the cell itself.
+                row_sums.count(&napkin.origin(), &self.rows)
+                    - (napkin[&NdVec::origin()] == 1) as usize
+            } else {
+                self.neighbors.iter().filter(|pos| napkin[pos] == 1).count()
+            };
+            match napkin[&NdVec::origin()] {
+                0 => self.birth[live_neighbors],
+                1 if self.survival[live_neighbors + self.include_middle as usize] != 0 => 1,
+                // The cell is dying; move on to the next state.
+                dying if (dying as usize) + 1 < self.states => dying + 1,
+                _ => 0,
+            }
+        })
+    }
+    fn info(&self) -> RuleInfo {
+        RuleInfo::new(self.states)
+    }
+    fn as_life_like(&self) -> Option<LifeLike<u8>> {
+        if self.states != 2 || self.neighborhood != Neighborhood::Moore(1) || self.birth.len() > 32
+        {
+            return None;
+        }
+        let survival = &self.survival[self.include_middle as usize..];
+        Some(LifeLike::from_tables(1, &self.birth, survival))
+    }
+}
+
+/// A 2-state outer-totalistic rule with the range-1 Moore neighborhood, in any
+/// number of dimensions up to three.
+///
+/// Patterns simulated with such a rule may only contain cells in the default
+/// state and in `live_state`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LifeLike<C> {
+    /// The state of live cells.
+    pub live_state: C,
+    /// Bit `n` is set if a dead cell with `n` live neighbors becomes live.
+    pub birth: u32,
+    /// Bit `n` is set if a live cell with `n` live neighbors stays live.
+    pub survival: u32,
+}
+impl<C> LifeLike<C> {
+    /// Constructs a Life-like rule from tables of the next state of dead and
+    /// live cells, indexed by neighbor count, where any nonzero entry means
+    /// that the cell will be live.
+    pub fn from_tables(live_state: C, birth: &[u8], survival: &[u8]) -> Self {
+        let mask = |table: &[u8]| {
+            table
+                .iter()
+                .enumerate()
+                .filter(|(_, &next)| next != 0)
+                .map(|(count, _)| 1 << count)
+                .sum()
+        };
+        Self {
+            live_state,
+            birth: mask(birth),
+            survival: mask(survival),
+        }
+    }
+}
+
+/// Splits a set of positions into runs of consecutive cells along the X axis,
+/// returning the first position and length of each run.
+fn split_rows<D: Dim>(positions: impl Iterator<Item = IVec<D>>) -> Vec<(IVec<D>, usize)> {
+    let mut positions: Vec<IVec<D>> = positions.collect();
+    // Sort by every other axis first, then by X.
+    positions.sort_by_key(|pos| {
+        D::axes()
+            .iter()
+            .rev()
+            .map(|&ax| pos[ax])
+            .collect::<Vec<_>>()
+    });
+    let mut rows: Vec<(IVec<D>, usize)> = vec![];
+    for pos in positions {
+        match rows.last_mut() {
+            Some((start, len)) if start.clone() + IVec::unit(X) * *len as isize == pos => *len += 1,
+            _ => rows.push((pos, 1)),
+        }
+    }
+    rows
+}
+
+/// Running totals of live cells (cells in state 1) along the X axis of an
+/// NdArray, which can be used to count the live cells in a run of consecutive
+/// cells in constant time.
+struct RowSums<D: Dim> {
+    array: Rc<NdArray<u8, D>>,
+    /// The number of live cells in the array before each position along the
+    /// same row. This is one cell longer along the X axis than the array.
+    sums: NdArray<u32, D>,
+}
+impl<D: Dim> RowSums<D> {
+    fn new(array: Rc<NdArray<u8, D>>) -> Self {
+        let mut size = array.size().clone();
+        size[X] += 1;
+        let mut sums = NdArray::from_fn(size, |_| 0);
+        // Elements are stored with the X axis changing fastest, so the total
+        // for the previous cell in each row is always available.
+        for (pos, &cell) in array.iter_enumerated() {
+            let next = sums[&pos] + (cell == 1) as u32;
+            sums[&(pos + IVec::unit(X))] = next;
+        }
+        Self { array, sums }
+    }
+    /// Returns the number of live cells in the given rows (see `split_rows()`)
+    /// relative to the given position in the array.
+    fn count(&self, origin: &IVec<D>, rows: &[(IVec<D>, usize)]) -> usize {
+        rows.iter()
+            .map(|(start, len)| {
+                let start = origin + start.clone();
+                let end = start.clone() + IVec::unit(X) * *len as isize;
+                (self.sums[&end] - self.sums[&start]) as usize
+            })
+            .sum()
+    }
+}
+
+impl<D: Dim> fmt::Display for NdTotalistic<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let is_single_digits = |table: &[u8]| table.iter().skip(10).all(|&x| x == 0);
+        if self.neighborhood == Neighborhood::Moore(1)
+            && !self.include_middle
+            && is_single_digits(&self.birth)
+            && is_single_digits(&self.survival)
+        {
+            // Use B/S notation.
+            if D::NDIM != 2 {
+                write!(f, "{}D:", D::NDIM)?;
+            }
+            write!(
+                f,
+                "B{}/S{}",
+                format_digits(&self.birth),
+                format_digits(&self.survival)
+            )?;
+            if self.states != 2 {
+                write!(f, "/C{}", self.states)?;
+            }
+            return Ok(());
+        }
+        write!(f, "R{},", self.neighborhood.radius())?;
+        match self.states {
+            2 => write!(f, "C0,")?,
+            n => write!(f, "C{},", n)?,
+        }
+        if self.include_middle {
+            write!(f, "M1,")?;
+        }
+        write!(f, "S{},", format_counts(&self.survival))?;
+        write!(f, "B{},", format_counts(&self.birth))?;
+        write!(f, "N{}", format_neighborhood(&self.neighborhood))
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for NdTotalistic<D> {
+    type Error = String;
+    /// Parses an outer-totalistic rulestring.
+    ///
+    /// Three formats are accepted:
+    ///
+    /// - Golly's HROT format, such as `R2,C0,S2-4,B3,NM`, where `C` is the
+    ///   number of states and `N` is followed by `M` (Moore), `N` (von
+    ///   Neumann) or `C` (circular). This includes Golly's "Larger than Life"
+    ///   format, such as `R5,C0,M1,S34..58,B34..45,NM`, where `M1` means that
+    ///   a live cell counts itself as a neighbor.
+    /// - B/S notation with an optional dimension prefix and an optional number
+    ///   of states, such as `B3/S23`, `3D:B5/S45` or `B2/S/C3`. Counts may be
+    ///   separated by commas and use ranges (as in `4D:B12-14/S10,12`);
+    ///   otherwise each digit is a separate count.
+    /// - Golly's S/B/C notation for "Generations" rules, such as `345/2/4`.
+    fn try_from(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.starts_with(['R', 'r'].as_ref()) {
+            parse_hrot(s)
+        } else {
+            parse_bs(s)
+        }
+    }
+}
+
+/// Parses a rulestring in Golly's HROT or "Larger than Life" format, such as
+/// `R2,C0,S2-4,B3,NM` or `R5,C0,M1,S34..58,B34..45,NM`.
+fn parse_hrot<D: Dim>(s: &str) -> Result<NdTotalistic<D>, String> {
+    let mut radius = None;
+    let mut states = 2;
+    let mut include_middle = false;
+    let mut neighborhood = "M";
+    let mut birth = vec![];
+    let mut survival = vec![];
+    // Which list of counts bare numbers should be added to.
+    let mut current_list = None;
+    for token in s.split(',') {
+        let token = token.trim();
+        let (prefix, rest) = match token.chars().next() {
+            Some(ch) if ch.is_ascii_alphabetic() => (ch.to_ascii_uppercase(), &token[1..]),
+            _ => {
+                let counts = parse_counts(token)?;
+                match current_list {
+                    Some('B') => birth.extend(counts),
+                    Some('S') => survival.extend(counts),
+                    _ => return Err(format!("Unexpected '{}' in rulestring", token)),
+                }
+                continue;
+            }
+        };
+        current_list = None;
+        match prefix {
+            'R' => radius = Some(parse_number(rest)?),
+            'C' => states = parse_number(rest)?,
+            'M' => include_middle = parse_number(rest)? != 0,
+            'B' => {
+                birth.extend(parse_counts(rest)?);
+                current_list = Some('B');
+            }
+            'S' => {
+                survival.extend(parse_counts(rest)?);
+                current_list = Some('S');
+            }
+            'N' => neighborhood = rest,
+            _ => return Err(format!("Unexpected '{}' in rulestring", token)),
+        }
+    }
+    let radius = radius.ok_or("Missing radius in rulestring")?;
+    let neighborhood = parse_neighborhood(neighborhood, radius)?;
+    // C0 is equivalent to C2.
+    let states = std::cmp::max(states, 2);
+    NdTotalistic::new(states, neighborhood, include_middle, &birth, &survival)
+}
+
+/// Parses a rulestring in B/S notation with an optional dimension prefix and
+/// number of states, such as `3D:B5/S45` or `B2/S/C3`, or in S/B/C notation,
+/// such as `345/2/4`.
+fn parse_bs<D: Dim>(s: &str) -> Result<NdTotalistic<D>, String> {
+    let regex = Regex::new(
+        r"^(?:(\d+)[Dd]:)?(?:[Bb]([\d,\-]*)/[Ss]([\d,\-]*)|[Ss]([\d,\-]*)/[Bb]([\d,\-]*))(?:/[CcGg](\d+))?$",
+    )
+    .unwrap();
+    let sbc_regex = Regex::new(r"^(\d*)/(\d*)/(\d+)$").unwrap();
+    if let Some(captures) = sbc_regex.captures(s) {
+        return NdTotalistic::new(
+            parse_number(&captures[3])?,
+            Neighborhood::Moore(1),
+            false,
+            &parse_digits(&captures[2]),
+            &parse_digits(&captures[1]),
+        );
+    }
+    let captures = regex
+        .captures(s)
+        .ok_or_else(|| format!("Invalid rulestring '{}'", s))?;
+    if let Some(ndim) = captures.get(1) {
+        if parse_number(ndim.as_str())? != D::NDIM {
+            return Err(format!(
+                "Rulestring is for {}D, but the automaton is {}D",
+                ndim.as_str(),
+                D::NDIM,
+            ));
+        }
+    }
+    let (birth, survival) = match captures.get(2) {
+        Some(b) => (b.as_str(), &captures[3]),
+        None => (&captures[5], &captures[4]),
+    };
+    let parse_list = |s: &str| {
+        if s.contains([',', '-']) {
+            parse_counts(s)
+        } else {
+            Ok(parse_digits(s))
+        }
+    };
+    let states = match captures.get(6) {
+        Some(states) => parse_number(states.as_str())?,
+        None => 2,
+    };
+    NdTotalistic::new(
+        states,
+        Neighborhood::Moore(1),
+        false,
+        &parse_list(birth)?,
+        &parse_list(survival)?,
+    )
+}
+
+/// Parses the neighborhood letter of an HROT rulestring: `M` (Moore), `N` (von
+/// Neumann), `H` (hexagonal), `+` (cross), `C` (circular), or `@` followed by
+/// a custom neighborhood in hexadecimal (see `format_neighborhood()`).
+fn parse_neighborhood<D: Dim>(s: &str, radius: usize) -> Result<Neighborhood<D>, String> {
+    Ok(match s {
+        "M" | "m" => Neighborhood::Moore(radius),
+        "N" | "n" => Neighborhood::VonNeumann(radius),
+        "H" | "h" => Neighborhood::Hexagonal(radius),
+        "+" => Neighborhood::Cross(radius),
+        "C" | "c" => Neighborhood::Circular(radius),
+        _ if s.starts_with('@') => {
+            let hex = &s[1..];
+            let cells = custom_neighborhood_order::<D>(radius);
+            if hex.len() != cells.len().div_ceil(4) {
+                return Err(format!(
+                    "Custom neighborhood must have {} hexadecimal digits for range {}",
+                    cells.len().div_ceil(4),
+                    radius,
+                ));
+            }
+            let mut bits = vec![];
+            for ch in hex.chars() {
+                let digit = ch
+                    .to_digit(16)
+                    .ok_or_else(|| format!("Invalid hexadecimal digit '{}'", ch))?;
+                bits.extend((0..4).rev().map(|i| digit & 1 << i != 0));
+            }
+            let cells = cells
+                .into_iter()
+                .zip(bits)
+                .filter(|(pos, bit)| *bit && !pos.is_zero())
+                .map(|(pos, _)| pos)
+                .collect();
+            Neighborhood::Custom(cells)
+        }
+        _ => return Err(format!("Unknown neighborhood '{}'", s)),
+    })
+}
+
+/// Formats a neighborhood as the neighborhood letter of an HROT rulestring.
+///
+/// Custom neighborhoods are written as `@` followed by one bit for each cell
+/// in the Moore neighborhood (in the order given by
+/// `custom_neighborhood_order()`), in hexadecimal and padded with zeros to a
+/// multiple of four bits.
+fn format_neighborhood<D: Dim>(neighborhood: &Neighborhood<D>) -> String {
+    match neighborhood {
+        Neighborhood::Moore(_) => "M".to_owned(),
+        Neighborhood::VonNeumann(_) => "N".to_owned(),
+        Neighborhood::Hexagonal(_) => "H".to_owned(),
+        Neighborhood::Cross(_) => "+".to_owned(),
+        Neighborhood::Circular(_) => "C".to_owned(),
+        Neighborhood::Custom(_) => {
+            let bits: Vec<bool> = custom_neighborhood_order(neighborhood.radius())
+                .iter()
+                .map(|pos| !pos.is_zero() && neighborhood.contains(pos))
+                .collect();
+            let hex: String = bits
+                .chunks(4)
+                .map(|chunk| {
+                    let digit =
+                        (0..4).fold(0, |d, i| d << 1 | *chunk.get(i).unwrap_or(&false) as u32);
+                    std::char::from_digit(digit, 16)
+                        .unwrap()
+                        .to_ascii_uppercase()
+                })
+                .collect();
+            format!("@{}", hex)
+        }
+    }
+}
+
+/// Returns the positions in the Moore neighborhood of the given radius, in
+/// the order used for custom neighborhoods in HROT rulestrings: row by row
+/// starting from the top left, like reading text.
+fn custom_neighborhood_order<D: Dim>(radius: usize) -> Vec<IVec<D>> {
+    let r = radius as isize;
+    IRect::span(IVec::repeat(-r), IVec::repeat(r))
+        .iter()
+        .map(|mut pos| {
+            if D::contains(Y) {
+                pos[Y] = -pos[Y];
+            }
+            pos
+        })
+        .collect()
+}
+
+/// Parses a non-negative integer.
+fn parse_number(s: &str) -> Result<usize, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("Expected a number; got '{}'", s))
+}
+
+/// Parses a list of single-digit neighbor counts, such as `23`.
+fn parse_digits(s: &str) -> Vec<usize> {
+    s.chars()
+        .map(|ch| ch.to_digit(10).unwrap() as usize)
+        .collect()
+}
+
+/// Parses a comma-separated list of neighbor counts and ranges of neighbor
+/// counts, such as `2-4,6`. Ranges may also be written as `2..4`.
+fn parse_counts(s: &str) -> Result<Vec<usize>, String> {
+    let mut ret = vec![];
+    for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let separator = if item.contains("..") { ".." } else { "-" };
+        let mut bounds = item.splitn(2, separator);
+        let lo = parse_number(bounds.next().unwrap())?;
+        let hi = match bounds.next() {
+            Some(hi) => parse_number(hi)?,
+            None => lo,
+        };
+        ret.extend(lo..=hi);
+    }
+    Ok(ret)
+}
+
+/// Formats a table of single-digit neighbor counts as a string of digits.
+fn format_digits(table: &[u8]) -> String {
+    (0..table.len())
+        .filter(|&count| table[count] != 0)
+        .map(|count| count.to_string())
+        .collect()
+}
+
+/// Formats a table of neighbor counts as a comma-separated list of counts and
+/// ranges of counts.
+fn format_counts(table: &[u8]) -> String {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for (count, _) in table.iter().enumerate().filter(|(_, &x)| x != 0) {
+        match ranges.last_mut() {
+            Some((_, hi)) if *hi + 1 == count => *hi = count,
+            _ => ranges.push((count, count)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(lo, hi)| match hi - lo {
+            0 => lo.to_string(),
+            _ => format!("{}-{}", lo, hi),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// An outer-totalistic 1D rule.
+pub type Totalistic1D = NdTotalistic<Dim1D>;
+/// An outer-totalistic 2D rule.
+pub type Totalistic2D = NdTotalistic<Dim2D>;
+/// An outer-totalistic 3D rule.
+pub type Totalistic3D = NdTotalistic<Dim3D>;
+/// An outer-totalistic 4D rule.
+pub type Totalistic4D = NdTotalistic<Dim4D>;
+/// An outer-totalistic 5D rule.
+pub type Totalistic5D = NdTotalistic<Dim5D>;
+/// An outer-totalistic 6D rule.
+pub type Totalistic6D = NdTotalistic<Dim6D>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests parsing of both supported rulestring formats.
+    #[test]
+    fn test_totalistic_parse() {
+        let rule = Totalistic2D::try_from("R2,C0,S2-4,6,B3,NM").unwrap();
+        assert_eq!(2, rule.radius());
+        assert_eq!(24, rule.neighbor_count());
+        assert_eq!("R2,C0,S2-4,6,B3,NM", rule.to_string());
+
+        let rule = Totalistic2D::try_from("R2,C2,S1,B1-2,NN").unwrap();
+        assert_eq!(12, rule.neighbor_count());
+        assert_eq!("R2,C0,S1,B1-2,NN", rule.to_string());
+
+        let rule = Totalistic3D::try_from("3D:B5/S45").unwrap();
+        assert_eq!(1, rule.radius());
+        assert_eq!(26, rule.neighbor_count());
+        assert_eq!("3D:B5/S45", rule.to_string());
+
+        let rule = Totalistic4D::try_from("4d:B12-14/S10,12").unwrap();
+        assert_eq!(80, rule.neighbor_count());
+        assert_eq!("R1,C0,S10,12,B12-14,NM", rule.to_string());
+
+        let rule = Totalistic2D::try_from("S23/B3").unwrap();
+        assert_eq!("B3/S23", rule.to_string());
+
+        // Generations
+        let rule = Totalistic2D::try_from("B2/S/C3").unwrap();
+        assert_eq!(3, rule.states());
+        assert_eq!("B2/S/C3", rule.to_string());
+        assert_eq!(Ok(rule), Totalistic2D::try_from("/2/3"));
+        let rule = Totalistic2D::try_from("345/2/4").unwrap();
+        assert_eq!(4, rule.states());
+        assert_eq!("B2/S345/C4", rule.to_string());
+        let rule = Totalistic3D::try_from("3D:B4/S4/C5").unwrap();
+        assert_eq!("3D:B4/S4/C5", rule.to_string());
+        let rule = Totalistic2D::try_from("R3,C10,S2,B3,NN").unwrap();
+        assert_eq!(10, rule.states());
+        assert_eq!(Ok(rule.clone()), Totalistic2D::try_from(&*rule.to_string()));
+
+        // Larger than Life
+        let rule = Totalistic2D::try_from("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(5, rule.radius());
+        assert_eq!(120, rule.neighbor_count());
+        assert_eq!("R5,C0,M1,S34-58,B34-45,NM", rule.to_string());
+        let rule = Totalistic2D::try_from("R2,C0,S3..5,B4..6,NC").unwrap();
+        assert_eq!(20, rule.neighbor_count());
+        assert_eq!("R2,C0,S3-5,B4-6,NC", rule.to_string());
+        let rule = Totalistic3D::try_from("R1,C0,S3..5,B4..6,NC").unwrap();
+        assert_eq!(18, rule.neighbor_count());
+
+        // Other neighborhoods
+        let rule = Totalistic2D::try_from("R2,C0,S2-4,B3,NH").unwrap();
+        assert_eq!(18, rule.neighbor_count());
+        assert_eq!("R2,C0,S2-4,B3,NH", rule.to_string());
+        let rule = Totalistic2D::try_from("R3,C0,S2-4,B3,N+").unwrap();
+        assert_eq!(12, rule.neighbor_count());
+        assert_eq!("R3,C0,S2-4,B3,N+", rule.to_string());
+        // Knight's move neighborhood
+        let rule = Totalistic2D::try_from("R2,C0,S2-4,B3,N@5441150").unwrap();
+        assert_eq!(8, rule.neighbor_count());
+        assert_eq!("R2,C0,S2-4,B3,N@5441150", rule.to_string());
+        assert_eq!(
+            Ok(rule),
+            Totalistic2D::new(
+                2,
+                Neighborhood::Custom(vec![
+                    NdVec([-1, 2]),
+                    NdVec([1, 2]),
+                    NdVec([-2, 1]),
+                    NdVec([2, 1]),
+                    NdVec([-2, -1]),
+                    NdVec([2, -1]),
+                    NdVec([-1, -2]),
+                    NdVec([1, -2]),
+                ]),
+                false,
+                &[3],
+                &[2, 3, 4],
+            ),
+        );
+        assert!(Totalistic2D::try_from("R2,C0,S2-4,B3,N@544115").is_err());
+
+        // Wrong dimensionality
+        assert!(Totalistic2D::try_from("3D:B5/S45").is_err());
+        // Neighbor count too high
+        assert!(Totalistic2D::try_from("B9/S23").is_err());
+        assert!(Totalistic2D::try_from("R1,C0,S2-5,B3,NN").is_err());
+        // Unsupported
+        assert!(Totalistic2D::try_from("R1,C257,S2-3,B3,NM").is_err());
+        // Garbage
+        assert!(Totalistic2D::try_from("B3/S23/").is_err());
+        assert!(Totalistic2D::try_from("R1,C0,S2-3,X,NM").is_err());
+    }
+}
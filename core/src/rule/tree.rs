@@ -0,0 +1,102 @@
+use super::{Neighborhood, Rule, RuleInfo, TransitionFunction};
+use crate::space::*;
+use core::fmt;
+
+/// A rule given by a decision tree, in the same form as Golly's "rule trees."
+///
+/// Each level of the tree corresponds to one cell of the neighborhood, with
+/// the root corresponding to the first cell. Each node has one child for each
+/// state, which is followed if that cell is in that state; the children of the
+/// nodes at the lowest level are the next state of the cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NdRuleTree<D: Dim> {
+    name: String,
+    states: usize,
+    neighborhood: Neighborhood<D>,
+    /// The positions of the cells that are checked at each level of the tree,
+    /// starting from the root.
+    cells: Vec<IVec<D>>,
+    /// The children of every node, concatenated. Children of nodes at the
+    /// lowest level are cell states; all others are the index into this list
+    /// where the child node begins.
+    data: Vec<usize>,
+    /// The index into `data` where the root node begins.
+    root: usize,
+    info: RuleInfo,
+}
+
+impl<D: Dim> NdRuleTree<D> {
+    /// Constructs a new rule tree with the given name, number of states,
+    /// neighborhood, and nodes.
+    ///
+    /// Each node is a level (starting at 1 for the lowest level) and a list
+    /// of children, one for each state. Children of nodes at level 1 are cell
+    /// states; all others are indices of nodes one level lower, which must
+    /// come earlier in the list. The last node is the root, and its level
+    /// must be the number of cells in the neighborhood.
+    pub fn new(
+        name: String,
+        states: usize,
+        neighborhood: Neighborhood<D>,
+        nodes: &[(usize, Vec<usize>)],
+    ) -> Self {
+        let cells = neighborhood.cells();
+        let mut data = Vec::with_capacity(nodes.len() * states);
+        for (i, (level, children)) in nodes.iter().enumerate() {
+            assert_eq!(states, children.len(), "Wrong number of children for node");
+            for &child in children {
+                if *level == 1 {
+                    assert!(child < states, "Invalid state in rule tree");
+                    data.push(child);
+                } else {
+                    assert!(child < i, "Child node must come before its parent");
+                    assert_eq!(level - 1, nodes[child].0, "Child node has wrong level");
+                    data.push(child * states);
+                }
+            }
+        }
+        let root = nodes.len().checked_sub(1).expect("Rule tree has no nodes");
+        assert_eq!(cells.len(), nodes[root].0, "Root node has wrong level");
+        Self {
+            name,
+            states,
+            neighborhood,
+            cells,
+            data,
+            root: root * states,
+            info: RuleInfo::new(states),
+        }
+    }
+    /// Returns this rule with different information about its states.
+    pub fn with_info(self, info: RuleInfo) -> Self {
+        Self { info, ..self }
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for NdRuleTree<D> {
+    fn radius(&self) -> usize {
+        self.neighborhood.radius()
+    }
+    fn get_transition_function(&self) -> TransitionFunction<'_, u8, D> {
+        Box::new(move |napkin| {
+            let mut idx = self.root;
+            for pos in &self.cells {
+                let state = napkin[pos] as usize;
+                if state >= self.states {
+                    return napkin[&NdVec::origin()];
+                }
+                idx = self.data[idx + state];
+            }
+            idx as u8
+        })
+    }
+    fn info(&self) -> RuleInfo {
+        self.info.clone()
+    }
+}
+
+impl<D: Dim> fmt::Display for NdRuleTree<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
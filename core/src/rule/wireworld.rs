@@ -0,0 +1,55 @@
+use super::{Neighborhood, Rule, RuleInfo, TransitionFunction};
+use crate::space::*;
+use core::fmt;
+use std::marker::PhantomData;
+
+/// Wireworld, a 4-state rule for simulating electronic circuits.
+///
+/// The states are 0 (empty), 1 (electron head), 2 (electron tail) and 3
+/// (conductor). Electron heads become tails, tails become conductors, and a
+/// conductor becomes an electron head if exactly one or two of the cells in its
+/// Moore neighborhood are electron heads. This generalizes to any number of
+/// dimensions using the Moore neighborhood of that dimensionality (e.g. 26
+/// neighbors in 3D).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Wireworld<D: Dim> {
+    _phantom: PhantomData<D>,
+}
+
+impl<D: Dim> Rule<u8, D> for Wireworld<D> {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn get_transition_function(&self) -> TransitionFunction<'_, u8, D> {
+        let neighbors = Neighborhood::<D>::Moore(1).neighbors();
+        Box::new(move |napkin| match napkin[&NdVec::origin()] {
+            1 => 2,
+            2 => 3,
+            3 => match neighbors.iter().filter(|pos| napkin[pos] == 1).count() {
+                1 | 2 => 1,
+                _ => 3,
+            },
+            _ => 0,
+        })
+    }
+    fn info(&self) -> RuleInfo {
+        let mut ret = RuleInfo::new(4);
+        let states = [
+            ("Empty", [0, 0, 0, 255]),
+            ("Electron head", [0, 128, 255, 255]),
+            ("Electron tail", [255, 255, 255, 255]),
+            ("Conductor", [255, 128, 0, 255]),
+        ];
+        for (info, &(name, color)) in ret.states.iter_mut().zip(&states) {
+            info.name = name.to_owned();
+            info.color = color;
+        }
+        ret
+    }
+}
+
+impl<D: Dim> fmt::Display for Wireworld<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WireWorld")
+    }
+}
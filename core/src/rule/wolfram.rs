@@ -0,0 +1,231 @@
+use num::{BigInt, ToPrimitive, Zero};
+
+use super::{Rule, RuleInfo, TransitionFunction};
+use crate::space::*;
+use core::fmt;
+use std::convert::TryFrom;
+
+/// The maximum number of entries in the lookup table of a Wolfram1D rule.
+const MAX_TABLE_SIZE: usize = 1 << 24;
+
+/// A 1D rule with any number of states and any radius, described by a
+/// Wolfram code.
+///
+/// The next state of a cell is determined either by the states of all the
+/// cells in its neighborhood (a "general" rule, such as Rule 110) or by the sum
+/// of those states (a "totalistic" rule). Either way, the code gives the next
+/// state for each possible neighborhood as one digit in base `states`: digit
+/// `n` is the next state for a neighborhood whose states (read left to right)
+/// spell out `n` in base `states`, or whose states sum to `n`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wolfram1D {
+    states: usize,
+    radius: usize,
+    totalistic: bool,
+    /// The next state of a cell, indexed by neighborhood.
+    table: Vec<u8>,
+}
+
+impl Wolfram1D {
+    /// Constructs a 2-state range-1 elementary cellular automaton, such as
+    /// Rule 110.
+    pub fn elementary(code: u8) -> Self {
+        Self::new(2, 1, false, (0..8).map(|i| code >> i & 1).collect())
+            .expect("Invalid elementary cellular automaton")
+    }
+    /// Constructs a rule with the given number of states and radius, given
+    /// the next state for each neighborhood.
+    ///
+    /// If `totalistic` is true, the table is indexed by the sum of the states
+    /// in the neighborhood; otherwise, it is indexed by the states in the
+    /// neighborhood read left to right as a number in base `states`.
+    pub fn new(
+        states: usize,
+        radius: usize,
+        totalistic: bool,
+        table: Vec<u8>,
+    ) -> Result<Self, String> {
+        let table_size = table_size(states, radius, totalistic)?;
+        if table.len() != table_size {
+            return Err(format!(
+                "Expected {} entries in lookup table; got {}",
+                table_size,
+                table.len(),
+            ));
+        }
+        if table.iter().any(|&next| next as usize >= states) {
+            return Err("Lookup table contains invalid states".to_owned());
+        }
+        if table[0] != 0 {
+            return Err("Rules where empty cells become live are not supported".to_owned());
+        }
+        Ok(Self {
+            states,
+            radius,
+            totalistic,
+            table,
+        })
+    }
+    /// Constructs a rule with the given number of states and radius from its
+    /// Wolfram code.
+    pub fn from_code(
+        states: usize,
+        radius: usize,
+        totalistic: bool,
+        code: &BigInt,
+    ) -> Result<Self, String> {
+        let table_size = table_size(states, radius, totalistic)?;
+        let base = BigInt::from(states);
+        let mut remaining = code.clone();
+        let mut table = Vec::with_capacity(table_size);
+        for _ in 0..table_size {
+            table.push((&remaining % &base).to_u8().unwrap());
+            remaining /= &base;
+        }
+        if !remaining.is_zero() || code < &BigInt::zero() {
+            return Err(format!("Code {} is out of range", code));
+        }
+        Self::new(states, radius, totalistic, table)
+    }
+    /// Returns the Wolfram code of this rule.
+    pub fn code(&self) -> BigInt {
+        let base = BigInt::from(self.states);
+        self.table
+            .iter()
+            .rev()
+            .fold(BigInt::zero(), |code, &next| code * &base + next)
+    }
+}
+
+/// Returns the number of entries in the lookup table for a rule with the given
+/// number of states and radius, or an error if the number of states is not
+/// supported or the table would be too large.
+fn table_size(states: usize, radius: usize, totalistic: bool) -> Result<usize, String> {
+    if !(2..=256).contains(&states) {
+        return Err(format!("{} states are not supported", states));
+    }
+    let width = radius
+        .checked_mul(2)
+        .and_then(|diameter| diameter.checked_add(1));
+    let size = width.and_then(|width| {
+        if totalistic {
+            width.checked_mul(states - 1)?.checked_add(1)
+        } else {
+            (0..width).try_fold(1_usize, |size, _| size.checked_mul(states))
+        }
+    });
+    size.filter(|&size| size <= MAX_TABLE_SIZE)
+        .ok_or_else(|| "Lookup table would be too large".to_owned())
+}
+
+impl Rule<u8, Dim1D> for Wolfram1D {
+    fn radius(&self) -> usize {
+        self.radius
+    }
+    fn get_transition_function(&self) -> TransitionFunction<'_, u8, Dim1D> {
+        let r = self.radius as isize;
+        Box::new(move |napkin| {
+            // Cells in states that are invalid for this rule are treated as
+            // empty.
+            let states = (-r..=r).map(|x| napkin[&NdVec([x])] as usize % self.states);
+            let idx = if self.totalistic {
+                states.sum()
+            } else {
+                states.fold(0, |idx, state| idx * self.states + state)
+            };
+            self.table[idx]
+        })
+    }
+    fn info(&self) -> RuleInfo {
+        RuleInfo::new(self.states)
+    }
+}
+
+impl fmt::Display for Wolfram1D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.states != 2 || self.radius != 1 || self.totalistic {
+            write!(f, "K{},R{},", self.states, self.radius)?;
+        }
+        let letter = if self.totalistic { 'T' } else { 'W' };
+        write!(f, "{}{}", letter, self.code())
+    }
+}
+
+impl TryFrom<&str> for Wolfram1D {
+    type Error = String;
+    /// Parses a 1D rulestring using a Wolfram code.
+    ///
+    /// Elementary rules are written as `W` followed by their code, such as
+    /// `W110`. Other rules may specify the number of states and radius using
+    /// `K` and `R`, and may use `T` instead of `W` for a totalistic code; for
+    /// example, `K3,R1,T1599`. The number of states defaults to 2 and the radius
+    /// defaults to 1.
+    fn try_from(s: &str) -> Result<Self, String> {
+        let mut states = 2;
+        let mut radius = 1;
+        let mut code = None;
+        for token in s.trim().split(',').map(str::trim) {
+            let mut chars = token.chars();
+            let prefix = chars.next().map(|ch| ch.to_ascii_uppercase());
+            let value = chars.as_str();
+            let parse_number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Expected a number; got '{}'", value))
+            };
+            match prefix {
+                Some('K') => states = parse_number()?,
+                Some('R') => radius = parse_number()?,
+                Some(letter @ 'W') | Some(letter @ 'T') if code.is_none() => {
+                    let value = value
+                        .parse::<BigInt>()
+                        .map_err(|_| format!("Expected a Wolfram code; got '{}'", value))?;
+                    code = Some((letter == 'T', value));
+                }
+                _ => return Err(format!("Unexpected '{}' in rulestring", token)),
+            }
+        }
+        let (totalistic, code) = code.ok_or("Missing Wolfram code in rulestring")?;
+        Self::from_code(states, radius, totalistic, &code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests parsing and formatting 1D rulestrings.
+    #[test]
+    fn test_wolfram_parse() {
+        let rule = Wolfram1D::try_from("W110").unwrap();
+        assert_eq!(rule, Wolfram1D::elementary(110));
+        assert_eq!("W110", rule.to_string());
+        assert_eq!(BigInt::from(110), rule.code());
+
+        let rule = Wolfram1D::try_from("k3,r1,t1599").unwrap();
+        assert_eq!("K3,R1,T1599", rule.to_string());
+        assert_eq!(7, rule.table.len());
+
+        let rule = Wolfram1D::try_from("K2,R2,W4294967294").unwrap();
+        assert_eq!(32, rule.table.len());
+        assert_eq!("K2,R2,W4294967294", rule.to_string());
+
+        // Out of range
+        assert!(Wolfram1D::try_from("W256").is_err());
+        assert!(Wolfram1D::try_from("K3,R1,T2187").is_err());
+        // Empty cells become live
+        assert!(Wolfram1D::try_from("W1").is_err());
+        // Too big
+        assert!(Wolfram1D::try_from("K16,R8,W2").is_err());
+        assert!(Wolfram1D::try_from("K256,R100000,T1").is_err());
+        assert!(Wolfram1D::try_from("K2,R18446744073709551615,T1").is_err());
+        assert!(Wolfram1D::try_from("K2,R9223372036854775808,W1").is_err());
+        // Unsupported
+        assert!(Wolfram1D::try_from("K0,R1,W0").is_err());
+        assert!(Wolfram1D::try_from("K1,R1,T0").is_err());
+        // Garbage
+        assert!(Wolfram1D::try_from("W110,W30").is_err());
+        assert!(Wolfram1D::try_from("R1").is_err());
+        assert!(Wolfram1D::try_from("B3/S23").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Bit-parallel simulation of Life-like rules.
+//!
+//! Each row of cells along the X axis is stored as the bits of a `u64`, and
+//! the neighbors of every cell in a row are counted at once by adding whole
+//! rows together as bit-sliced binary numbers.
+
+use crate::*;
+
+/// The maximum number of rows in a node simulated using bitwise operations.
+const MAX_ROWS: usize = 64;
+/// The number of bits needed to count the 3^D cells in the neighborhood of a
+/// cell (including the cell itself), up to three dimensions.
+const COUNT_BITS: usize = 5;
+
+/// Returns the layer at which nodes are simulated using bitwise operations:
+/// the highest layer whose branches are leaf chunks (see `LeafChunk`) and
+/// whose rows fit in a `u64`.
+pub(super) fn min_layer<D: Dim>() -> usize {
+    std::cmp::min(6 / D::NDIM + 1, 6)
+}
+
+/// Computes the inner node of a node at the layer given by `min_layer()` after
+/// the given number of generations, returning its bitboard.
+pub(super) fn advance_inner_node<C: CellType, D: Dim>(
+    rule: &LifeLike<C>,
+    node: &NdTreeNode<C, D>,
+    generations: usize,
+) -> u64 {
+    let layer = node.layer;
+    let len = 1 << layer;
+    let row_count = 1 << (layer * (D::NDIM - 1));
+    assert!(
+        generations <= len / 4,
+        "Cannot simulate {} generations of a node at layer {}",
+        generations,
+        layer,
+    );
+
+    // Gather the rows of the node from its own bitboard if it has one, or
+    // else from the bitboards of its branches.
+    let mut rows = [0; MAX_ROWS];
+    if let Some(bits) = node.bits() {
+        for (row, row_bits) in rows[..row_count].iter_mut().enumerate() {
+            *row_bits = (bits >> (row * len)) & row_mask(len);
+        }
+    } else {
+        let branches = node
+            .branches()
+            .expect("Node without a bitboard has branches");
+        let branch_mask = row_mask(len / 2);
+        for (array_idx, branch) in branches.iter().enumerate() {
+            let branch_bits = branch.node().and_then(|branch| branch.bits()).unwrap_or(0);
+            let branch_idx: ByteVec<D> = ByteVec::from_array_idx(array_idx);
+            for_each_branch_row(layer, branch_idx, |branch_bit, row, x| {
+                rows[row] |= ((branch_bits >> branch_bit) & branch_mask) << x;
+            });
+        }
+    }
+
+    // Each generation, the cells that may be wrong because of the missing
+    // cells beyond the edges get one cell closer to the center.
+    for _ in 0..generations {
+        rows = step_rows::<C, D>(rule, &rows[..row_count], len);
+    }
+
+    // Take the inner node, which is offset by a quarter of the node's length
+    // along each axis.
+    let inner_len = len / 2;
+    let offset = len / 4;
+    let inner_mask = row_mask(inner_len);
+    let mut ret = 0;
+    for inner_row in 0..1 << ((layer - 1) * (D::NDIM - 1)) {
+        let mut row = 0;
+        let mut stride = 1;
+        for ax in 1..D::NDIM {
+            let pos = (inner_row >> ((layer - 1) * (ax - 1))) % inner_len;
+            row += (pos + offset) * stride;
+            stride *= len;
+        }
+        ret |= ((rows[row] >> offset) & inner_mask) << (inner_row * inner_len);
+    }
+    ret
+}
+
+/// Simulates one generation of rows of cells in a hypercube of side length
+/// `len`. Cells beyond the edges are treated as dead, so cells near the edges
+/// may be wrong.
+fn step_rows<C: CellType, D: Dim>(rule: &LifeLike<C>, rows: &[u64], len: usize) -> [u64; MAX_ROWS] {
+    let mask = row_mask(len);
+    let neighbor_rows = 3_usize.pow(D::NDIM as u32 - 1);
+    let mut ret = [0; MAX_ROWS];
+    for (row, &live) in rows.iter().enumerate() {
+        // Count the live cells in the neighborhood of each cell, including the
+        // cell itself, as a binary number with one bit in each element.
+        let mut count = [0; COUNT_BITS];
+        'neighbor_rows: for neighbor_row in 0..neighbor_rows {
+            // Offset the row by -1, 0, or +1 along every axis except X.
+            let mut idx = 0;
+            let mut stride = 1;
+            for ax in 1..D::NDIM {
+                let pos = (row / stride) % len;
+                let offset = (neighbor_row / 3_usize.pow(ax as u32 - 1)) % 3;
+                if pos + offset < 1 || pos + offset > len {
+                    continue 'neighbor_rows;
+                }
+                idx += (pos + offset - 1) * stride;
+                stride *= len;
+            }
+            let bits = rows[idx];
+            add_bits(&mut count, bits << 1);
+            add_bits(&mut count, bits);
+            add_bits(&mut count, bits >> 1);
+        }
+
+        let mut next = 0;
+        let mut births = rule.birth;
+        while births != 0 {
+            let neighbors = births.trailing_zeros() as usize;
+            next |= !live & count_equals(&count, neighbors);
+            births &= births - 1;
+        }
+        let mut survivals = rule.survival;
+        while survivals != 0 {
+            // Live cells count themselves.
+            let neighbors = survivals.trailing_zeros() as usize;
+            next |= live & count_equals(&count, neighbors + 1);
+            survivals &= survivals - 1;
+        }
+        ret[row] = next & mask;
+    }
+    ret
+}
+
+/// Adds one to each bit-sliced counter where the corresponding bit is set.
+fn add_bits(count: &mut [u64; COUNT_BITS], mut carry: u64) {
+    for digit in count {
+        let new_carry = *digit & carry;
+        *digit ^= carry;
+        carry = new_carry;
+    }
+}
+
+/// Returns a mask of the bit-sliced counters that are equal to `n`.
+fn count_equals(count: &[u64; COUNT_BITS], n: usize) -> u64 {
+    let mut ret = !0;
+    for (i, &digit) in count.iter().enumerate() {
+        ret &= if n >> i & 1 != 0 { digit } else { !digit };
+    }
+    ret
+}
+
+/// Returns a mask of the lowest `len` bits.
+fn row_mask(len: usize) -> u64 {
+    u64::MAX >> (64 - len)
+}
@@ -0,0 +1,269 @@
+//! A simple algorithm that computes every cell individually, one generation at
+//! a time, like Golly's QuickLife.
+
+use num::{BigInt, Signed, ToPrimitive};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::*;
+
+/// A simulation that copies the pattern into an array each generation and
+/// applies the transition function to every cell in and around it.
+///
+/// Unlike HashLife, this does not cache anything, so it is never slowed down
+/// by patterns that rarely repeat. However, the time it takes is proportional
+/// to the number of generations and to the volume of the pattern.
+#[derive(Debug)]
+pub struct BruteForce<C: CellType, D: Dim> {
+    rule: Arc<dyn Rule<C, D>>,
+    topology: Topology<D>,
+    /// The estimated number of bytes of memory, if any, beyond which unused
+    /// nodes are removed from the node cache after each step.
+    memory_limit: Option<usize>,
+}
+impl<C: CellType, D: Dim> Default for BruteForce<C, D> {
+    fn default() -> Self {
+        Self::new(Arc::new(DummyRule))
+    }
+}
+
+impl<C: CellType, D: Dim> BruteForce<C, D> {
+    /// Constructs a new BruteForce simulation using the given rule.
+    pub fn from<R: 'static + Rule<C, D>>(rule: R) -> Self {
+        Self::new(Arc::new(rule))
+    }
+    /// Constructs a new BruteForce simulation using the given rule.
+    pub fn new(rule: Arc<dyn Rule<C, D>>) -> Self {
+        Self {
+            rule,
+            topology: Topology::infinite(),
+            memory_limit: Some(DEFAULT_MEMORY_LIMIT),
+        }
+    }
+
+    /// Returns this simulation with a different grid topology.
+    pub fn with_topology(mut self, topology: Topology<D>) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Advances the given NdTree by one generation, or returns an error if
+    /// the pattern is too large to copy into an array.
+    fn step_generation<'a>(
+        &'a self,
+        tree: &mut NdTree<C, D>,
+        transition_function: &mut TransitionFunction<'a, C, D>,
+    ) -> Result<(), StepError> {
+        let r = self.rule.radius() as isize;
+        let topology = &self.topology;
+        let fully_bounded = D::axes().iter().all(|&ax| topology.is_bounded(ax));
+        let old_background = tree.get_background().clone();
+        let tile_len = 1 << old_background.get_layer();
+
+        // Only cells within `r` of the cells that differ from the background
+        // can become different from the background, so only copy the
+        // smallest node containing those.
+        let pattern_rect = tree
+            .get_bounding_rect()
+            .unwrap_or_else(|| NdRect::single_cell(tree.slice.offset.clone()));
+        let old_slice = tree.get_slice_containing(&pattern_rect);
+        if 2_isize
+            .checked_pow((old_slice.root.layer * D::NDIM) as u32)
+            .is_none()
+        {
+            return Err(StepError::PatternTooLarge);
+        }
+        // In an infinite grid, positions are relative to the pattern so that
+        // it can be anywhere. Along the bounded axes of other grids, positions
+        // must be inside the grid anyway.
+        let origin = if topology.is_infinite() {
+            old_slice.offset.clone()
+        } else {
+            BigVec::origin()
+        };
+        let to_ivec = |pos: BigVec<D>| -> Result<IVec<D>, StepError> {
+            let mut ret = IVec::origin();
+            for &ax in D::axes() {
+                ret[ax] = pos[ax].to_isize().ok_or(StepError::PatternTooLarge)?;
+            }
+            Ok(ret)
+        };
+        let pattern_rect = NdRect::span(
+            to_ivec(pattern_rect.min() - &origin)?,
+            to_ivec(pattern_rect.max() - &origin)?,
+        );
+        let old_offset = to_ivec(old_slice.offset - &origin)?;
+        // The background tile is aligned with the root node.
+        let tile_offset =
+            to_ivec((&tree.slice.offset - &origin).mod_floor(&BigInt::from(tile_len)))?;
+        let old_cells = NdArray::from(&old_slice.root);
+        let old_len = old_cells.size()[X] as isize;
+        let get_old_cell = |pos: &IVec<D>| {
+            let array_pos = pos - old_offset.clone();
+            if D::axes()
+                .iter()
+                .all(|&ax| 0 <= array_pos[ax] && array_pos[ax] < old_len)
+            {
+                return old_cells[&array_pos];
+            }
+            match &old_background {
+                NdTreeBranch::Leaf(cell_state) => *cell_state,
+                NdTreeBranch::Node(tile) => tile.get_cell(&(pos - tile_offset.clone())),
+            }
+        };
+        let new_rect = topology.rect(&pattern_rect.offset_min_max(-r, r));
+
+        // Copy every cell that those cells depend on into an array.
+        let input_rect = new_rect.clone().offset_min_max(-r, r);
+        let input_min = input_rect.min();
+        let input_cells = Rc::new(NdArray::from_fn(
+            input_rect.size().as_uvec(),
+            |pos| match topology.wrap_pos(&(&input_min + pos)) {
+                Some(pos) => get_old_cell(&pos),
+                None => C::default(),
+            },
+        ));
+
+        // Compute the next state of each of those cells.
+        let new_cells = NdArray::from_fn(new_rect.size().as_uvec(), |pos| {
+            transition_function(input_cells.clone().offset_slice(-pos - r))
+        });
+
+        // Compute the next state of the background. Cells beyond the edges of
+        // a bounded grid are always in the default state.
+        let new_background = match &old_background {
+            _ if fully_bounded => NdTreeBranch::Leaf(C::default()),
+            NdTreeBranch::Leaf(cell_state) => {
+                let napkin = NdArray::from_fn(UVec::repeat(2 * r as usize + 1), |_| *cell_state);
+                NdTreeBranch::Leaf(transition_function(
+                    Rc::new(napkin).offset_slice(IVec::repeat(-r)),
+                ))
+            }
+            NdTreeBranch::Node(tile) => {
+                assert!(
+                    topology.is_infinite(),
+                    "Periodic backgrounds are not supported in bounded grids"
+                );
+                // The tile repeats, so each cell's neighborhood wraps around
+                // the edges of the tile.
+                let napkin = Rc::new(NdArray::from_fn(
+                    UVec::repeat((tile_len + 2 * r) as usize),
+                    |pos| tile.get_cell(&(pos - r)),
+                ));
+                NdTreeBranch::Node(tree.cache.get_small_node_from_cell_fn(
+                    tile.layer,
+                    NdVec::origin(),
+                    &mut |pos| transition_function(napkin.clone().offset_slice(-pos - r)),
+                ))
+            }
+        };
+
+        // Build a new root node containing the new cells, aligned with the
+        // background tile (if there is one).
+        let background_layer = new_background.get_layer();
+        let tile_len = 1 << background_layer;
+        let new_min = IVec::from_fn(|ax| {
+            tile_offset[ax] + (new_rect.min()[ax] - tile_offset[ax]).div_euclid(tile_len) * tile_len
+        });
+        let new_size = new_rect.max() - new_min.clone() + 1;
+        let mut layer = background_layer + 1;
+        while D::axes().iter().any(|&ax| (1 << layer) < new_size[ax]) {
+            layer += 1;
+        }
+        let root = tree
+            .cache
+            .get_small_node_from_cell_fn(layer, new_min.clone(), &mut |pos| {
+                if new_rect.contains(&pos) {
+                    new_cells[&(pos - new_rect.min())]
+                } else if topology.wrap_pos(&pos).as_ref() != Some(&pos) {
+                    // Cells beyond the edges of the grid are cleared.
+                    C::default()
+                } else {
+                    match &new_background {
+                        NdTreeBranch::Leaf(cell_state) => *cell_state,
+                        NdTreeBranch::Node(tile) => tile.get_cell(&(pos - tile_offset.clone())),
+                    }
+                }
+            });
+        tree.slice = NdTreeSlice {
+            root,
+            offset: new_min.convert::<BigInt>() + origin,
+        };
+        tree.set_background(new_background);
+        // Shrink the tree as much as possible to avoid wasted space.
+        tree.shrink();
+        Ok(())
+    }
+}
+
+impl<C: CellType, D: Dim> Algorithm<C, D> for BruteForce<C, D> {
+    fn kind(&self) -> AlgorithmKind {
+        AlgorithmKind::BruteForce
+    }
+    fn rule(&self) -> &Arc<dyn Rule<C, D>> {
+        &self.rule
+    }
+    fn topology(&self) -> &Topology<D> {
+        &self.topology
+    }
+    fn set_topology(&mut self, topology: Topology<D>) {
+        self.topology = topology;
+    }
+    fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+    fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.memory_limit = memory_limit;
+    }
+    fn memory_stats(&self, cache: &NdTreeCache<C, D>) -> MemoryStats {
+        MemoryStats {
+            node_count: cache.node_count(),
+            result_count: 0,
+            estimated_bytes: cache.estimated_bytes(),
+            collections: cache.collection_count(),
+        }
+    }
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        step_size: &BigInt,
+        control: &StepControl,
+    ) -> Result<(), StepError> {
+        assert!(
+            step_size.is_positive(),
+            "Step size must be a positive integer"
+        );
+        let old_slice = tree.slice.clone();
+        let old_background = tree.get_background().clone();
+        let mut transition_function = self.rule.get_transition_function();
+        let mut progress = StepProgress {
+            completed: 0,
+            estimated_total: step_size.to_usize().unwrap_or(usize::MAX),
+        };
+        let mut remaining = step_size.clone();
+        let mut result = Ok(());
+        while remaining.is_positive() {
+            result = control
+                .check()
+                .map_err(StepError::from)
+                .and_then(|()| self.step_generation(tree, &mut transition_function));
+            if result.is_err() {
+                break;
+            }
+            remaining -= 1;
+            progress.completed += 1;
+            control.report_progress(progress);
+        }
+        if result.is_err() {
+            // Put back the original pattern.
+            tree.slice = old_slice;
+            tree.set_background(old_background);
+        }
+        if let Some(memory_limit) = self.memory_limit {
+            if self.memory_stats(&tree.cache).estimated_bytes > memory_limit {
+                tree.cache.collect_garbage(|_, _| ());
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,126 @@
+//! Cancellation and progress reporting for long simulation steps.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The number of units of work between calls to a progress callback.
+pub(super) const PROGRESS_INTERVAL: usize = 1024;
+
+/// How far along a simulation step is.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct StepProgress {
+    /// The number of units of work completed so far. For HashLife this is the
+    /// number of nodes computed; for the brute-force algorithm it is the
+    /// number of generations.
+    pub completed: usize,
+    /// A rough estimate of the total number of units of work in the step,
+    /// which `completed` may exceed.
+    pub estimated_total: usize,
+}
+impl StepProgress {
+    /// Returns the estimated fraction of the step that is complete, from 0.0
+    /// up to (but never reaching) 1.0.
+    pub fn fraction(self) -> f64 {
+        if self.estimated_total == 0 {
+            return 0.0;
+        }
+        let fraction = self.completed as f64 / self.estimated_total as f64;
+        fraction.min(0.99)
+    }
+}
+
+/// The error returned by a simulation step that was cancelled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StepCancelled;
+impl fmt::Display for StepCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Simulation step was cancelled")
+    }
+}
+
+/// The error returned by a simulation step that did not finish.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StepError {
+    /// The step was cancelled using a StepControl.
+    Cancelled,
+    /// The pattern is too large for the simulation algorithm.
+    PatternTooLarge,
+}
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "{}", StepCancelled),
+            Self::PatternTooLarge => write!(f, "Pattern is too large for this algorithm"),
+        }
+    }
+}
+impl From<StepCancelled> for StepError {
+    fn from(_: StepCancelled) -> Self {
+        Self::Cancelled
+    }
+}
+
+/// A handle used to cancel a simulation step, possibly from another thread,
+/// and to receive reports of its progress.
+///
+/// Clones of a StepControl share the same cancellation state, so cancelling
+/// one cancels them all. Once cancelled, a StepControl stays cancelled.
+#[derive(Clone, Default)]
+pub struct StepControl {
+    cancelled: Arc<AtomicBool>,
+    progress_callback: Option<Arc<dyn Fn(StepProgress) + Send + Sync>>,
+}
+impl fmt::Debug for StepControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StepControl")
+            .field("cancelled", &self.is_cancelled())
+            .field("has_progress_callback", &self.has_progress_callback())
+            .finish()
+    }
+}
+impl StepControl {
+    /// Constructs a new StepControl that has not been cancelled and does not
+    /// report progress.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns this StepControl with a function that is called periodically
+    /// (possibly from several threads at once) with the progress of the step.
+    pub fn with_progress_callback(
+        mut self,
+        callback: impl 'static + Fn(StepProgress) + Send + Sync,
+    ) -> Self {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Cancels any simulation step using this StepControl or a clone of it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+    /// Returns true if the step has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+    /// Returns an error if the step has been cancelled.
+    pub fn check(&self) -> Result<(), StepCancelled> {
+        if self.is_cancelled() {
+            Err(StepCancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns true if there is a progress callback, so that it is worth
+    /// estimating the amount of work in a step.
+    pub fn has_progress_callback(&self) -> bool {
+        self.progress_callback.is_some()
+    }
+    /// Calls the progress callback, if there is one.
+    pub fn report_progress(&self, progress: StepProgress) {
+        if let Some(callback) = &self.progress_callback {
+            callback(progress);
+        }
+    }
+}
@@ -0,0 +1,925 @@
+//! HashLife, which simulates large and repetitive patterns quickly by caching
+//! the results of simulating each node.
+
+use dashmap::DashMap;
+use num::{BigInt, Signed, ToPrimitive};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+
+use super::control::PROGRESS_INTERVAL;
+use super::lookup::LookupTable;
+use super::*;
+
+/// The default limit on the estimated memory used by a simulation and its node
+/// cache, in bytes, beyond which garbage is collected automatically.
+pub const DEFAULT_MEMORY_LIMIT: usize = 512 * 1024 * 1024;
+
+/// Nodes at least this many layers above the minimum simulation layer have
+/// their branches simulated in parallel. Below that, the overhead of splitting
+/// up the work outweighs the benefit.
+const PARALLEL_MIN_HEIGHT: usize = 3;
+
+/// A HashLife simulation of a given automaton that caches simulation results.
+#[derive(Debug)]
+pub struct Simulation<C: CellType, D: Dim> {
+    rule: Arc<dyn Rule<C, D>>,
+    topology: Topology<D>,
+    min_layer: usize,
+    /// The rule as a Life-like rule, if it is one and can be simulated using
+    /// bitwise operations at the minimum layer.
+    life_like: Option<LifeLike<C>>,
+    /// The rule's table of the next state of a cell for each neighborhood, if
+    /// it has few enough states for one.
+    lookup_table: Option<Arc<LookupTable<C>>>,
+    results: ResultsCache<C, D>,
+    /// The state of a cell whose entire neighborhood is in a given state after
+    /// `2^k` generations, for each state and `k` that has been seen.
+    uniform_transitions: DashMap<(C, usize), C>,
+    /// The estimated number of bytes of memory, if any, beyond which garbage
+    /// is collected after each step.
+    memory_limit: Option<usize>,
+}
+
+/// Statistics about the memory used by a simulation and its node cache.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The number of nodes in the node cache.
+    pub node_count: usize,
+    /// The number of cached simulation results.
+    pub result_count: usize,
+    /// The estimated number of bytes used by nodes, simulation results, and
+    /// the lookup table of the rule, if it has one.
+    pub estimated_bytes: usize,
+    /// The number of times that garbage has been collected from the node
+    /// cache.
+    pub collections: usize,
+}
+impl<C: 'static + CellType, D: Dim> Algorithm<C, D> for Simulation<C, D> {
+    fn kind(&self) -> AlgorithmKind {
+        AlgorithmKind::HashLife
+    }
+    fn rule(&self) -> &Arc<dyn Rule<C, D>> {
+        self.rule()
+    }
+    fn topology(&self) -> &Topology<D> {
+        self.topology()
+    }
+    fn set_topology(&mut self, topology: Topology<D>) {
+        self.set_topology(topology);
+    }
+    fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit()
+    }
+    fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.set_memory_limit(memory_limit);
+    }
+    fn memory_stats(&self, cache: &NdTreeCache<C, D>) -> MemoryStats {
+        self.memory_stats(cache)
+    }
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        step_size: &BigInt,
+        control: &StepControl,
+    ) -> Result<(), StepError> {
+        self.try_step(tree, step_size, control)
+    }
+}
+impl<C: 'static + CellType, D: Dim> Default for Simulation<C, D> {
+    fn default() -> Self {
+        Self::new(Arc::new(DummyRule))
+    }
+}
+
+impl<C: 'static + CellType, D: Dim> Simulation<C, D> {
+    /// Constructs a new Simulation using the given rule.
+    pub fn from<R: 'static + Rule<C, D>>(rule: R) -> Self {
+        Self::new(Arc::new(rule))
+    }
+    /// Constructs a new Simulation using the given rule.
+    pub fn new(rule: Arc<dyn Rule<C, D>>) -> Self {
+        // Determine the minimum layer at which we can simulate one generation
+        // of the automaton, using `n / 4 >= r`. (See the documentation for
+        // Simulation::advance_inner_node() for an explanation.) Even at r=0 or
+        // r=1, the minimum layer is 2 because we need to return the inner node
+        // (which is at a lower layer) and the minimum layer is 1.
+        let mut min_layer = 2;
+        while (1 << min_layer) / 4 < rule.radius() {
+            min_layer += 1;
+        }
+        // Life-like rules are simulated using bitwise operations on larger
+        // nodes instead.
+        let life_like = rule.as_life_like().filter(|_| D::NDIM <= 3);
+        if life_like.is_some() {
+            min_layer = bitwise::min_layer::<D>();
+        }
+        // Otherwise, rules with few enough states have the next state of each
+        // neighborhood stored in a table as it is computed.
+        let lookup_table = match life_like {
+            Some(_) => None,
+            None => LookupTable::for_rule(&rule),
+        };
+
+        Self {
+            rule,
+            topology: Topology::infinite(),
+            min_layer,
+            life_like,
+            lookup_table,
+            results: ResultsCache::default(),
+            uniform_transitions: DashMap::new(),
+            memory_limit: Some(DEFAULT_MEMORY_LIMIT),
+        }
+    }
+
+    /// Returns this simulation with a different grid topology.
+    pub fn with_topology(mut self, topology: Topology<D>) -> Self {
+        self.set_topology(topology);
+        self
+    }
+
+    /// Returns this simulation with a different memory limit.
+    pub fn with_memory_limit(mut self, memory_limit: Option<usize>) -> Self {
+        self.set_memory_limit(memory_limit);
+        self
+    }
+
+    /// Returns the rule used by this simulation.
+    pub fn rule(&self) -> &Arc<dyn Rule<C, D>> {
+        &self.rule
+    }
+    /// Returns the topology of the grid, which is infinite by default.
+    pub fn topology(&self) -> &Topology<D> {
+        &self.topology
+    }
+    /// Sets the topology of the grid. Cells outside of a bounded grid are
+    /// cleared the next time the simulation is stepped.
+    pub fn set_topology(&mut self, topology: Topology<D>) {
+        self.topology = topology;
+    }
+
+    /// Returns the estimated number of bytes of memory beyond which garbage is
+    /// collected after each step, or None if there is no limit.
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+    /// Sets the estimated number of bytes of memory beyond which garbage is
+    /// collected after each step, or None for no limit.
+    pub fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.memory_limit = memory_limit;
+    }
+
+    /// Returns statistics about the memory used by this simulation and the
+    /// given node cache.
+    pub fn memory_stats(&self, cache: &NdTreeCache<C, D>) -> MemoryStats {
+        MemoryStats {
+            node_count: cache.node_count(),
+            result_count: self.results.len(),
+            estimated_bytes: cache.estimated_bytes()
+                + self.results.estimated_bytes()
+                + self
+                    .lookup_table
+                    .as_ref()
+                    .map_or(0, |table| table.estimated_bytes()),
+            collections: cache.collection_count(),
+        }
+    }
+    /// Removes nodes that are no longer in use from the given node cache,
+    /// along with simulation results for them.
+    ///
+    /// Every node that is reachable from an NdTree (or anything else holding a
+    /// reference to a node, such as an undo history entry) is kept, as are
+    /// the cached results of simulating those nodes.
+    pub fn collect_garbage(&mut self, cache: &NdTreeCache<C, D>) {
+        // Hold the results weakly so that they do not keep nodes in use, and
+        // then only keep the results of simulating nodes that are still in use.
+        let weak_results = self.results.take_weak();
+        cache.collect_garbage(|node, reachable| {
+            if let Some(results) = weak_results.get(&Arc::as_ptr(node)) {
+                reachable.extend(results.iter().filter_map(|(_, _, result)| result.upgrade()));
+            }
+        });
+        self.results.restore_weak(weak_results);
+    }
+
+    /// Advances the given NdTree by the given number of generations.
+    pub fn step(&mut self, tree: &mut NdTree<C, D>, step_size: &BigInt) {
+        self.try_step(tree, step_size, &StepControl::new())
+            .expect("Simulation step was cancelled without a way to cancel it");
+    }
+    /// Advances the given NdTree by the given number of generations, stopping
+    /// early if the step is cancelled using `control`.
+    ///
+    /// If the step is cancelled, the tree is left unchanged. Only the results
+    /// of fully simulating a node are cached, so the cache stays consistent
+    /// and any work that was finished is reused by the next step.
+    pub fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        step_size: &BigInt,
+        control: &StepControl,
+    ) -> Result<(), StepError> {
+        assert!(
+            step_size.is_positive(),
+            "Step size must be a positive integer"
+        );
+        let old_slice = tree.slice.clone();
+        let old_background = tree.get_background().clone();
+        let state = StepState::new(control);
+        let result = if self.topology.is_infinite() {
+            self.step_infinite(tree, step_size, &state)
+        } else {
+            self.step_bounded(tree, step_size, &state)
+        };
+        if result.is_err() {
+            // Put back the original pattern.
+            tree.slice = old_slice;
+            tree.set_background(old_background);
+        }
+        if let Some(memory_limit) = self.memory_limit {
+            if self.memory_stats(&tree.cache).estimated_bytes > memory_limit {
+                self.collect_garbage(&tree.cache);
+            }
+        }
+        result.map_err(StepError::from)
+    }
+
+    /// Advances the given NdTree by the given number of generations in a
+    /// bounded grid.
+    ///
+    /// Each step copies the grid into a node padded on every side with the
+    /// cells that will wrap around into it, simulates that node as if it were
+    /// infinite, and then keeps only the cells inside the grid. The padding
+    /// must be at least `r * t`, so the number of generations simulated at
+    /// once is limited by the topology.
+    fn step_bounded(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        step_size: &BigInt,
+        state: &StepState,
+    ) -> Result<(), StepCancelled> {
+        let topology = self.topology.clone();
+        let r = self.rule.radius();
+        // Cells beyond the edges of the grid are in the default state, but
+        // along infinite axes the background still applies.
+        let bounded_background = |tree: &NdTree<C, D>| {
+            if D::axes().iter().all(|&ax| topology.is_bounded(ax)) {
+                C::default()
+            } else {
+                tree.get_background()
+                    .leaf()
+                    .expect("Periodic backgrounds are not supported in bounded grids")
+            }
+        };
+        // Returns the smallest layer of node that can contain the rectangle.
+        let layer_containing = |rect: &IRect<D>| {
+            let size = rect.size();
+            let max_len = size[size.max_axis(|_, len| *len)];
+            let mut layer = 1;
+            while (1 << layer) < max_len {
+                layer += 1;
+            }
+            layer
+        };
+        let mut remaining = step_size.clone();
+        while remaining.is_positive() {
+            let t = match topology.max_step_size(r) {
+                Some(max) => std::cmp::min(remaining.clone(), BigInt::from(max)),
+                None => remaining.clone(),
+            };
+            let padding = (&t * r)
+                .to_isize()
+                .expect("Too many generations to simulate at once");
+            let iterations_left: BigInt = (&remaining + &t - 1) / &t;
+            state.iterations_left.store(
+                iterations_left.to_usize().unwrap_or(usize::MAX),
+                Ordering::Relaxed,
+            );
+
+            // Copy the grid into a node padded by `r * t` on every side.
+            let background = bounded_background(tree);
+            let grid = topology.rect(&tree.slice.rect().as_irect());
+            let padded = grid.clone().offset_min_max(-padding, padding);
+            let root = tree.cache.get_small_node_from_cell_fn(
+                layer_containing(&padded),
+                padded.min(),
+                &mut |pos| match topology.wrap_pos(&pos) {
+                    Some(pos) => tree.get_cell(&pos.convert()),
+                    None => C::default(),
+                },
+            );
+            tree.slice = NdTreeSlice {
+                root,
+                offset: padded.min().convert(),
+            };
+            tree.set_background(NdTreeBranch::Leaf(background));
+
+            self.step_infinite(tree, &t, state)?;
+
+            // Keep only the cells inside the grid.
+            let background = bounded_background(tree);
+            let grid = topology.rect(&tree.slice.rect().as_irect());
+            let root = tree.cache.get_small_node_from_cell_fn(
+                layer_containing(&grid),
+                grid.min(),
+                &mut |pos| {
+                    // Along infinite axes, cells outside of the pattern are
+                    // still part of the grid.
+                    if topology.wrap_pos(&pos).as_ref() == Some(&pos) {
+                        tree.get_cell(&pos.convert())
+                    } else {
+                        C::default()
+                    }
+                },
+            );
+            tree.slice = NdTreeSlice {
+                root,
+                offset: grid.min().convert(),
+            };
+            tree.set_background(NdTreeBranch::Leaf(background));
+            tree.shrink();
+
+            remaining -= t;
+        }
+        Ok(())
+    }
+
+    /// Advances the given NdTree by the given number of generations in an
+    /// infinite grid.
+    ///
+    /// The step is split into steps of powers of two (one for each bit of
+    /// `step_size`), and only those are cached. Results cached while using one
+    /// step size are therefore reused by every other step size.
+    fn step_infinite(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        step_size: &BigInt,
+        state: &StepState,
+    ) -> Result<(), StepCancelled> {
+        // Prepare the transition function.
+        let mut transition_function = self.rule.get_transition_function();
+        let powers = powers_of_two(step_size);
+        for (i, &generations_log2) in powers.iter().enumerate() {
+            let sub_steps_left = powers.len() - i;
+            self.step_infinite_pow2(
+                tree,
+                generations_log2,
+                &mut transition_function,
+                state,
+                sub_steps_left,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Advances the given NdTree by `2^generations_log2` generations in an
+    /// infinite grid.
+    fn step_infinite_pow2<'a>(
+        &'a self,
+        tree: &mut NdTree<C, D>,
+        generations_log2: usize,
+        transition_function: &mut TransitionFunction<'a, C, D>,
+        state: &StepState,
+        sub_steps_left: usize,
+    ) -> Result<(), StepCancelled> {
+        // Expand out to the sphere of influence of the existing pattern,
+        // following `expansion_distance >= r * t` (rounding `r` up to the
+        // next-highest power of two, and `t` up to the next power of two after
+        // that).
+        let min_expansion_distance =
+            BigInt::from(1) << (BigInt::from(self.rule.radius()).bits() + generations_log2 + 1);
+        let mut expansion_distance = BigInt::from(0);
+        while expansion_distance < min_expansion_distance {
+            tree.expand();
+            expansion_distance += tree.get_root().len() / 4;
+        }
+        // Now expand one more layer to guarantee that the sphere of influence
+        // is within the inner node, because Simulation::advance_inner_node()
+        // must always returns a node one layer lower than its input. (This also
+        // ensures that we aren't somehow still at layer 1; we need to be at at
+        // least layer 2 so that the result can be at layer 1, which is the
+        // minimum layer for a node.)
+        tree.expand();
+        // A periodic background must still fit inside the result, which is one
+        // layer lower.
+        while tree.get_root().layer < tree.get_background().get_layer() + 2 {
+            tree.expand();
+        }
+        // The root must also be large enough to simulate at all.
+        while tree.get_root().layer < self.min_layer {
+            tree.expand();
+        }
+        if state.control.has_progress_callback() {
+            let nodes = self.estimate_nodes(&tree.slice.root);
+            state.add_estimate(nodes.saturating_mul(sub_steps_left));
+        }
+        // Now do the actual simulation.
+        let new_node = self.advance_inner_node(
+            &tree.cache,
+            &tree.slice.root,
+            generations_log2,
+            transition_function,
+            state,
+        )?;
+        let new_background = match tree.get_background().clone() {
+            NdTreeBranch::Leaf(cell_state) => NdTreeBranch::Leaf(self.advance_uniform_state_pow2(
+                cell_state,
+                generations_log2,
+                transition_function,
+            )),
+            // Simulate a periodic background just like the pattern, using a node
+            // made entirely of copies of the tile. The result is still periodic
+            // and aligned with the new root node, so take the tile from its
+            // corner.
+            tile @ NdTreeBranch::Node(_) => {
+                let tiled_node = tree.cache.get_tiled_branch(tree.get_root().layer, &tile);
+                let mut ret = self.advance_inner_node(
+                    &tree.cache,
+                    tiled_node.node().unwrap(),
+                    generations_log2,
+                    transition_function,
+                    state,
+                )?;
+                while ret.layer > tile.get_layer() {
+                    ret = ret
+                        .get_branch(&tree.cache, ByteVec::origin())
+                        .node()
+                        .unwrap()
+                        .clone();
+                }
+                NdTreeBranch::Node(ret)
+            }
+        };
+        tree.set_root_centered(new_node);
+        tree.set_background(new_background);
+        // Shrink the tree as much as possible to avoid wasted space.
+        tree.shrink();
+        Ok(())
+    }
+
+    /// Returns a rough estimate of the number of nodes that must be computed
+    /// to simulate the given node.
+    ///
+    /// Each distinct node in the tree is simulated at most once per step size,
+    /// along with the nodes that overlap it, which are roughly `(3/2)^D` times
+    /// as numerous as the nodes they are made from.
+    fn estimate_nodes(&self, root: &NdCachedNode<C, D>) -> usize {
+        let mut seen = std::collections::HashSet::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if node.layer <= self.min_layer
+                || node.uniform_state().is_some()
+                || !seen.insert(Arc::as_ptr(node))
+            {
+                continue;
+            }
+            stack.extend(node.child_nodes());
+        }
+        (seen.len() * 3usize.pow(D::NDIM as u32)) >> D::NDIM
+    }
+
+    /// Computes the inner node for a given node after `2^generations_log2`
+    /// generations.
+    ///
+    /// A node's inner node is the node one layer down, centered on the original
+    /// node. For example, the inner node of a 16x16 node (layer 4) is the 8x8
+    /// node (layer 3) centered on it. HashLife always performs calculations
+    /// like this; a node is progressed some distance into the future, and the
+    /// state of its inner node is the result. This is because without outside
+    /// information, it is impossible to predict the state of the entire node
+    /// (since adjacent cells outside of the node could affect it), but it is
+    /// always possible in theory to predict the inner node of a node with
+    /// length `n` after `t` generations using a rule with max neighborhood
+    /// radius `r` if `n / 4 >= r * t`. (`r` defines the maximum speed that
+    /// information can travel, so `r * t` is the distance that information can
+    /// travel in time `t`, and `n / 4` is the distance from any edge of the
+    /// inner node to the edge of the outer node.) In practice, however, each
+    /// layer must be computed separately, so the `r` and `t` must each be
+    /// replaced with their next lowest power of two.
+    ///
+    /// Only steps of powers of two are simulated here, so `t` can always be
+    /// split evenly in half, and results are cached by the exponent.
+    fn advance_inner_node<'a>(
+        &'a self,
+        cache: &NdTreeCache<C, D>,
+        node: &NdCachedNode<C, D>,
+        generations_log2: usize,
+        transition_function: &mut TransitionFunction<'a, C, D>,
+        state: &StepState,
+    ) -> Result<NdCachedNode<C, D>, StepCancelled> {
+        // If every cell in the node is the same, then in the future every cell
+        // will still be the same (although it may be a different state, for
+        // rules with "B0" behavior). This is not strictly necessary, but it is
+        // an obvious optimization, especially for empty space.
+        if let Some(cell_state) = node.uniform_state() {
+            let new_state =
+                self.advance_uniform_state_pow2(cell_state, generations_log2, transition_function);
+            if new_state == cell_state {
+                // Rather than building a new node, just take one of the
+                // branches of this one.
+                return Ok(node
+                    .get_branch(cache, ByteVec::origin())
+                    .node()
+                    .unwrap()
+                    .clone());
+            }
+            return Ok(cache.get_filled_node(node.layer - 1, new_state));
+        }
+
+        // If the result is already in the cache, just return that.
+        if let Some(result) = self.results.get_result(node, generations_log2) {
+            return Ok(result);
+        }
+
+        // Stop if the step has been cancelled. Nothing unfinished is cached, so
+        // this can happen anywhere.
+        state.control.check()?;
+
+        // Otherwise make sure we're above the minimum layer.
+        assert!(
+            node.layer >= self.min_layer,
+            "Cannot advance inner node at layer below minimum simulation layer"
+        );
+
+        let ret;
+
+        // If this is the minimum layer, just compute each cell manually. This
+        // is the recursive base case.
+        if node.layer == self.min_layer {
+            ret = match &self.life_like {
+                // The result is stored as a leaf chunk straight from its
+                // bitboard, without creating any of its branches.
+                Some(life_like) => {
+                    let bits = bitwise::advance_inner_node(life_like, node, 1 << generations_log2);
+                    cache.get_node_from_bits(node.layer - 1, bits, life_like.live_state)
+                }
+                None => {
+                    assert_eq!(
+                        0, generations_log2,
+                        "Cannot simulate more than 1 generation at minimum layer"
+                    );
+                    // Look up the next state of each cell in the rule's table
+                    // if it has one, or else call the transition function.
+                    self.lookup_table
+                        .as_ref()
+                        .and_then(|table| {
+                            table.advance_inner_node(
+                                cache,
+                                node,
+                                self.rule.radius(),
+                                transition_function,
+                            )
+                        })
+                        .unwrap_or_else(|| {
+                            self.advance_min_layer_node(cache, node, transition_function)
+                        })
+                }
+            };
+        } else {
+            // In the algorithm described below, there are two `t/2`s that must
+            // add up to `t` (where `t` is the number of generations to
+            // simulate). If `t` is 1, then instead the first step simulates 1
+            // generation and the second step simulates none (`t_inner` is
+            // None). Both are given as exponents of two.
+            let (t_outer, t_inner) = match generations_log2 {
+                0 => (0, None),
+                k => (k - 1, Some(k - 1)),
+            };
+
+            // Let `L` be the layer of the current node, and let `t` be the
+            // number of generations to simulate. Colors refer to Figure 4 in
+            // this article: https://www.drdobbs.com/jvm/_/184406478.
+            //
+            // The branches of each node built in steps #4 and #6 can be
+            // computed independently, so large nodes compute them in parallel.
+            let layer = node.layer;
+            ret = self.get_node_from_fn(
+                cache,
+                layer,
+                transition_function,
+                |tf, final_branch_idx| {
+                    let node_halfway =
+                        self.get_node_from_fn(cache, layer, tf, |tf, inner_branch_idx| {
+                            let node_intial = cache.get_node_from_fn(|outer_branch_idx| {
+                                // 1. Grab sub-branches at layer `L-2` of the original
+                                //    node at time `0`.
+                                node.get_sub_branch(
+                                    cache,
+                                    final_branch_idx.clone()
+                                        + inner_branch_idx.clone()
+                                        + outer_branch_idx,
+                                )
+                                // 2. Use these branches to make a node at layer `L-1` and
+                                //    time `0`.
+                            });
+                            // 3. Simulate that node to get a new node at layer `L-2`
+                            //    and time `t/2` (red squares).
+                            Ok(NdTreeBranch::Node(self.advance_inner_node(
+                                cache,
+                                &node_intial,
+                                t_outer,
+                                tf,
+                                state,
+                            )?))
+                            // 4. Using branches from step #3, create a node at layer
+                            //    `L-1` and time `t/2`.
+                        })?;
+                    // 5. Simulate that node to get a new node at layer `L-2` and
+                    //    time `t` (green squares).
+                    Ok(NdTreeBranch::Node(match t_inner {
+                        Some(t_inner) => {
+                            self.advance_inner_node(cache, &node_halfway, t_inner, tf, state)?
+                        }
+                        None => node_halfway.get_inner_node(cache),
+                    }))
+                    // 6. Using branches from step #5, create a new node at layer
+                    //    `L-1` and time `t` (blue square). This is the final
+                    //    result.
+                },
+            )?;
+        }
+
+        // Add the result to the cache so we don't have to do all that work next
+        // time.
+        self.results
+            .set_result(node.clone(), generations_log2, ret.clone());
+        state.node_computed();
+        Ok(ret)
+    }
+
+    /// Computes the inner node of a node at the minimum layer after one
+    /// generation by calling the transition function on each cell.
+    fn advance_min_layer_node<'a>(
+        &'a self,
+        cache: &NdTreeCache<C, D>,
+        node: &NdTreeNode<C, D>,
+        transition_function: &mut TransitionFunction<'a, C, D>,
+    ) -> NdCachedNode<C, D> {
+        let old_cell_ndarray = Rc::new(NdArray::from(node));
+        let base_offset = 1 << (node.layer - 2);
+        cache.get_small_node_from_cell_fn(node.layer - 1, NdVec::origin(), &mut |pos| {
+            let slice = old_cell_ndarray.clone().offset_slice(-&pos - base_offset);
+            transition_function(slice)
+        })
+    }
+
+    /// Returns a cached node at the given layer, using a function to generate
+    /// each branch. If the layer is high enough, the branches are generated in
+    /// parallel, each thread using its own transition function.
+    fn get_node_from_fn<'a>(
+        &'a self,
+        cache: &NdTreeCache<C, D>,
+        layer: usize,
+        transition_function: &mut TransitionFunction<'a, C, D>,
+        generator: impl Fn(
+                &mut TransitionFunction<'a, C, D>,
+                ByteVec<D>,
+            ) -> Result<NdTreeBranch<C, D>, StepCancelled>
+            + Send
+            + Sync,
+    ) -> Result<NdCachedNode<C, D>, StepCancelled> {
+        // This recurses through every layer of the node, so the results are
+        // collected in simple steps to keep stack frames small.
+        let branches = if layer >= self.min_layer + PARALLEL_MIN_HEIGHT {
+            let mut results = (0..D::TREE_BRANCHES)
+                .into_par_iter()
+                .map(ByteVec::from_array_idx)
+                .map_init(|| self.rule.get_transition_function(), generator)
+                .collect::<Vec<_>>()
+                .into_iter();
+            NdTreeBranches::<C, D>::try_from_fn(|_| results.next().unwrap())?
+        } else {
+            NdTreeBranches::<C, D>::try_from_fn(|branch_idx| {
+                generator(transition_function, ByteVec::from_array_idx(branch_idx))
+            })?
+        };
+        Ok(cache.get_node(branches))
+    }
+
+    /// Returns the state that an infinite grid of cells all in the given state
+    /// will have after the given number of generations.
+    ///
+    /// This is how the background of the grid evolves. For most rules it stays
+    /// the same, but in rules with "B0" behavior it may alternate between
+    /// states.
+    pub fn advance_background(&self, background: C, generations: &BigInt) -> C {
+        let mut transition_function = self.rule.get_transition_function();
+        self.advance_uniform_state(background, generations, &mut transition_function)
+    }
+
+    /// Returns the state that an infinite grid of cells all in the given state
+    /// will have after the given number of generations, using the given
+    /// transition function.
+    fn advance_uniform_state(
+        &self,
+        cell_state: C,
+        generations: &BigInt,
+        transition_function: &mut TransitionFunction<C, D>,
+    ) -> C {
+        // Follow the sequence of states until it repeats, which must happen
+        // eventually since each state is determined by the previous one.
+        let mut sequence = vec![cell_state];
+        loop {
+            if let Some(i) = generations.to_usize().filter(|&i| i < sequence.len()) {
+                return sequence[i];
+            }
+            let next = self.uniform_transition(*sequence.last().unwrap(), transition_function);
+            if let Some(cycle_start) = sequence.iter().position(|&state| state == next) {
+                let cycle_len = sequence.len() - cycle_start;
+                let i = ((generations - cycle_start) % cycle_len)
+                    .to_usize()
+                    .unwrap();
+                return sequence[cycle_start + i];
+            }
+            sequence.push(next);
+        }
+    }
+
+    /// Returns the next state of a cell whose entire neighborhood is in the
+    /// given state.
+    fn uniform_transition(
+        &self,
+        cell_state: C,
+        transition_function: &mut TransitionFunction<C, D>,
+    ) -> C {
+        self.advance_uniform_state_pow2(cell_state, 0, transition_function)
+    }
+
+    /// Returns the state that an infinite grid of cells all in the given state
+    /// will have after `2^generations_log2` generations.
+    fn advance_uniform_state_pow2(
+        &self,
+        cell_state: C,
+        generations_log2: usize,
+        transition_function: &mut TransitionFunction<C, D>,
+    ) -> C {
+        if let Some(ret) = self
+            .uniform_transitions
+            .get(&(cell_state, generations_log2))
+        {
+            return *ret;
+        }
+        let ret = match generations_log2 {
+            0 => {
+                let r = self.rule.radius();
+                let napkin = Rc::new(NdArray::from_fn(UVec::repeat(2 * r + 1), |_| cell_state));
+                transition_function(napkin.offset_slice(IVec::repeat(-(r as isize))))
+            }
+            k => {
+                let halfway =
+                    self.advance_uniform_state_pow2(cell_state, k - 1, transition_function);
+                self.advance_uniform_state_pow2(halfway, k - 1, transition_function)
+            }
+        };
+        self.uniform_transitions
+            .insert((cell_state, generations_log2), ret);
+        ret
+    }
+}
+
+/// Returns the exponents of the powers of two that add up to the given
+/// positive number, from smallest to largest.
+fn powers_of_two(n: &BigInt) -> Vec<usize> {
+    let (_, bytes) = n.to_bytes_le();
+    let mut ret = vec![];
+    for (i, byte) in bytes.into_iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (1 << bit) != 0 {
+                ret.push(i * 8 + bit);
+            }
+        }
+    }
+    ret
+}
+
+/// The progress of a single call to `Simulation::try_step()`, which may be
+/// updated from several threads at once.
+struct StepState<'a> {
+    control: &'a StepControl,
+    /// The number of nodes whose results have been computed so far.
+    nodes_computed: AtomicUsize,
+    /// The estimated number of nodes to compute, which is updated each time
+    /// a node as large as the whole tree is about to be simulated.
+    nodes_estimated: AtomicUsize,
+    /// The number of times that the whole tree will be simulated, including
+    /// the current one. This is more than one in bounded grids, where only a
+    /// limited number of generations can be simulated at once.
+    iterations_left: AtomicUsize,
+}
+impl<'a> StepState<'a> {
+    fn new(control: &'a StepControl) -> Self {
+        Self {
+            control,
+            nodes_computed: AtomicUsize::new(0),
+            nodes_estimated: AtomicUsize::new(0),
+            iterations_left: AtomicUsize::new(1),
+        }
+    }
+    /// Updates the estimated number of nodes to compute, given an estimate
+    /// for simulating the whole tree once.
+    fn add_estimate(&self, nodes: usize) {
+        let iterations_left = self.iterations_left.load(Ordering::Relaxed);
+        let estimate =
+            self.nodes_computed.load(Ordering::Relaxed) + nodes.saturating_mul(iterations_left);
+        self.nodes_estimated.store(estimate, Ordering::Relaxed);
+    }
+    /// Records that a node has been computed, reporting progress every so
+    /// often.
+    fn node_computed(&self) {
+        let completed = self.nodes_computed.fetch_add(1, Ordering::Relaxed) + 1;
+        if completed % PROGRESS_INTERVAL == 0 {
+            self.control.report_progress(StepProgress {
+                completed,
+                estimated_total: self.nodes_estimated.load(Ordering::Relaxed),
+            });
+        }
+    }
+}
+
+/// Simulation results held weakly, indexed by the node that was simulated. Each
+/// result is the base-2 logarithm of the step size, the node that was
+/// simulated, and the result.
+type WeakResults<C, D> =
+    HashMap<*const NdTreeNode<C, D>, Vec<(usize, Weak<NdTreeNode<C, D>>, Weak<NdTreeNode<C, D>>)>>;
+
+/// A cache of simulation results for a variety of step sizes, which are always
+/// powers of two and indexed by their base-2 logarithm. It may be used from
+/// several threads at once.
+#[derive(Debug, Default)]
+struct ResultsCache<C: CellType, D: Dim>(DashMap<usize, Arc<SingleStepResultsCache<C, D>>>);
+impl<C: CellType, D: Dim> ResultsCache<C, D> {
+    fn get_result(
+        &self,
+        node: &NdCachedNode<C, D>,
+        step_size_log2: usize,
+    ) -> Option<NdCachedNode<C, D>> {
+        // Clone the single-step cache out of the map so that the map is not
+        // locked while using it.
+        let single_step_cache = self.0.get(&step_size_log2)?.value().clone();
+        single_step_cache.get_result(node)
+    }
+    fn set_result(
+        &self,
+        node: NdCachedNode<C, D>,
+        step_size_log2: usize,
+        result: NdCachedNode<C, D>,
+    ) {
+        let single_step_cache = match self.0.get(&step_size_log2) {
+            Some(existing) => existing.value().clone(),
+            None => self.0.entry(step_size_log2).or_default().value().clone(),
+        };
+        single_step_cache.set_result(node, result);
+    }
+
+    /// Returns the number of results in the cache.
+    fn len(&self) -> usize {
+        self.0.iter().map(|entry| entry.value().0.len()).sum()
+    }
+    /// Returns an estimate of the number of bytes used by the results in the
+    /// cache, not including the nodes themselves.
+    fn estimated_bytes(&self) -> usize {
+        self.len() * 2 * std::mem::size_of::<NdCachedNode<C, D>>()
+    }
+
+    /// Removes every result from the cache and returns them, held weakly.
+    fn take_weak(&self) -> WeakResults<C, D> {
+        let mut ret: WeakResults<C, D> = HashMap::new();
+        for entry in self.0.iter() {
+            for result in entry.value().0.iter() {
+                ret.entry(Arc::as_ptr(result.key())).or_default().push((
+                    *entry.key(),
+                    Arc::downgrade(result.key()),
+                    Arc::downgrade(result.value()),
+                ));
+            }
+        }
+        self.0.clear();
+        ret
+    }
+    /// Adds back results returned by `take_weak()`, if both nodes involved
+    /// still exist.
+    fn restore_weak(&self, results: WeakResults<C, D>) {
+        for (step_size_log2, node, result) in results.into_values().flatten() {
+            if let (Some(node), Some(result)) = (node.upgrade(), result.upgrade()) {
+                self.set_result(node, step_size_log2, result);
+            }
+        }
+    }
+}
+
+/// A cache of simulation results for a given step size.
+#[derive(Debug, Default)]
+struct SingleStepResultsCache<C: CellType, D: Dim>(
+    DashMap<NdCachedNode<C, D>, NdCachedNode<C, D>, NodeHasher>,
+);
+impl<C: CellType, D: Dim> SingleStepResultsCache<C, D> {
+    fn get_result(&self, node: &NdCachedNode<C, D>) -> Option<NdCachedNode<C, D>> {
+        self.0.get(node).map(|result| result.value().clone())
+    }
+    fn set_result(&self, node: NdCachedNode<C, D>, result: NdCachedNode<C, D>) {
+        self.0.insert(node, result);
+    }
+}
@@ -0,0 +1,191 @@
+//! Lookup tables of cell transitions for rules with small state spaces.
+//!
+//! Each possible neighborhood of a cell is given an index by treating its
+//! cells as the digits of a number, with one digit for each state of the rule.
+//! The table stores the next state of the cell for each one, filled in the
+//! first time that neighborhood is seen. A rule with two states has 512
+//! entries for a 3x3 neighborhood and 2^27 for a 3x3x3 neighborhood, so the
+//! entries are split into pages that are only allocated once one of their
+//! entries is filled.
+//!
+//! Tables are shared by every simulation using the same rule, and they store
+//! cell states rather than nodes, so they work with any node cache and keep
+//! nothing alive that garbage collection would otherwise remove.
+
+use std::any::Any;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+
+use crate::*;
+
+/// The maximum number of entries in a lookup table.
+const MAX_ENTRIES: usize = 1 << 27;
+/// The number of entries in each page of a lookup table.
+const PAGE_LEN: usize = 1 << 16;
+
+/// The lookup table for each rule that has one, along with the rule it is for.
+static TABLES: Mutex<Vec<Arc<dyn Any + Send + Sync>>> = Mutex::new(Vec::new());
+
+/// A lookup table in `TABLES`.
+struct RuleTable<C: CellType, D: Dim> {
+    rule: Weak<dyn Rule<C, D>>,
+    table: Arc<LookupTable<C>>,
+}
+
+/// A lookup table of the next state of a cell for each neighborhood.
+#[derive(Debug)]
+pub(super) struct LookupTable<C: CellType> {
+    /// The number of states in the rule, which is the base of the digits of
+    /// each index.
+    state_count: usize,
+    /// The cell states that have been seen, in order of their digits. The
+    /// default state is always first.
+    states: RwLock<Vec<C>>,
+    /// The pages of entries. Each entry is one more than the digit of the
+    /// next state, or zero if it has not been computed yet.
+    pages: Vec<OnceLock<Box<[AtomicU8]>>>,
+    /// The number of entries in each page.
+    page_len: usize,
+}
+
+impl<C: 'static + CellType> LookupTable<C> {
+    /// Returns the lookup table for the given rule, creating it if this is the
+    /// first time it has been used, or `None` if the rule has too many
+    /// possible neighborhoods.
+    pub fn for_rule<D: Dim>(rule: &Arc<dyn Rule<C, D>>) -> Option<Arc<Self>> {
+        let mut tables = TABLES.lock().unwrap();
+        // Forget the tables of rules that no longer exist.
+        tables.retain(|entry| match entry.downcast_ref::<RuleTable<C, D>>() {
+            Some(entry) => entry.rule.strong_count() > 0,
+            None => true,
+        });
+        let rule_ptr = Arc::as_ptr(rule) as *const ();
+        let existing = tables
+            .iter()
+            .filter_map(|entry| entry.downcast_ref::<RuleTable<C, D>>())
+            .find(|entry| entry.rule.as_ptr() as *const () == rule_ptr);
+        if let Some(entry) = existing {
+            return Some(entry.table.clone());
+        }
+        let neighborhood_size = (2 * rule.radius() + 1).pow(D::NDIM as u32);
+        let table = Arc::new(Self::new(rule.info().state_count(), neighborhood_size)?);
+        tables.push(Arc::new(RuleTable {
+            rule: Arc::downgrade(rule),
+            table: table.clone(),
+        }));
+        Some(table)
+    }
+}
+
+impl<C: CellType> LookupTable<C> {
+    /// Returns a new empty lookup table for a rule with the given number of
+    /// states and cells in each neighborhood, or `None` if there are too many
+    /// possible neighborhoods.
+    fn new(state_count: usize, neighborhood_size: usize) -> Option<Self> {
+        // Digits are stored in a byte, with zero meaning no entry.
+        if state_count >= u8::MAX as usize {
+            return None;
+        }
+        let entry_count = state_count
+            .checked_pow(neighborhood_size as u32)
+            .filter(|&n| n <= MAX_ENTRIES)?;
+        let page_len = std::cmp::min(entry_count, PAGE_LEN);
+        Some(Self {
+            state_count,
+            states: RwLock::new(vec![C::default()]),
+            pages: (0..(entry_count + page_len - 1) / page_len)
+                .map(|_| OnceLock::new())
+                .collect(),
+            page_len,
+        })
+    }
+
+    /// Returns the number of bytes used by the pages that have been allocated.
+    pub fn estimated_bytes(&self) -> usize {
+        let page_count = self
+            .pages
+            .iter()
+            .filter(|page| page.get().is_some())
+            .count();
+        page_count * self.page_len
+    }
+
+    /// Computes the inner node of a node at the minimum layer after one
+    /// generation, using `transition_function` for each cell whose
+    /// neighborhood is not in the table yet. Returns `None` if the node
+    /// contains more states than the rule has.
+    pub fn advance_inner_node<D: Dim>(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        node: &NdTreeNode<C, D>,
+        radius: usize,
+        transition_function: &mut TransitionFunction<C, D>,
+    ) -> Option<NdCachedNode<C, D>> {
+        let mut states = self.states.read().unwrap().clone();
+        let cells = Rc::new(NdArray::from(node));
+        let mut digits = Vec::with_capacity(cells.size().product());
+        for (_, &cell) in cells.iter_enumerated() {
+            digits.push(self.digit(&mut states, cell)?);
+        }
+        let mut digits = digits.into_iter();
+        let digits = NdArray::from_fn(cells.size().clone(), |_| digits.next().unwrap());
+
+        let neighborhood: Vec<IVec<D>> = IRect::centered(NdVec::origin(), radius as isize)
+            .iter()
+            .collect();
+        let base_offset: isize = 1 << (node.layer - 2);
+        Some(
+            cache.get_small_node_from_cell_fn(node.layer - 1, NdVec::origin(), &mut |pos| {
+                let center = &pos + base_offset;
+                let mut idx = 0;
+                for offset in &neighborhood {
+                    idx = idx * self.state_count + digits[&(center.clone() + offset.clone())];
+                }
+                let entry = &self.page(idx / self.page_len)[idx % self.page_len];
+                match entry.load(Ordering::Acquire) as usize {
+                    0 => {
+                        let slice = cells.clone().offset_slice(-&center);
+                        let new_state = transition_function(slice);
+                        // A state without a digit is still correct, but can't
+                        // be stored.
+                        if let Some(digit) = self.digit(&mut states, new_state) {
+                            entry.store(digit as u8 + 1, Ordering::Release);
+                        }
+                        new_state
+                    }
+                    n => {
+                        // Another thread may have filled in the entry using a
+                        // state that is newer than this copy.
+                        if n > states.len() {
+                            states = self.states.read().unwrap().clone();
+                        }
+                        states[n - 1]
+                    }
+                }
+            }),
+        )
+    }
+
+    /// Returns the digit of a cell state, assigning one to it if it has not
+    /// been seen before, or `None` if the rule has no more states. `states`
+    /// is a copy of the states that have been seen, which is updated if it is
+    /// out of date.
+    fn digit(&self, states: &mut Vec<C>, cell: C) -> Option<usize> {
+        if let Some(digit) = states.iter().position(|&s| s == cell) {
+            return Some(digit);
+        }
+        // Some cell state has not been seen before, at least by this copy.
+        let mut all_states = self.states.write().unwrap();
+        if !all_states.contains(&cell) && all_states.len() < self.state_count {
+            all_states.push(cell);
+        }
+        *states = all_states.clone();
+        states.iter().position(|&s| s == cell)
+    }
+
+    /// Returns a page of entries, allocating it if it has not been used yet.
+    fn page(&self, page_idx: usize) -> &[AtomicU8] {
+        self.pages[page_idx].get_or_init(|| (0..self.page_len).map(|_| AtomicU8::new(0)).collect())
+    }
+}
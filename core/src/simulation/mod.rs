@@ -0,0 +1,97 @@
+//! The functions that apply a rule to each cell in a grid.
+
+use num::BigInt;
+use std::fmt;
+use std::sync::Arc;
+
+use super::*;
+
+mod bitwise;
+mod bruteforce;
+mod control;
+mod hashlife;
+mod lookup;
+mod naive;
+
+pub use bruteforce::BruteForce;
+pub use control::{StepCancelled, StepControl, StepError, StepProgress};
+pub use hashlife::{MemoryStats, Simulation, DEFAULT_MEMORY_LIMIT};
+pub use naive::NaivePattern;
+
+/// An algorithm for simulating a cellular automaton, which advances an NdTree
+/// using a rule.
+pub trait Algorithm<C: CellType, D: Dim>: fmt::Debug + Send {
+    /// Returns which algorithm this is.
+    fn kind(&self) -> AlgorithmKind;
+    /// Returns the rule used by this simulation.
+    fn rule(&self) -> &Arc<dyn Rule<C, D>>;
+    /// Returns the topology of the grid, which is infinite by default.
+    fn topology(&self) -> &Topology<D>;
+    /// Sets the topology of the grid. Cells outside of a bounded grid are
+    /// cleared the next time the simulation is stepped. Periodic backgrounds
+    /// are only supported in infinite grids.
+    fn set_topology(&mut self, topology: Topology<D>);
+    /// Returns the estimated number of bytes of memory beyond which garbage is
+    /// collected after each step, or None if there is no limit.
+    fn memory_limit(&self) -> Option<usize>;
+    /// Sets the estimated number of bytes of memory beyond which garbage is
+    /// collected after each step, or None for no limit.
+    fn set_memory_limit(&mut self, memory_limit: Option<usize>);
+    /// Returns statistics about the memory used by this simulation and the
+    /// given node cache.
+    fn memory_stats(&self, cache: &NdTreeCache<C, D>) -> MemoryStats;
+    /// Advances the given NdTree by the given number of generations, stopping
+    /// early if the step is cancelled using `control` or the pattern is too
+    /// large for this algorithm. If the step does not finish, the tree is left
+    /// unchanged.
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        step_size: &BigInt,
+        control: &StepControl,
+    ) -> Result<(), StepError>;
+    /// Advances the given NdTree by the given number of generations, panicking
+    /// if the pattern is too large for this algorithm.
+    fn step(&mut self, tree: &mut NdTree<C, D>, step_size: &BigInt) {
+        if let Err(e) = self.try_step(tree, step_size, &StepControl::new()) {
+            panic!("{}", e);
+        }
+    }
+}
+
+/// One of the available simulation algorithms.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AlgorithmKind {
+    /// HashLife, which caches the results of simulating each node. It is very
+    /// fast for repetitive patterns and for large steps.
+    #[default]
+    HashLife,
+    /// Computing every cell individually, one generation at a time. This is
+    /// faster than HashLife for chaotic patterns that rarely repeat.
+    BruteForce,
+}
+impl fmt::Display for AlgorithmKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::HashLife => write!(f, "HashLife"),
+            Self::BruteForce => write!(f, "Brute force"),
+        }
+    }
+}
+impl AlgorithmKind {
+    /// Every algorithm, in the order they should be listed.
+    pub const ALL: &'static [Self] = &[Self::HashLife, Self::BruteForce];
+
+    /// Constructs a new simulation using this algorithm with the given rule
+    /// and topology.
+    pub fn new_algorithm<C: 'static + CellType, D: Dim>(
+        self,
+        rule: Arc<dyn Rule<C, D>>,
+        topology: Topology<D>,
+    ) -> Box<dyn Algorithm<C, D>> {
+        match self {
+            Self::HashLife => Box::new(Simulation::new(rule).with_topology(topology)),
+            Self::BruteForce => Box::new(BruteForce::new(rule).with_topology(topology)),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! A deliberately simple simulation that applies a rule to one cell at a time,
+//! which is useful for checking the results of the other algorithms.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::*;
+
+/// A pattern stored as a map from positions to cell states, along with the
+/// state of every cell not in the map.
+///
+/// This is much too slow for real use, but it is simple enough to be obviously
+/// correct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaivePattern<C: CellType, D: Dim> {
+    /// Every cell that is not in the background state.
+    pub cells: HashMap<IVec<D>, C>,
+    /// The state of every cell not in `cells`.
+    pub background: C,
+}
+impl<C: CellType, D: Dim> Default for NaivePattern<C, D> {
+    fn default() -> Self {
+        Self::new(C::default())
+    }
+}
+impl<C: CellType, D: Dim> From<&NdTree<C, D>> for NaivePattern<C, D> {
+    fn from(tree: &NdTree<C, D>) -> Self {
+        let background = tree
+            .get_background()
+            .leaf()
+            .expect("Periodic backgrounds are not supported by NaivePattern");
+        let mut ret = Self::new(background);
+        let offset = tree.slice.offset.as_ivec();
+        for (pos, &cell_state) in NdArray::from(tree.get_root()).iter_enumerated() {
+            ret.set_cell(pos + offset.clone(), cell_state);
+        }
+        ret
+    }
+}
+
+impl<C: CellType, D: Dim> NaivePattern<C, D> {
+    /// Constructs an empty pattern with the given background state.
+    pub fn new(background: C) -> Self {
+        Self {
+            cells: HashMap::new(),
+            background,
+        }
+    }
+
+    /// Returns the state of the cell at the given position.
+    pub fn get_cell(&self, pos: &IVec<D>) -> C {
+        self.cells.get(pos).copied().unwrap_or(self.background)
+    }
+    /// Sets the state of the cell at the given position.
+    pub fn set_cell(&mut self, pos: IVec<D>, cell_state: C) {
+        if cell_state == self.background {
+            self.cells.remove(&pos);
+        } else {
+            self.cells.insert(pos, cell_state);
+        }
+    }
+
+    /// Returns the smallest rectangle containing every cell that is not in the
+    /// background state, or None if there are no such cells.
+    pub fn bounding_rect(&self) -> Option<IRect<D>> {
+        let mut positions = self.cells.keys();
+        let first = positions.next()?.clone();
+        let (min, max) = positions.fold((first.clone(), first), |(min, max), pos| {
+            (NdVec::min(&min, pos), NdVec::max(&max, pos))
+        });
+        Some(NdRect::span(min, max))
+    }
+
+    /// Advances the pattern by one generation using the given rule.
+    pub fn step(&mut self, rule: &dyn Rule<C, D>) {
+        let r = rule.radius() as isize;
+        let mut transition_function = rule.get_transition_function();
+
+        // Every cell far away from the pattern sees only the background.
+        let napkin = NdArray::from_fn(UVec::repeat(2 * r as usize + 1), |_| self.background);
+        let new_background = transition_function(Rc::new(napkin).offset_slice(IVec::repeat(-r)));
+        let mut ret = Self::new(new_background);
+
+        // Only cells within `r` of the pattern can be different.
+        if let Some(rect) = self.bounding_rect() {
+            let new_rect = rect.offset_min_max(-r, r);
+            let input_rect = new_rect.clone().offset_min_max(-r, r);
+            let input_min = input_rect.min();
+            let input_cells = Rc::new(NdArray::from_fn(input_rect.size().as_uvec(), |pos| {
+                self.get_cell(&(&input_min + pos))
+            }));
+            for pos in new_rect.iter() {
+                let array_pos = pos.clone() - input_min.clone();
+                let cell_state = transition_function(input_cells.clone().offset_slice(-array_pos));
+                ret.set_cell(pos, cell_state);
+            }
+        }
+
+        *self = ret;
+    }
+
+    /// Advances the pattern by the given number of generations using the
+    /// given rule.
+    pub fn step_generations(&mut self, rule: &dyn Rule<C, D>, generations: usize) {
+        for _ in 0..generations {
+            self.step(rule);
+        }
+    }
+}
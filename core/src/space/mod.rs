@@ -10,18 +10,20 @@ mod ndarray;
 mod ndrect;
 mod ndtree;
 mod ndvec;
+mod topology;
 
 pub use ndarray::*;
 pub use ndrect::*;
 pub use ndtree::*;
 pub use ndvec::*;
+pub use topology::*;
 
 /// A "trait alias" for a cell type that has a "default" value and can be copied
 /// for free or near-free.
 ///
 /// TODO: rename to Cell and use CellType as the name for an enum over Cell types
-pub trait CellType: Debug + Copy + Default + Eq + Hash {}
-impl<T: Debug + Copy + Default + Eq + Hash> CellType for T {}
+pub trait CellType: Debug + Copy + Default + Eq + Hash + Send + Sync {}
+impl<T: Debug + Copy + Default + Eq + Hash + Send + Sync> CellType for T {}
 
 /// A trait to allow overloading of the contains() method.
 pub trait CanContain<I> {
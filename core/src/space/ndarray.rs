@@ -26,7 +26,7 @@ impl<C: CellType, D: Dim> From<&NdTreeNode<C, D>> for NdArray<C, D> {
 
         let mut data = Vec::with_capacity(count);
         for idx in 0..count {
-            data.push(node[unflatten_idx(&size, idx)]);
+            data.push(node.get_cell(&unflatten_idx(&size, idx)));
         }
         assert_eq!(count, data.len());
 
@@ -54,6 +54,15 @@ impl<T, D: Dim> IndexMut<&IVec<D>> for NdArray<T, D> {
 }
 
 impl<T, D: Dim> NdArray<T, D> {
+    /// Constructs an NdArray of the given size using a function of each
+    /// position to generate each element.
+    pub fn from_fn(size: UVec<D>, mut generator: impl FnMut(IVec<D>) -> T) -> Self {
+        let count = size.product();
+        let data = (0..count)
+            .map(|idx| generator(unflatten_idx(&size, idx)))
+            .collect();
+        Self { size, data }
+    }
     fn flatten_idx(&self, pos: &IVec<D>) -> usize {
         flatten_idx(&self.size, pos)
     }
@@ -110,6 +119,15 @@ impl<T, D: Dim> Index<&IVec<D>> for NdArrayView<T, D> {
 }
 
 impl<T, D: Dim> NdArrayView<T, D> {
+    /// Returns the NdArray that this is a view of.
+    pub fn array(&self) -> &Rc<NdArray<T, D>> {
+        &self.array
+    }
+    /// Returns the position in the underlying NdArray of the origin of this
+    /// NdArrayView.
+    pub fn origin(&self) -> IVec<D> {
+        -self.offset.clone()
+    }
     /// Returns the rectangle of this NdArrayView.
     pub fn rect(&self) -> IRect<D> {
         IRect::new(
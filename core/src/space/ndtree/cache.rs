@@ -1,13 +1,12 @@
 use dashmap::DashMap;
 use seahash::SeaHasher;
+use std::collections::{HashMap, HashSet};
 use std::hash::BuildHasherDefault;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 
 use crate::*;
 
-// TODO: use one node cache for all nodes with the same cell type.
-// TODO: garbage-collect the hash set?
-
 /// Fast hasher used for NdTreeNodes.
 pub type NodeHasher = BuildHasherDefault<SeaHasher>;
 
@@ -15,6 +14,10 @@ pub type NodeHasher = BuildHasherDefault<SeaHasher>;
 pub type NdCachedNode<C, D> = Arc<NdTreeNode<C, D>>;
 
 /// A cache of NdTreeNodes.
+///
+/// Trees that share a cache (see `NdTree::with_cache()`) also share any nodes
+/// with the same contents, so keeping many similar trees (such as undo history
+/// entries) costs little more than keeping one.
 #[derive(Debug, Default)]
 pub struct NdTreeCache<C: CellType, D: Dim> {
     /// A HashSet of all of the nodes.
@@ -23,6 +26,12 @@ pub struct NdTreeCache<C: CellType, D: Dim> {
     ///
     /// The element at index N is the empty node at layer N-1.
     empty_nodes: RwLock<Vec<NdCachedNode<C, D>>>,
+    /// The number of times that garbage has been collected from this cache.
+    collections: AtomicUsize,
+    /// Lock that is held for reading while looking up a node and for writing
+    /// while collecting garbage, so that no node can be handed out after it
+    /// has been found to be unused.
+    gc_lock: RwLock<()>,
 }
 
 impl<C: CellType, D: Dim> NdTreeCache<C, D> {
@@ -31,12 +40,92 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
         Self::default()
     }
 
+    /// Returns the number of nodes in the cache.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    /// Returns an estimate of the number of bytes used by the nodes in the
+    /// cache.
+    pub fn estimated_bytes(&self) -> usize {
+        // Each node is stored along with a copy of its contents, which is used
+        // as the key for lookups. Both store their contents inline.
+        let node_bytes =
+            std::mem::size_of::<NdTreeNode<C, D>>() + std::mem::size_of::<NdBaseTreeNode<C, D>>();
+        self.node_count() * node_bytes
+    }
+    /// Returns the number of times that garbage has been collected from this
+    /// cache.
+    pub fn collection_count(&self) -> usize {
+        self.collections.load(Ordering::Relaxed)
+    }
+
+    /// Removes every node from the cache that is no longer in use.
+    ///
+    /// A node is in use if anything outside of the cache (such as an NdTree or
+    /// an undo history entry) holds a reference to it, or if it is a branch of
+    /// another node in use. `extra_refs` is called for each node in use and may
+    /// add more nodes that should be kept along with it, such as simulation
+    /// results; any references to those must not be held during the collection.
+    ///
+    /// Looking up nodes in the cache (from any tree sharing it) waits until
+    /// the collection is finished. Other threads must not take a node out of
+    /// another one during the collection unless they keep the outer node in
+    /// use until it is finished.
+    pub fn collect_garbage(
+        &self,
+        mut extra_refs: impl FnMut(&NdCachedNode<C, D>, &mut Vec<NdCachedNode<C, D>>),
+    ) {
+        let _gc_guard = self.gc_lock.write().unwrap();
+        // Count the references to each node from within the cache: one from
+        // the map, plus two (one from the key and one from the value) for each
+        // time that it is a branch of another node.
+        let mut internal_refs: HashMap<*const NdTreeNode<C, D>, usize> = HashMap::new();
+        for entry in self.nodes.iter() {
+            *internal_refs.entry(Arc::as_ptr(entry.value())).or_default() += 1;
+            for node in entry.value().child_nodes() {
+                *internal_refs.entry(Arc::as_ptr(node)).or_default() += 2;
+            }
+        }
+        // Any node with more references than that is in use outside of the
+        // cache.
+        let mut unmarked: Vec<NdCachedNode<C, D>> = self
+            .nodes
+            .iter()
+            .filter(|entry| {
+                let node = entry.value();
+                match internal_refs.get(&Arc::as_ptr(node)) {
+                    Some(&refs) => Arc::strong_count(node) > refs,
+                    None => true,
+                }
+            })
+            .map(|entry| entry.value().clone())
+            .collect();
+        // Mark every node reachable from those.
+        let mut marked = HashSet::new();
+        while let Some(node) = unmarked.pop() {
+            if marked.insert(Arc::as_ptr(&node)) {
+                unmarked.extend(node.child_nodes().cloned());
+                extra_refs(&node, &mut unmarked);
+            }
+        }
+        // Remove everything else.
+        self.nodes
+            .retain(|_, node| marked.contains(&Arc::as_ptr(node)));
+        self.collections.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Returns the cached node with the given branches, creating it if it does
     /// not exist.
-    pub fn get_node(&self, branches: Vec<NdTreeBranch<C, D>>) -> NdCachedNode<C, D> {
+    ///
+    /// A small node whose cells can be stored as a leaf chunk is stored as
+    /// one, so the cached node may not have these branches.
+    pub fn get_node(&self, branches: NdTreeBranches<C, D>) -> NdCachedNode<C, D> {
         // Create an NdBaseTreeNode (cheaper than a full NdTreeNode) for HashSet
         // lookup.
-        let base_node = NdBaseTreeNode::from(branches);
+        self.get_node_from_base(NdBaseTreeNode::new(branches))
+    }
+    fn get_node_from_base(&self, base_node: NdBaseTreeNode<C, D>) -> NdCachedNode<C, D> {
+        let _gc_guard = self.gc_lock.read().unwrap();
         // If the node is already in the cache, return the one from the cache.
         if let Some(existing_node) = self.nodes.get(&base_node) {
             return existing_node.value().clone();
@@ -48,6 +137,38 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
             .value()
             .clone()
     }
+    /// Returns the node in this cache with the same contents as the given
+    /// node, which may be from a different cache.
+    pub fn import(&self, node: &NdCachedNode<C, D>) -> NdCachedNode<C, D> {
+        self.import_memoized(node, &mut HashMap::new())
+    }
+    fn import_memoized(
+        &self,
+        node: &NdCachedNode<C, D>,
+        memo: &mut HashMap<*const NdTreeNode<C, D>, NdCachedNode<C, D>>,
+    ) -> NdCachedNode<C, D> {
+        if let Some(ret) = memo.get(&Arc::as_ptr(node)) {
+            return ret.clone();
+        }
+        let branches = match &node.contents {
+            NdTreeNodeContents::Branches(branches) => branches,
+            NdTreeNodeContents::LeafChunk(chunk) => {
+                return self.get_node_from_bits(chunk.layer, chunk.bits, chunk.live_state);
+            }
+        };
+        // Import the branches first, so that comparing the new node to the
+        // ones already in this cache only has to compare pointers.
+        let branches = branches
+            .iter()
+            .map(|branch| match branch {
+                NdTreeBranch::Leaf(cell_state) => NdTreeBranch::Leaf(*cell_state),
+                NdTreeBranch::Node(node) => NdTreeBranch::Node(self.import_memoized(node, memo)),
+            })
+            .collect();
+        let ret = self.get_node(branches);
+        memo.insert(Arc::as_ptr(node), ret.clone());
+        ret
+    }
     /// Returns the NdTreeNode at the given layer with all default cells.
     pub fn get_empty_node(&self, layer: usize) -> NdCachedNode<C, D> {
         let empty_nodes = self.empty_nodes.read().unwrap();
@@ -57,8 +178,8 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
         } else {
             drop(empty_nodes);
             // Cache miss
-            let branches = vec![self.get_empty_branch(layer - 1); D::TREE_BRANCHES];
-            let ret = self.get_node(branches);
+            let empty_branch = self.get_empty_branch(layer - 1);
+            let ret = self.get_node(repeat_branch(empty_branch));
             // All lower entries in the cache have been filled by the recursive
             // call. Check again here to make sure that another thread hasn't
             // filled in this layer before us.
@@ -77,6 +198,40 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
             _ => NdTreeBranch::Node(self.get_empty_node(layer)),
         }
     }
+    /// Returns the NdTreeNode at the given layer with all cells in the given
+    /// state.
+    pub fn get_filled_node(&self, layer: usize, cell_state: C) -> NdCachedNode<C, D> {
+        if cell_state == C::default() {
+            return self.get_empty_node(layer);
+        }
+        self.get_node(repeat_branch(self.get_filled_branch(layer - 1, cell_state)))
+    }
+    /// Returns the NdTreeBranch containing a node at the given layer with all
+    /// cells in the given state (or just an NdTreeBranch::Leaf of that state).
+    pub fn get_filled_branch(&self, layer: usize, cell_state: C) -> NdTreeBranch<C, D> {
+        match layer {
+            0 => NdTreeBranch::Leaf(cell_state),
+            _ => NdTreeBranch::Node(self.get_filled_node(layer, cell_state)),
+        }
+    }
+    /// Returns the NdTreeBranch at the given layer made of copies of the given
+    /// tile, which must not be at a higher layer. If the tile is a leaf, this
+    /// is the same as `get_filled_branch()`.
+    pub fn get_tiled_branch(&self, layer: usize, tile: &NdTreeBranch<C, D>) -> NdTreeBranch<C, D> {
+        match tile {
+            NdTreeBranch::Leaf(cell_state) => self.get_filled_branch(layer, *cell_state),
+            NdTreeBranch::Node(node) => {
+                assert!(layer >= node.layer, "Tile is larger than requested node");
+                if layer == node.layer {
+                    tile.clone()
+                } else {
+                    NdTreeBranch::Node(
+                        self.get_node(repeat_branch(self.get_tiled_branch(layer - 1, tile))),
+                    )
+                }
+            }
+        }
+    }
     /// Returns a cached node, using a function to generate each branch.
     pub fn get_node_from_fn(
         &self,
@@ -111,4 +266,25 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
             }
         })
     }
+    /// Returns a cached node at the given layer from a bitboard (see
+    /// `NdTreeNode::bits`), with cells in `live_state` wherever a bit is set and
+    /// default cells elsewhere. This is a leaf chunk, so none of its branches
+    /// are created.
+    pub fn get_node_from_bits(&self, layer: usize, bits: u64, live_state: C) -> NdCachedNode<C, D> {
+        assert!(
+            NdTreeNode::<C, D>::has_bits_at_layer(layer),
+            "Node at layer {} is too large for a bitboard",
+            layer,
+        );
+        self.get_node_from_base(NdBaseTreeNode::new_leaf_chunk(LeafChunk::new(
+            layer, bits, live_state,
+        )))
+    }
+}
+
+/// Returns an array of branches that are all copies of the given one.
+pub(super) fn repeat_branch<C: CellType, D: Dim>(
+    branch: NdTreeBranch<C, D>,
+) -> NdTreeBranches<C, D> {
+    std::iter::repeat(branch).take(D::TREE_BRANCHES).collect()
 }
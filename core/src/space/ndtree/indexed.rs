@@ -41,9 +41,16 @@ impl<C: CellType, D: Dim> IndexedNdTree<C, D> {
 
     /// Constructs a IndexedNdTree from an NdCachedNode, indexing nodes down to
     /// the given layer. Use `min_layer = 0` to store the entire NdTree.
-    pub fn from_node(node: &NdCachedNode<C, D>, min_layer: usize) -> Self {
+    ///
+    /// The branches of leaf chunks are created in the given node cache.
+    pub fn from_node(
+        node: &NdCachedNode<C, D>,
+        min_layer: usize,
+        node_cache: &NdTreeCache<C, D>,
+    ) -> Self {
         IndexedNdTreeInProgress {
             min_layer,
+            node_cache,
             nodes: vec![],
             cache: HashMap::default(),
         }
@@ -69,23 +76,23 @@ impl<C: CellType, D: Dim> IndexedNdTree<C, D> {
 /// A temporary struct used to create a IndexedNdTree.
 struct IndexedNdTreeInProgress<'a, C: CellType, D: Dim> {
     min_layer: usize,
+    node_cache: &'a NdTreeCache<C, D>,
     nodes: Vec<Vec<IndexedNdTreeBranch<C, D>>>,
-    cache: HashMap<&'a NdCachedNode<C, D>, usize, NodeHasher>,
+    cache: HashMap<NdCachedNode<C, D>, usize, NodeHasher>,
 }
 impl<'a, C: CellType, D: Dim> IndexedNdTreeInProgress<'a, C, D> {
     /// Adds a node recursively to the indexed NdTree if it is not already
     /// present and returns its index.
-    fn add_node(&mut self, node: &'a NdCachedNode<C, D>) -> usize {
+    fn add_node(&mut self, node: &NdCachedNode<C, D>) -> usize {
         if let Some(&node_index) = self.cache.get(node) {
             node_index
         } else {
             let indexed_branches;
             indexed_branches = node
-                .branches
-                .iter()
-                .map(|branch| {
+                .branch_iter(self.node_cache)
+                .map(|(_, branch)| {
                     if branch.get_layer() <= self.min_layer {
-                        IndexedNdTreeBranch::Leaf(branch.clone())
+                        IndexedNdTreeBranch::Leaf(branch)
                     } else {
                         IndexedNdTreeBranch::Pointer(self.add_node(branch.node().unwrap()))
                     }
@@ -93,12 +100,12 @@ impl<'a, C: CellType, D: Dim> IndexedNdTreeInProgress<'a, C, D> {
                 .collect();
             let node_index = self.nodes.len();
             self.nodes.push(indexed_branches);
-            self.cache.insert(node, node_index);
+            self.cache.insert(node.clone(), node_index);
             node_index
         }
     }
     /// Returns the final IndexedNdTree, using the given node as the root.
-    pub fn complete(mut self, node: &'a NdCachedNode<C, D>) -> IndexedNdTree<C, D> {
+    pub fn complete(mut self, node: &NdCachedNode<C, D>) -> IndexedNdTree<C, D> {
         let root_idx = self.add_node(node);
         IndexedNdTree {
             layers: node.layer - self.min_layer,
@@ -148,19 +155,19 @@ x = 8, y = 8, rule = B3/S23
         // nodes of layer >= N, and leaving the rest as NdTreeNodes. The number
         // of indexed nodes is indexed_N.nodes.len().
 
-        let indexed_0 = IndexedNdTree::from_node(&node, 0);
+        let indexed_0 = IndexedNdTree::from_node(&node, 0, &cache);
         assert_eq!(3, indexed_0.layers);
         // 1+3+2 = 6, so there should be a total of eight nodes in this one.
         assert_eq!(6, indexed_0.nodes.len());
         assert_eq!(node, indexed_0.to_node(&cache));
 
-        let indexed_1 = IndexedNdTree::from_node(&node, 1);
+        let indexed_1 = IndexedNdTree::from_node(&node, 1, &cache);
         assert_eq!(2, indexed_1.layers);
         // 1+3 = 4
         assert_eq!(4, indexed_1.nodes.len());
         assert_eq!(node, indexed_1.to_node(&cache));
 
-        let indexed_2 = IndexedNdTree::from_node(&node, 2);
+        let indexed_2 = IndexedNdTree::from_node(&node, 2, &cache);
         assert_eq!(1, indexed_2.layers);
         // 1 = 1
         assert_eq!(1, indexed_2.nodes.len());
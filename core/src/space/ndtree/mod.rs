@@ -1,15 +1,19 @@
+use num::{BigInt, One};
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
 mod cache;
 mod indexed;
 mod node;
+mod population;
 mod slice;
 
 use super::*;
 pub use cache::*;
 pub use indexed::*;
 pub use node::*;
+pub use population::*;
 pub use slice::*;
 
 /// An N-dimensional generalization of a quadtree.
@@ -19,10 +23,14 @@ pub struct NdTree<C: CellType, D: Dim> {
     pub cache: Arc<NdTreeCache<C, D>>,
     /// The slice describing the root node and offset.
     pub slice: NdTreeSlice<C, D>,
+    /// The contents of every cell outside of the root node: either a single
+    /// cell state (a leaf) or a tile (a node) that repeats in every direction,
+    /// aligned with the root node.
+    background: NdTreeBranch<C, D>,
 }
 impl<C: CellType, D: Dim> PartialEq for NdTree<C, D> {
     fn eq(&self, other: &Self) -> bool {
-        self.slice == other.slice
+        self.slice == other.slice && self.background == other.background
     }
 }
 impl<C: CellType, D: Dim> Eq for NdTree<C, D> {}
@@ -65,24 +73,121 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
     /// Constructs a new empty NdTree with an empty node cache centered on the
     /// origin.
     pub fn new() -> Self {
-        let cache = NdTreeCache::default();
+        Self::with_cache(&Arc::new(NdTreeCache::default()))
+    }
+    /// Constructs a new empty NdTree centered on the origin that shares the
+    /// given node cache with other trees.
+    pub fn with_cache(cache: &Arc<NdTreeCache<C, D>>) -> Self {
         let root = cache.get_empty_node(1);
         let offset = NdVec::repeat(-1);
         Self {
-            cache: Arc::new(cache),
+            cache: cache.clone(),
             slice: NdTreeSlice { root, offset },
+            background: NdTreeBranch::Leaf(C::default()),
+        }
+    }
+
+    /// Constructs a new NdTree centered on the origin with every cell in the
+    /// given state.
+    pub fn with_background(background: C) -> Self {
+        let mut ret = Self::new();
+        ret.slice.root = ret.cache.get_filled_node(1, background);
+        ret.background = NdTreeBranch::Leaf(background);
+        ret
+    }
+    /// Constructs a new NdTree with every cell in the state given by a tile
+    /// that repeats in every direction, with one corner of the tile at the
+    /// origin. This is useful for periodic backgrounds ("agars").
+    ///
+    /// The size of the tile along each axis must be a power of two.
+    pub fn with_background_tile(tile: &NdArray<C, D>) -> Self {
+        let size = tile.size();
+        assert!(
+            D::axes().iter().all(|&ax| size[ax].is_power_of_two()),
+            "Tile size must be a power of two"
+        );
+        let len = D::axes().iter().map(|&ax| size[ax]).max().unwrap();
+        let layer = len.trailing_zeros() as usize;
+        if layer == 0 {
+            return Self::with_background(tile[&NdVec::origin()]);
         }
+        let mut ret = Self::new();
+        let tile = NdTreeBranch::Node(ret.cache.get_small_node_from_cell_fn(
+            layer,
+            NdVec::origin(),
+            &mut |pos| tile[&IVec::from_fn(|ax| pos[ax] % size[ax] as isize)],
+        ));
+        ret.slice = NdTreeSlice {
+            root: ret
+                .cache
+                .get_tiled_branch(layer + 1, &tile)
+                .node()
+                .unwrap()
+                .clone(),
+            offset: NdVec::repeat(-(BigInt::one() << layer)),
+        };
+        ret.set_background(tile);
+        ret
     }
 
     /// Returns the root node of this tree.
     pub fn get_root(&self) -> &NdCachedNode<C, D> {
         &self.slice.root
     }
+    /// Returns the contents of every cell outside of the root node: either a
+    /// single cell state (a leaf) or a tile (a node) that repeats in every
+    /// direction, aligned with the root node.
+    pub fn get_background(&self) -> &NdTreeBranch<C, D> {
+        &self.background
+    }
+    /// Sets the contents of every cell outside of the root node, without
+    /// changing any cells inside it.
+    ///
+    /// A tile is aligned with the root node, which must be at a higher layer.
+    /// Call `shrink()` first to change as many cells as possible.
+    pub fn set_background(&mut self, mut background: NdTreeBranch<C, D>) {
+        // Use the smallest tile that repeats to form the same background.
+        while let NdTreeBranch::Node(tile) = &background {
+            let branches = tile.get_branches(&self.cache);
+            let first = &branches[0];
+            if !branches.iter().all(|branch| branch == first) {
+                break;
+            }
+            background = first.clone();
+        }
+        assert!(
+            self.get_root().layer > background.get_layer(),
+            "Background tile must be smaller than the root node"
+        );
+        self.background = background;
+    }
+    /// Moves this tree to the given node cache, so that it shares nodes with
+    /// other trees that use it. This can be used before combining trees that
+    /// were created with different caches.
+    pub fn set_cache(&mut self, cache: &Arc<NdTreeCache<C, D>>) {
+        if Arc::ptr_eq(&self.cache, cache) {
+            return;
+        }
+        self.slice.root = cache.import(&self.slice.root);
+        if let NdTreeBranch::Node(tile) = &self.background {
+            self.background = NdTreeBranch::Node(cache.import(tile));
+        }
+        self.cache = cache.clone();
+    }
+    /// Returns a branch at the given layer containing only background cells,
+    /// aligned with the root node.
+    fn get_background_branch(&self, layer: usize) -> NdTreeBranch<C, D> {
+        self.cache.get_tiled_branch(layer, &self.background)
+    }
     /// Sets the root node of this tree.
     pub fn set_root(&mut self, new_root: NdCachedNode<C, D>) {
         self.slice.root = new_root;
     }
     /// Sets the root node of this tree and adjusts the offset so that the tree remains centered on the same point.
+    ///
+    /// If the background is periodic, the new root node must still be larger
+    /// than the tile, and the change in offset must be a multiple of the tile
+    /// size.
     pub fn set_root_centered(&mut self, new_root: NdCachedNode<C, D>) {
         self.slice.offset += &((self.get_root().len() - new_root.len()) / 2);
         self.set_root(new_root);
@@ -96,17 +201,17 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
     /// corner. The final result is that the entire tree contains the same
     /// contents as before, but with 25% padding on each edge.
     pub fn expand(&mut self) {
-        let empty_sub_branch = self.cache.get_empty_branch(self.slice.root.layer - 1);
+        let empty_sub_branch = self.get_background_branch(self.slice.root.layer - 1);
         let old_root = self.slice.root.clone();
         self.slice.root = self.cache.get_node_from_fn(|branch_idx| {
-            let old_branch = &old_root[branch_idx.clone()];
+            let old_branch = old_root.get_branch(&self.cache, branch_idx.clone());
             // Compute the index of the opposite branch (diagonally opposite
             // on all axes).
             let opposite_branch_idx = branch_idx.opposite();
-            // All branches of this node will be empty ...
-            let mut inner_branches = vec![empty_sub_branch.clone(); D::TREE_BRANCHES];
+            // All branches of this node will be background ...
+            let mut inner_branches = repeat_branch(empty_sub_branch.clone());
             // ... except for the opposite branch, which is closest to the center.
-            inner_branches[opposite_branch_idx.to_array_idx()] = old_branch.clone();
+            inner_branches[opposite_branch_idx.to_array_idx()] = old_branch;
             // And return a branch with that node.
             NdTreeBranch::Node(self.cache.get_node(inner_branches))
         });
@@ -125,17 +230,31 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
         unreachable!();
     }
     /// "Zooms in" to the current tree as much as possible without losing
-    /// non-empty cells. Returns the number of times the tree was shrunk by a
-    /// factor of 2.
+    /// non-background cells. Returns the number of times the tree was shrunk by
+    /// a factor of 2.
     pub fn shrink(&mut self) -> usize {
-        // If we are already at the minimum layer, do not shrink further.
-        if self.get_root().layer == 1 {
+        // If we are already at the minimum layer, do not shrink further. A
+        // periodic background must still fit inside the root node after
+        // shrinking.
+        if self.get_root().layer < self.background.get_layer() + 2 {
             return 0;
         }
-        let new_node = self.get_root().get_inner_node(&self.cache);
-        // Make sure the populations are the same (i.e. we haven't lost any
-        // cells); otherwise don't do anything.
-        if new_node.population == self.get_root().population {
+        // Make sure that everything outside the inner node is background (i.e.
+        // we won't lose any cells); otherwise don't do anything.
+        let background = self.get_background_branch(self.get_root().layer - 2);
+        let only_background_outside =
+            self.get_root()
+                .branch_iter(&self.cache)
+                .all(|(branch_idx, branch)| {
+                    let inner_idx = branch_idx.opposite();
+                    branch.node().unwrap().branch_iter(&self.cache).all(
+                        |(sub_branch_idx, sub_branch)| {
+                            sub_branch_idx == inner_idx || sub_branch == background
+                        },
+                    )
+                });
+        if only_background_outside {
+            let new_node = self.get_root().get_inner_node(&self.cache);
             self.set_root_centered(new_node);
             1 + self.shrink()
         } else {
@@ -149,7 +268,14 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
 
     /// Returns the state of the cell at the given position.
     pub fn get_cell(&self, pos: &BigVec<D>) -> C {
-        self.slice.get_cell(pos).unwrap_or_default()
+        self.slice
+            .get_cell(pos)
+            .unwrap_or_else(|| match &self.background {
+                NdTreeBranch::Leaf(cell_state) => *cell_state,
+                NdTreeBranch::Node(tile) => {
+                    tile.get_cell(&(pos - &self.slice.offset).mod_floor(&tile.len()))
+                }
+            })
     }
     /// Sets the state of the cell at the given position.
     pub fn set_cell(&mut self, pos: &BigVec<D>, cell_state: C) {
@@ -160,6 +286,99 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
                 .set_cell(&self.cache, &(pos - &self.slice.offset), cell_state);
     }
 
+    /// Returns the smallest rectangle containing every cell whose state is
+    /// different from the background, or None if there are no such cells. If
+    /// the background is periodic, this is rounded outwards to whole tiles.
+    pub fn get_bounding_rect(&self) -> Option<BigRect<D>> {
+        fn bounding_rect<C: CellType, D: Dim>(
+            tree: &NdTree<C, D>,
+            branch: &NdTreeBranch<C, D>,
+            offset: BigVec<D>,
+        ) -> Option<BigRect<D>> {
+            let layer = branch.get_layer();
+            let background_layer = tree.background.get_layer();
+            if layer >= background_layer && *branch == tree.get_background_branch(layer) {
+                return None;
+            }
+            match branch {
+                NdTreeBranch::Node(node) if layer > background_layer => node
+                    .branch_iter(&tree.cache)
+                    .filter_map(|(branch_idx, branch)| {
+                        bounding_rect(tree, &branch, &offset + &node.branch_offset(branch_idx))
+                    })
+                    .reduce(|a, b| {
+                        NdRect::span(
+                            NdVec::from_fn(|ax| std::cmp::min(&a.min()[ax], &b.min()[ax]).clone()),
+                            NdVec::from_fn(|ax| std::cmp::max(&a.max()[ax], &b.max()[ax]).clone()),
+                        )
+                    }),
+                _ => Some(NdRect::new(offset, NdVec::repeat(BigInt::from(1) << layer))),
+            }
+        }
+        let root = NdTreeBranch::Node(self.get_root().clone());
+        bounding_rect(self, &root, self.slice.offset.clone())
+    }
+    /// Returns the number of cells whose state is different from the
+    /// background.
+    pub fn get_population(&self) -> BigInt {
+        if self.background.is_empty() {
+            // This is already stored in each node.
+            return self.get_root().population().to_bigint();
+        }
+        // Otherwise compare each node to the background, remembering the count
+        // for each distinct pair of nodes.
+        type Counts<C, D> = HashMap<(NdCachedNode<C, D>, NdCachedNode<C, D>), Population, NodeHasher>;
+        fn count<C: CellType, D: Dim>(
+            cache: &NdTreeCache<C, D>,
+            node: &NdCachedNode<C, D>,
+            background: &NdCachedNode<C, D>,
+            counts: &mut Counts<C, D>,
+        ) -> Population {
+            if node == background {
+                return Population::default();
+            }
+            // Two leaf chunks can be compared using their bitboards.
+            if let (Some(chunk), Some(background_chunk)) =
+                (node.leaf_chunk(), background.leaf_chunk())
+            {
+                let different = if chunk.live_state == background_chunk.live_state {
+                    chunk.bits ^ background_chunk.bits
+                } else {
+                    chunk.bits | background_chunk.bits
+                };
+                return Population::from(different.count_ones() as u64);
+            }
+            let key = (node.clone(), background.clone());
+            if let Some(ret) = counts.get(&key) {
+                return ret.clone();
+            }
+            let mut ret = Population::default();
+            let branches = node.get_branches(cache);
+            let background_branches = background.get_branches(cache);
+            for branches in branches.iter().zip(background_branches.iter()) {
+                match branches {
+                    (NdTreeBranch::Leaf(cell_state), NdTreeBranch::Leaf(background_state)) => {
+                        ret += (cell_state != background_state) as u64;
+                    }
+                    (NdTreeBranch::Node(node), NdTreeBranch::Node(background)) => {
+                        ret += &count(cache, node, background, counts);
+                    }
+                    _ => unreachable!("Node and background are at different layers"),
+                }
+            }
+            counts.insert(key, ret.clone());
+            ret
+        }
+        let background = self.get_background_branch(self.get_root().layer);
+        count(
+            &self.cache,
+            self.get_root(),
+            background.node().unwrap(),
+            &mut HashMap::default(),
+        )
+        .into()
+    }
+
     /// Returns an NdTreeSlice of the smallest node in the grid containing the
     /// given rectangle.
     ///
@@ -214,8 +433,7 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
             let smaller_node = self.cache.get_node_from_fn(|new_branch_idx| {
                 slice
                     .root
-                    .get_sub_branch(&min_sub_branch_idx + new_branch_idx)
-                    .clone()
+                    .get_sub_branch(&self.cache, &min_sub_branch_idx + new_branch_idx)
             });
             smaller_slice = NdTreeSlice {
                 root: smaller_node,
@@ -246,7 +464,7 @@ mod tests {
                     .filter(|(_, &cell_state)| cell_state)
                     .count()
             ),
-            ndtree.get_root().population
+            ndtree.get_root().population().to_bigint()
         );
         for pos in cells_to_check {
             assert_eq!(
@@ -304,7 +522,7 @@ mod tests {
                 ndtree.set_cell(&(pos - 128).convert(), state);
                 ndtree.set_cell(&(pos + 128).convert(), state);
             }
-            let branches = &ndtree.slice.root.branches;
+            let branches = ndtree.slice.root.get_branches(&ndtree.cache);
             let subnode1 = branches[0].node().unwrap();
             let subnode2 = branches[branches.len() - 1].node().unwrap();
             assert_eq!(subnode1, subnode2);
@@ -344,5 +562,152 @@ mod tests {
                 }
             }
         }
+
+        /// Tests that trees sharing a node cache share nodes, and that a tree
+        /// can be moved to a different cache.
+        #[test]
+        fn test_ndtree_shared_cache(
+            cells_to_set: Vec<(IVec2D, u8)>,
+            tile_cells in prop::collection::vec(0..3u8, 4),
+        ) {
+            let mut ndtree_1 = NdTree::new();
+            let mut ndtree_2 = NdTree::with_cache(&ndtree_1.cache);
+            let mut ndtree_3 = NdTree::new();
+            for &(pos, state) in &cells_to_set {
+                ndtree_1.set_cell(&pos.convert(), state);
+                ndtree_2.set_cell(&pos.convert(), state);
+                ndtree_3.set_cell(&pos.convert(), state);
+            }
+            assert!(Arc::ptr_eq(ndtree_1.get_root(), ndtree_2.get_root()));
+            assert!(!Arc::ptr_eq(ndtree_1.get_root(), ndtree_3.get_root()));
+            ndtree_3.set_cache(&ndtree_1.cache);
+            assert!(Arc::ptr_eq(&ndtree_1.cache, &ndtree_3.cache));
+            assert!(Arc::ptr_eq(ndtree_1.get_root(), ndtree_3.get_root()));
+            for &(pos, _) in &cells_to_set {
+                assert_eq!(ndtree_1.get_cell(&pos.convert()), ndtree_3.get_cell(&pos.convert()));
+            }
+
+            // A periodic background is moved too.
+            let tile = NdArray::from_fn(NdVec([2, 2]), |pos| {
+                tile_cells[(pos[X] + 2 * pos[Y]) as usize]
+            });
+            let mut agar = NdTree::with_background_tile(&tile);
+            let expected = agar.clone();
+            agar.set_cache(&ndtree_1.cache);
+            assert_eq!(expected, agar);
+            if let NdTreeBranch::Node(tile) = agar.get_background() {
+                let branches = tile.get_branches(&ndtree_1.cache);
+                assert!(Arc::ptr_eq(tile, &ndtree_1.cache.get_node(branches)));
+            }
+        }
+
+        /// Tests that every operation on an NdTree honors its background.
+        #[test]
+        fn test_ndtree_background(
+            background in 0..4u8,
+            cells_to_set: Vec<(IVec2D, u8)>,
+            center: IVec2D,
+            radius in 0..20isize,
+        ) {
+            let cells_to_set: Vec<_> = cells_to_set
+                .into_iter()
+                .map(|(pos, state)| (pos, state % 4))
+                .collect();
+            let mut ndtree = NdTree::with_background(background);
+            let mut hashmap = HashMap::new();
+            for &(pos, state) in &cells_to_set {
+                hashmap.insert(pos, state);
+                ndtree.set_cell(&pos.convert(), state);
+            }
+            let population = hashmap
+                .values()
+                .filter(|&&state| state != background)
+                .count();
+            let check = |ndtree: &NdTree2D<u8>| {
+                assert_eq!(Some(background), ndtree.get_background().leaf());
+                assert_eq!(BigInt::from(population), ndtree.get_population());
+                for &(pos, _) in &cells_to_set {
+                    assert_eq!(hashmap[&pos], ndtree.get_cell(&pos.convert()));
+                }
+                // Cells that were never set are background, even far away.
+                let far_away: BigVec2D = NdVec::big([1, -1]) << 100;
+                assert_eq!(background, ndtree.get_cell(&far_away));
+            };
+            check(&ndtree);
+            while ndtree.slice.root.layer < 5 {
+                ndtree.expand();
+                check(&ndtree);
+            }
+            ndtree.shrink();
+            check(&ndtree);
+            // Test that a slice includes the background around the pattern.
+            let rect = IRect2D::centered(center, radius);
+            let slice = ndtree.get_slice_containing(&rect.convert());
+            for pos in rect.iter() {
+                let expected = hashmap.get(&pos).copied().unwrap_or(background);
+                assert_eq!(Some(expected), slice.get_cell(&pos.convert()));
+            }
+            check(&ndtree);
+        }
+
+        /// Tests that every operation on an NdTree honors a periodic
+        /// background.
+        #[test]
+        fn test_ndtree_background_tile(
+            tile_cells in prop::collection::vec(0..3u8, 8),
+            cells_to_set: Vec<(IVec2D, u8)>,
+            center: IVec2D,
+            radius in 0..20isize,
+            offset: IVec2D,
+        ) {
+            // Use a 4x2 tile, which repeats to form a 4x4 tile.
+            let tile = NdArray::from_fn(NdVec([4, 2]), |pos| {
+                tile_cells[(pos[X] + 4 * pos[Y]) as usize]
+            });
+            let background = |pos: IVec2D| tile[&NdVec([pos[X].rem_euclid(4), pos[Y].rem_euclid(2)])];
+            let mut ndtree = NdTree::with_background_tile(&tile);
+            let mut hashmap = HashMap::new();
+            for &(pos, state) in &cells_to_set {
+                hashmap.insert(pos, state % 3);
+                ndtree.set_cell(&pos.convert(), state % 3);
+            }
+            let expected = |pos: IVec2D| -> u8 {
+                hashmap.get(&pos).copied().unwrap_or_else(|| background(pos))
+            };
+            let population = hashmap
+                .iter()
+                .filter(|&(&pos, &state)| state != background(pos))
+                .count();
+            let check = |ndtree: &NdTree2D<u8>| {
+                assert_eq!(BigInt::from(population), ndtree.get_population());
+                for &(pos, _) in &cells_to_set {
+                    assert_eq!(expected(pos), ndtree.get_cell(&pos.convert()));
+                }
+                for pos in IRect2D::moore(4).iter() {
+                    // Check the background far away.
+                    let far_away: BigVec2D = pos.convert() + (NdVec::big([1, -1]) << 100);
+                    assert_eq!(background(pos), ndtree.get_cell(&far_away));
+                }
+            };
+            check(&ndtree);
+            while ndtree.slice.root.layer < 6 {
+                ndtree.expand();
+                check(&ndtree);
+            }
+            ndtree.shrink();
+            check(&ndtree);
+            // Test that a slice includes the background around the pattern.
+            let rect = IRect2D::centered(center, radius);
+            let slice = ndtree.get_slice_containing(&rect.convert());
+            for pos in rect.iter() {
+                assert_eq!(Some(expected(pos)), slice.get_cell(&pos.convert()));
+            }
+            check(&ndtree);
+            // Test that recentering moves the background too.
+            ndtree.recenter(offset.convert());
+            for pos in IRect2D::centered(center, 4).iter() {
+                assert_eq!(expected(pos), ndtree.get_cell(&(pos - offset).convert()));
+            }
+        }
     }
 }
@@ -1,44 +1,54 @@
-use num::{BigInt, One, ToPrimitive, Zero};
+use num::{BigInt, One, ToPrimitive};
 use seahash::SeaHasher;
 use std::borrow::Borrow;
 use std::convert::From;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::ops::{Deref, Index};
+use std::ops::Deref;
 
 use crate::*;
 
-/// A tree node with only branches and a hash code; it does not contain
+/// The branches of a node, stored as a fixed-size array of `D::TREE_BRANCHES`
+/// elements.
+pub type NdTreeBranches<C, D> = <D as Dim>::Branches<NdTreeBranch<C, D>>;
+
+/// A tree node with only its contents and a hash code; it does not contain
 /// information about its layer, population, etc. While an NdTreeNode instance
 /// is guaranteed to have 2**d branches with the same layer, NdBaseTreeNode is
 /// not.
 #[derive(Debug, Clone)]
 pub struct NdBaseTreeNode<C: CellType, D: Dim> {
-    /// The branches of this node, stored as a flattened 2^d hypercube of nodes
-    /// one layer lower.
-    ///
-    /// If layer == 1, then all of these must be `NdTreeBranch::Leaf`s. If layer
-    /// > 1, then all of these must be `NdTreeBranch::Branch`es.
-    ///
-    /// Until rust-lang #44580 (RFC 2000) is resolved, there's no way to use
-    /// D::NDIM as the array size. It might be worth implementing a custom
-    /// unsafe type for this, but at the time of writing such an optimization
-    /// would be entirely premature.
-    pub branches: Vec<NdTreeBranch<C, D>>,
+    /// The contents of this node: either its branches or, for a small node,
+    /// its cells.
+    pub contents: NdTreeNodeContents<C, D>,
 
-    /// This node's hash, based solely on the hashes of its branches.
+    /// This node's hash, based solely on its contents.
     pub hash_code: u64,
 
     /// Phantom because we don't technically own anything of type D, but we
     /// still definitely need to know it.
     phantom: PhantomData<D>,
 }
-impl<C: CellType, D: Dim> From<Vec<NdTreeBranch<C, D>>> for NdBaseTreeNode<C, D> {
-    fn from(branches: Vec<NdTreeBranch<C, D>>) -> Self {
+impl<C: CellType, D: Dim> NdBaseTreeNode<C, D> {
+    /// Constructs a node with the given branches, computing its hash code.
+    ///
+    /// If the node can be stored as a leaf chunk, it is, so that nodes with the
+    /// same cells always have the same contents.
+    pub fn new(branches: NdTreeBranches<C, D>) -> Self {
+        match LeafChunk::from_branches::<D>(&branches) {
+            Some(chunk) => Self::new_leaf_chunk(chunk),
+            None => Self::with_contents(NdTreeNodeContents::Branches(branches)),
+        }
+    }
+    /// Constructs a leaf chunk node, computing its hash code.
+    pub fn new_leaf_chunk(chunk: LeafChunk<C>) -> Self {
+        Self::with_contents(NdTreeNodeContents::LeafChunk(chunk))
+    }
+    fn with_contents(contents: NdTreeNodeContents<C, D>) -> Self {
         let mut hasher = SeaHasher::new();
-        branches.hash(&mut hasher);
+        contents.hash(&mut hasher);
         Self {
-            branches,
+            contents,
             hash_code: hasher.finish(),
             phantom: PhantomData,
         }
@@ -58,7 +68,101 @@ impl<C: CellType, D: Dim> PartialEq for NdBaseTreeNode<C, D> {
             // If that fails, check hash codes (very fast; guarantees false).
             || (self.hash_code == rhs.hash_code
                 // If neither of those worked, we have to check the hard way.
-                && self.branches == rhs.branches)
+                && self.contents == rhs.contents)
+    }
+}
+
+/// The contents of a node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NdTreeNodeContents<C: CellType, D: Dim> {
+    /// The branches of the node, stored as a flattened 2^d hypercube of nodes
+    /// one layer lower.
+    ///
+    /// If layer == 1, then all of these must be `NdTreeBranch::Leaf`s. If layer
+    /// > 1, then all of these must be `NdTreeBranch::Node`s.
+    ///
+    /// These are stored inline, so creating a node does not need a separate
+    /// allocation for its branches.
+    Branches(NdTreeBranches<C, D>),
+    /// The cells of a small node, stored as a bitboard. Its branches are only
+    /// created when they are asked for.
+    LeafChunk(LeafChunk<C>),
+}
+
+/// The cells of a node with at most 64 cells (see
+/// `NdTreeNode::has_bits_at_layer()`), each of which is either in the default
+/// state or in one other state.
+///
+/// Every node that fits this description is stored as a leaf chunk rather than
+/// as branches, which makes the small nodes of a two-state pattern much cheaper
+/// to create and to simulate using bitwise operations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LeafChunk<C: CellType> {
+    /// The layer of the node.
+    pub layer: usize,
+    /// A bitboard of the cells in `live_state` (see `NdTreeNode::bits()`). All
+    /// other cells are in the default state.
+    pub bits: u64,
+    /// The state of the cells whose bits are set, which is the default state
+    /// if there are none.
+    pub live_state: C,
+}
+impl<C: CellType> LeafChunk<C> {
+    /// Returns a leaf chunk at the given layer with cells in `live_state`
+    /// wherever a bit is set and default cells elsewhere.
+    pub fn new(layer: usize, bits: u64, live_state: C) -> Self {
+        if bits == 0 || live_state == C::default() {
+            Self {
+                layer,
+                bits: 0,
+                live_state: C::default(),
+            }
+        } else {
+            Self {
+                layer,
+                bits,
+                live_state,
+            }
+        }
+    }
+    /// Returns the leaf chunk with the same cells as a node with the given
+    /// branches, or None if the node is too large or its branches are not all
+    /// leaves or leaf chunks in the same state.
+    fn from_branches<D: Dim>(branches: &NdTreeBranches<C, D>) -> Option<Self> {
+        let layer = branches[0].get_layer() + 1;
+        if !NdTreeNode::<C, D>::has_bits_at_layer(layer) {
+            return None;
+        }
+        let mut bits = 0;
+        let mut live_state = C::default();
+        for (array_idx, branch) in branches.iter().enumerate() {
+            let (branch_bits, branch_state) = match branch {
+                NdTreeBranch::Leaf(cell_state) => {
+                    ((*cell_state != C::default()) as u64, *cell_state)
+                }
+                NdTreeBranch::Node(node) => {
+                    let chunk = node.leaf_chunk()?;
+                    (chunk.bits, chunk.live_state)
+                }
+            };
+            if branch_bits != 0 {
+                if live_state != C::default() && live_state != branch_state {
+                    return None;
+                }
+                live_state = branch_state;
+            }
+            bits |= place_branch_bits(layer, branch_bits, ByteVec::<D>::from_array_idx(array_idx));
+        }
+        Some(Self::new(layer, bits, live_state))
+    }
+
+    /// Returns the state of the cell whose bit is the lowest bit of `bits`.
+    fn cell_state(&self, bits: u64) -> C {
+        if bits & 1 != 0 {
+            self.live_state
+        } else {
+            C::default()
+        }
     }
 }
 
@@ -66,14 +170,30 @@ impl<C: CellType, D: Dim> PartialEq for NdBaseTreeNode<C, D> {
 /// (base-2 logarithm of hypercube side length) and its children.
 #[derive(Clone, Eq)]
 pub struct NdTreeNode<C: CellType, D: Dim> {
-    /// The member containing the branches and hash code.
+    /// The member containing the contents and hash code.
     pub base: NdBaseTreeNode<C, D>,
 
     /// The "layer" of this node (base-2 logarithm of hypercube side length).
     pub layer: usize,
 
-    /// The population of this node.
-    pub population: BigInt,
+    /// The cells in this node that are not in the default state.
+    non_default: NonDefaultCells,
+}
+
+/// The cells in a node that are not in the default state.
+///
+/// A node with at most 64 cells (see `NdTreeNode::has_bits_at_layer()`)
+/// stores a bitboard of them, from which its population can be computed, so
+/// this takes no more space than a Population.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NonDefaultCells {
+    /// A bitboard of the cells that are not in the default state. The cell at
+    /// position `p` in a node of side length `n` is bit
+    /// `p[X] + p[Y] * n + p[Z] * n^2 + ...`, so each row of cells along the X
+    /// axis is a run of `n` consecutive bits.
+    Bits(u64),
+    /// The number of cells that are not in the default state.
+    Population(Population),
 }
 
 // Implement Borrow so that NdBaseTreeNode can be used for HashSet lookups.
@@ -93,18 +213,17 @@ impl<C: CellType, D: Dim> Deref for NdTreeNode<C, D> {
 
 impl<C: CellType, D: Dim> From<NdBaseTreeNode<C, D>> for NdTreeNode<C, D> {
     fn from(base: NdBaseTreeNode<C, D>) -> Self {
-        let branches = &base.branches;
-        // Compute the layer based on the layer of the node's branches, and check
-        // that all branches are at the same layer.
-        // Check that there are the correct number of branches.
-        assert_eq!(
-            D::TREE_BRANCHES,
-            branches.len(),
-            "Node with {} dimensions must have {} branches; got {} instead",
-            D::NDIM,
-            D::TREE_BRANCHES,
-            branches.len()
-        );
+        let branches = match &base.contents {
+            NdTreeNodeContents::Branches(branches) => branches,
+            NdTreeNodeContents::LeafChunk(chunk) => {
+                return Self {
+                    layer: chunk.layer,
+                    non_default: NonDefaultCells::Bits(chunk.bits),
+                    base,
+                };
+            }
+        };
+        // Compute the layer based on the layer of the node's branches.
         // Check that all branches are at the same layer.
         let mut branch_iter = branches.iter();
         let branch_layer = branch_iter.next().unwrap().get_layer();
@@ -115,34 +234,42 @@ impl<C: CellType, D: Dim> From<NdBaseTreeNode<C, D>> for NdTreeNode<C, D> {
         );
         // This node is at the layer above all of its branches.
         let layer = branch_layer + 1;
-        // Compute the population based on the population of each of the node's
-        // branches.
-        let big_zero = BigInt::zero();
-        let big_one = BigInt::one();
-        let population = branches
-            .iter()
-            .map(|branch| match branch {
-                NdTreeBranch::Leaf(cell_state) => {
-                    if *cell_state == C::default() {
-                        &big_zero
-                    } else {
-                        &big_one
-                    }
+        let non_default = if Self::has_bits_at_layer(layer) {
+            // Compute the bitboard based on the bitboard of each of the
+            // node's branches.
+            let mut bits = 0;
+            for (array_idx, branch) in branches.iter().enumerate() {
+                let branch_bits = match branch {
+                    NdTreeBranch::Leaf(cell_state) => (*cell_state != C::default()) as u64,
+                    NdTreeBranch::Node(node) => node.bits().unwrap(),
+                };
+                bits |=
+                    place_branch_bits(layer, branch_bits, ByteVec::<D>::from_array_idx(array_idx));
+            }
+            NonDefaultCells::Bits(bits)
+        } else {
+            // Compute the population based on the population of each of the
+            // node's branches. Only nodes at layer 1 have leaves as branches,
+            // and those always have bitboards.
+            let mut population = Population::default();
+            for branch in branches.iter() {
+                if let NdTreeBranch::Node(node) = branch {
+                    node.add_population_to(&mut population);
                 }
-                NdTreeBranch::Node(node) => &node.population,
-            })
-            .sum();
+            }
+            NonDefaultCells::Population(population)
+        };
         Self {
             base,
             layer,
-            population,
+            non_default,
         }
     }
 }
 
 impl<C: CellType, D: Dim> fmt::Debug for NdTreeNode<C, D> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "NdTreeNode {{ branches: {:?} }}", self.branches)
+        write!(f, "NdTreeNode {{ contents: {:?} }}", self.contents)
     }
 }
 
@@ -159,39 +286,74 @@ impl<C: CellType, D: Dim> Hash for NdTreeNode<C, D> {
     }
 }
 
-// Implement indexing a cell of an NdTreeNode.
-impl<C: CellType, D: Dim> Index<&BigVec<D>> for NdTreeNode<C, D> {
-    type Output = C;
-    fn index(&self, pos: &BigVec<D>) -> &C {
-        self.get_cell_ref(pos)
+impl<C: CellType, D: Dim> NdTreeNode<C, D> {
+    /// Returns true if nodes at the given layer have at most 64 cells, so that
+    /// they store a bitboard of their cells (`NdTreeNode::bits()`).
+    pub fn has_bits_at_layer(layer: usize) -> bool {
+        layer * D::NDIM <= 6
     }
-}
-impl<C: CellType, D: Dim> Index<IVec<D>> for NdTreeNode<C, D> {
-    type Output = C;
-    fn index(&self, pos: IVec<D>) -> &C {
-        self.get_cell_ref(&pos)
+
+    /// Returns the population of this node (number of cells that are not in
+    /// the default state). Use `NdTree::get_population()` to count cells that
+    /// are not in the tree's background state.
+    pub fn population(&self) -> Population {
+        let mut ret = Population::default();
+        self.add_population_to(&mut ret);
+        ret
     }
-}
-impl<C: CellType, D: Dim> Index<UVec<D>> for NdTreeNode<C, D> {
-    type Output = C;
-    fn index(&self, pos: UVec<D>) -> &C {
-        self.get_cell_ref(&pos)
+    /// Adds the population of this node to `population`, without cloning it.
+    fn add_population_to(&self, population: &mut Population) {
+        match &self.non_default {
+            NonDefaultCells::Bits(bits) => *population += bits.count_ones() as u64,
+            NonDefaultCells::Population(n) => *population += n,
+        }
     }
-}
-
-// Implement indexing a branch of an NdTreeNode.
-impl<C: CellType, D: Dim> Index<ByteVec<D>> for NdTreeNode<C, D> {
-    type Output = NdTreeBranch<C, D>;
-    fn index(&self, branch_idx: ByteVec<D>) -> &NdTreeBranch<C, D> {
-        &self.branches[branch_idx.to_array_idx()]
+    /// Returns a bitboard of the cells in this node that are not in the
+    /// default state, or None if this node has more than 64 cells (see
+    /// `has_bits_at_layer()`).
+    ///
+    /// The cell at position `p` in a node of side length `n` is bit
+    /// `p[X] + p[Y] * n + p[Z] * n^2 + ...`, so each row of cells along the X
+    /// axis is a run of `n` consecutive bits.
+    pub fn bits(&self) -> Option<u64> {
+        match self.non_default {
+            NonDefaultCells::Bits(bits) => Some(bits),
+            NonDefaultCells::Population(_) => None,
+        }
     }
-}
 
-impl<C: CellType, D: Dim> NdTreeNode<C, D> {
     /// Returns false if this node contains at least one non-default cell, or
     /// true if it contains only default cells.
     pub fn is_empty(&self) -> bool {
-        self.population.is_zero()
+        match &self.non_default {
+            NonDefaultCells::Bits(bits) => *bits == 0,
+            NonDefaultCells::Population(n) => n.is_zero(),
+        }
+    }
+
+    /// Returns the state of every cell in this node if they are all the same,
+    /// or None if they are not.
+    pub fn uniform_state(&self) -> Option<C> {
+        if self.is_empty() {
+            return Some(C::default());
+        }
+        let branches = match &self.contents {
+            NdTreeNodeContents::Branches(branches) => branches,
+            NdTreeNodeContents::LeafChunk(chunk) => {
+                let full = chunk.bits.count_ones() as usize == 1 << (self.layer * D::NDIM);
+                return if full { Some(chunk.live_state) } else { None };
+            }
+        };
+        // Identical branches are always the same cached node, so this is
+        // usually a pointer comparison.
+        let first = &branches[0];
+        if !branches.iter().all(|branch| branch == first) {
+            return None;
+        }
+        match first {
+            NdTreeBranch::Leaf(cell_state) => Some(*cell_state),
+            NdTreeBranch::Node(node) => node.uniform_state(),
+        }
     }
 
     /// Returns the side length of the rectangle encompassing this node.
@@ -229,15 +391,74 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
     /// node (layer 3) centered on it.
     pub fn get_inner_node(&self, cache: &NdTreeCache<C, D>) -> NdCachedNode<C, D> {
         assert_ne!(1, self.layer, "Cannot take inner node of node at layer 1");
-        let new_branches = self
-            .branches
-            .iter()
-            .enumerate()
-            .map(|(branch_idx, branch)| {
-                branch.node().unwrap()[ByteVec::from_array_idx(branch_idx).opposite()].clone()
-            })
-            .collect();
-        cache.get_node(new_branches)
+        // Small inner nodes are taken directly from bitboards when possible.
+        if Self::has_bits_at_layer(self.layer - 1) {
+            if let Some((bits, live_state)) = self.inner_bits() {
+                return cache.get_node_from_bits(self.layer - 1, bits, live_state);
+            }
+        }
+        cache.get_node_from_fn(|branch_idx| {
+            self.get_branch(cache, branch_idx.clone())
+                .node()
+                .unwrap()
+                .get_branch(cache, branch_idx.opposite())
+        })
+    }
+    /// Returns the bitboard of this node's inner node and the state of the
+    /// cells whose bits are set, or None if this node is not a leaf chunk and
+    /// does not have leaf chunks as branches.
+    fn inner_bits(&self) -> Option<(u64, C)> {
+        let inner_layer = self.layer - 1;
+        let mut bits = 0;
+        let mut live_state = C::default();
+        for array_idx in 0..D::TREE_BRANCHES {
+            let branch_idx = ByteVec::<D>::from_array_idx(array_idx);
+            let (branch_bits, branch_state) = match &self.contents {
+                NdTreeNodeContents::LeafChunk(chunk) => (
+                    get_branch_bits(self.layer, chunk.bits, branch_idx.clone()),
+                    chunk.live_state,
+                ),
+                NdTreeNodeContents::Branches(branches) => {
+                    let chunk = branches[array_idx].node()?.leaf_chunk()?;
+                    (chunk.bits, chunk.live_state)
+                }
+            };
+            let sub_branch_bits =
+                get_branch_bits(inner_layer, branch_bits, branch_idx.clone().opposite());
+            if sub_branch_bits != 0 {
+                if live_state != C::default() && live_state != branch_state {
+                    return None;
+                }
+                live_state = branch_state;
+            }
+            bits |= place_branch_bits(inner_layer, sub_branch_bits, branch_idx);
+        }
+        Some((bits, live_state))
+    }
+
+    /// Returns the branches of this node, or None if it is a leaf chunk.
+    pub fn branches(&self) -> Option<&NdTreeBranches<C, D>> {
+        match &self.contents {
+            NdTreeNodeContents::Branches(branches) => Some(branches),
+            NdTreeNodeContents::LeafChunk(_) => None,
+        }
+    }
+    /// Returns the cells of this node if it is a leaf chunk, or None if it
+    /// has branches.
+    pub fn leaf_chunk(&self) -> Option<&LeafChunk<C>> {
+        match &self.contents {
+            NdTreeNodeContents::Branches(_) => None,
+            NdTreeNodeContents::LeafChunk(chunk) => Some(chunk),
+        }
+    }
+    /// Returns an iterator over the branches of this node that are nodes,
+    /// which does not include those of a leaf chunk because they are not
+    /// stored.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &NdCachedNode<C, D>> {
+        self.branches()
+            .into_iter()
+            .flat_map(|branches| branches.iter())
+            .filter_map(NdTreeBranch::node)
     }
 
     /// Returns the NdTreeBranchIndex of the branch of this node containing the
@@ -251,30 +472,71 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
         branch_idx.branch_offset(self.layer)
     }
 
+    /// Returns the given branch of this node. The branches of a leaf chunk are
+    /// created in the cache.
+    pub fn get_branch(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        branch_idx: ByteVec<D>,
+    ) -> NdTreeBranch<C, D> {
+        match &self.contents {
+            NdTreeNodeContents::Branches(branches) => branches[branch_idx.to_array_idx()].clone(),
+            NdTreeNodeContents::LeafChunk(chunk) => {
+                let bits = get_branch_bits(self.layer, chunk.bits, branch_idx);
+                if self.layer == 1 {
+                    NdTreeBranch::Leaf(chunk.cell_state(bits))
+                } else {
+                    NdTreeBranch::Node(cache.get_node_from_bits(
+                        self.layer - 1,
+                        bits,
+                        chunk.live_state,
+                    ))
+                }
+            }
+        }
+    }
+    /// Returns all of the branches of this node. The branches of a leaf chunk
+    /// are created in the cache.
+    pub fn get_branches(&self, cache: &NdTreeCache<C, D>) -> NdTreeBranches<C, D> {
+        match &self.contents {
+            NdTreeNodeContents::Branches(branches) => branches.clone(),
+            NdTreeNodeContents::LeafChunk(_) => (0..D::TREE_BRANCHES)
+                .map(|array_idx| self.get_branch(cache, ByteVec::from_array_idx(array_idx)))
+                .collect(),
+        }
+    }
+
     /// Returns the specified sub-branch which is two layers below the given
     /// node, given a ByteVec with values in the range 0..=3.
-    pub fn get_sub_branch(&self, sub_branch_idx: ByteVec<D>) -> &NdTreeBranch<C, D> {
+    pub fn get_sub_branch(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        sub_branch_idx: ByteVec<D>,
+    ) -> NdTreeBranch<C, D> {
         // Get the more significant bit.
         let outer_branch_idx = sub_branch_idx.clone() >> 1;
         // Get the less significant bit.
         let inner_branch_idx = sub_branch_idx & 1;
         // Now use those to index into the node.
-        &self[outer_branch_idx]
+        self.get_branch(cache, outer_branch_idx)
             .node()
-            .expect("Cannot get sub-branch of node at layer 1")[inner_branch_idx]
+            .expect("Cannot get sub-branch of node at layer 1")
+            .get_branch(cache, inner_branch_idx)
     }
 
-    /// Returns a reference to the cell value at the given position, modulo the
-    /// node size. This is the same as indexing the node using the vector.
-    pub fn get_cell_ref<I: NdTreeIndex<D>>(&self, pos: &I) -> &C {
-        match &self[self.branch_idx(pos)] {
-            NdTreeBranch::Leaf(cell_state) => cell_state,
-            NdTreeBranch::Node(node) => node.get_cell_ref(pos),
-        }
-    }
     /// Returns the cell value at the given position, modulo the node size.
     pub fn get_cell<I: NdTreeIndex<D>>(&self, pos: &I) -> C {
-        self.get_cell_ref(pos).clone()
+        match &self.contents {
+            NdTreeNodeContents::Branches(branches) => {
+                match &branches[self.branch_idx(pos).to_array_idx()] {
+                    NdTreeBranch::Leaf(cell_state) => *cell_state,
+                    NdTreeBranch::Node(node) => node.get_cell(pos),
+                }
+            }
+            NdTreeNodeContents::LeafChunk(chunk) => {
+                chunk.cell_state(chunk.bits >> cell_bit(self.layer, pos))
+            }
+        }
     }
     /// Returns a node with the cell at the given position, modulo the node
     /// size, having the given cell state.
@@ -285,7 +547,18 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
         pos: &I,
         cell_state: C,
     ) -> NdCachedNode<C, D> {
-        let mut new_branches = self.branches.clone();
+        if let Some(chunk) = self.leaf_chunk() {
+            // The cell can be set directly in the bitboard unless it would add
+            // a second non-default state.
+            let bit = 1 << cell_bit(self.layer, pos);
+            if cell_state == C::default() {
+                return cache.get_node_from_bits(self.layer, chunk.bits & !bit, chunk.live_state);
+            }
+            if chunk.bits & !bit == 0 || cell_state == chunk.live_state {
+                return cache.get_node_from_bits(self.layer, chunk.bits | bit, cell_state);
+            }
+        }
+        let mut new_branches = self.get_branches(cache);
         // Get the branch containing the given cell.
         let branch = &mut new_branches[self.branch_idx(pos).to_array_idx()];
         match branch {
@@ -296,12 +569,86 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
         }
         cache.get_node(new_branches)
     }
-    /// Returns an iterator over the branches of this node.
-    pub fn branch_iter(&self) -> impl Iterator<Item = (ByteVec<D>, &NdTreeBranch<C, D>)> {
-        self.branches
-            .iter()
-            .enumerate()
-            .map(|(array_idx, branch)| (ByteVec::from_array_idx(array_idx), branch))
+    /// Returns an iterator over the branches of this node. The branches of a
+    /// leaf chunk are created in the cache.
+    pub fn branch_iter<'a>(
+        &'a self,
+        cache: &'a NdTreeCache<C, D>,
+    ) -> impl Iterator<Item = (ByteVec<D>, NdTreeBranch<C, D>)> + 'a {
+        (0..D::TREE_BRANCHES).map(move |array_idx| {
+            let branch_idx = ByteVec::from_array_idx(array_idx);
+            (branch_idx.clone(), self.get_branch(cache, branch_idx))
+        })
+    }
+}
+
+/// Returns the index of the bit for the cell at the given position, modulo
+/// the node size, in the bitboard of a node at the given layer (see
+/// `NdTreeNode::bits()`).
+fn cell_bit<D: Dim, I: NdTreeIndex<D>>(layer: usize, pos: &I) -> usize {
+    let mut ret = 0;
+    for bit_layer in 1..=layer {
+        let branch_idx = pos.branch_idx(bit_layer);
+        for &ax in D::axes() {
+            ret |= (branch_idx[ax] as usize) << (bit_layer - 1 + layer * ax as usize);
+        }
+    }
+    ret
+}
+
+/// Returns the bitboard of the given branch of a node at the given layer,
+/// given the bitboard of the whole node.
+pub(super) fn get_branch_bits<D: Dim>(layer: usize, bits: u64, branch_idx: ByteVec<D>) -> u64 {
+    let row_len = 1 << (layer - 1);
+    let row_mask: u64 = (1 << row_len) - 1;
+    let mut ret = 0;
+    for_each_branch_row(layer, branch_idx, |branch_bit, row, x| {
+        ret |= ((bits >> (row * 2 * row_len + x)) & row_mask) << branch_bit;
+    });
+    ret
+}
+/// Returns the bitboard of a node at the given layer with the given bitboard
+/// in one branch and no bits set in the others.
+pub(super) fn place_branch_bits<D: Dim>(
+    layer: usize,
+    branch_bits: u64,
+    branch_idx: ByteVec<D>,
+) -> u64 {
+    let row_len = 1 << (layer - 1);
+    let row_mask: u64 = (1 << row_len) - 1;
+    let mut ret = 0;
+    for_each_branch_row(layer, branch_idx, |branch_bit, row, x| {
+        ret |= ((branch_bits >> branch_bit) & row_mask) << (row * 2 * row_len + x);
+    });
+    ret
+}
+
+/// Calls a function for each row of cells along the X axis in the given branch
+/// of a node at the given layer, with the bit index of the start of the row in
+/// the branch's bitboard (see `NdTreeNode::bits`), the index of the same row
+/// in the node, and the X coordinate in the node where the row starts.
+pub fn for_each_branch_row<D: Dim>(
+    layer: usize,
+    branch_idx: ByteVec<D>,
+    mut f: impl FnMut(usize, usize, usize),
+) {
+    let branch_len = 1 << (layer - 1);
+    let node_len = branch_len * 2;
+    let row_count = (1 << ((layer - 1) * (D::NDIM - 1))) as usize;
+    for branch_row in 0..row_count {
+        // The position of the row along every axis except X.
+        let mut row = 0;
+        let mut stride = 1;
+        for &ax in &D::axes()[1..] {
+            let branch_pos = (branch_row >> ((layer - 1) * (ax as usize - 1))) % branch_len;
+            row += (branch_pos + branch_idx[ax] as usize * branch_len) * stride;
+            stride *= node_len;
+        }
+        f(
+            branch_row * branch_len,
+            row,
+            branch_idx[X] as usize * branch_len,
+        );
     }
 }
 
@@ -443,4 +790,14 @@ mod tests {
             assert_eq!(idx, vec.to_array_idx());
         }
     }
+
+    /// Tests that storing a bitboard in small nodes does not make any node
+    /// larger than storing only its population.
+    #[test]
+    fn test_non_default_cells_size() {
+        assert_eq!(
+            std::mem::size_of::<Population>(),
+            std::mem::size_of::<NonDefaultCells>()
+        );
+    }
 }
@@ -0,0 +1,134 @@
+use num::{BigInt, ToPrimitive, Zero};
+use std::fmt;
+use std::ops::AddAssign;
+
+/// A number of cells, stored inline unless it is too large to fit in a `u64`.
+///
+/// Nearly every node has a population small enough to fit in a `u64`, so this
+/// avoids allocating a `BigInt` for each one. A `Big` population is always
+/// larger than `u64::MAX`, so there is only one representation of each number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Population {
+    /// A population that fits in a `u64`.
+    Small(u64),
+    /// A population larger than `u64::MAX`.
+    Big(Box<BigInt>),
+}
+impl Default for Population {
+    fn default() -> Self {
+        Self::Small(0)
+    }
+}
+impl fmt::Display for Population {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Small(n) => write!(f, "{}", n),
+            Self::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl From<u64> for Population {
+    fn from(n: u64) -> Self {
+        Self::Small(n)
+    }
+}
+impl From<BigInt> for Population {
+    fn from(n: BigInt) -> Self {
+        match n.to_u64() {
+            Some(n) => Self::Small(n),
+            None => Self::Big(Box::new(n)),
+        }
+    }
+}
+impl From<&Population> for BigInt {
+    fn from(population: &Population) -> Self {
+        population.to_bigint()
+    }
+}
+impl From<Population> for BigInt {
+    fn from(population: Population) -> Self {
+        match population {
+            Population::Small(n) => n.into(),
+            Population::Big(n) => *n,
+        }
+    }
+}
+
+impl AddAssign<&Population> for Population {
+    fn add_assign(&mut self, rhs: &Population) {
+        match (&mut *self, rhs) {
+            (Self::Small(a), Self::Small(b)) => match a.checked_add(*b) {
+                Some(sum) => *a = sum,
+                None => *self = Self::Big(Box::new(BigInt::from(*a) + *b)),
+            },
+            (Self::Small(a), Self::Big(b)) => *self = Self::Big(Box::new(&**b + *a)),
+            (Self::Big(a), Self::Small(b)) => **a += *b,
+            (Self::Big(a), Self::Big(b)) => **a += &**b,
+        }
+    }
+}
+impl AddAssign<u64> for Population {
+    fn add_assign(&mut self, rhs: u64) {
+        *self += &Self::Small(rhs);
+    }
+}
+impl<'a> std::iter::Sum<&'a Population> for Population {
+    fn sum<I: Iterator<Item = &'a Population>>(iter: I) -> Self {
+        let mut ret = Self::default();
+        for population in iter {
+            ret += population;
+        }
+        ret
+    }
+}
+
+impl Population {
+    /// Returns true if the population is zero.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Self::Small(n) => *n == 0,
+            Self::Big(n) => n.is_zero(),
+        }
+    }
+    /// Returns the population as a BigInt.
+    pub fn to_bigint(&self) -> BigInt {
+        match self {
+            Self::Small(n) => BigInt::from(*n),
+            Self::Big(n) => (**n).clone(),
+        }
+    }
+    /// Returns the population as an f64, which may be imprecise or infinite.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::Small(n) => *n as f64,
+            Self::Big(n) => n.to_f64().unwrap_or(f64::INFINITY),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a Population is promoted to a BigInt when it overflows a
+    /// u64, and that the result is the same as adding BigInts.
+    #[test]
+    fn test_population_overflow() {
+        let mut population = Population::from(u64::MAX - 1);
+        population += 1;
+        assert_eq!(Population::Small(u64::MAX), population);
+        population += 1;
+        let expected = BigInt::from(u64::MAX) + 1u64;
+        assert_eq!(Population::Big(Box::new(expected.clone())), population);
+        assert_eq!(Population::from(expected.clone()), population);
+
+        let sum: Population = vec![population.clone(); 4].iter().sum();
+        assert_eq!(expected * 4, sum.to_bigint());
+        assert!(!sum.is_zero());
+        assert_eq!(2.0f64.powi(66), sum.to_f64());
+
+        let sum: Population = vec![Population::default(); 4].iter().sum();
+        assert!(sum.is_zero());
+    }
+}
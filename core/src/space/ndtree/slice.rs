@@ -1,5 +1,4 @@
 use num::ToPrimitive;
-use std::ops::Index;
 
 use super::*;
 
@@ -45,8 +44,9 @@ impl DisplayCell for u8 {
         match self {
             0 => '.',
             1 => '#',
-            2 => '2',
-            3 => '3',
+            2..=9 => (b'0' + self) as char,
+            10..=35 => (b'A' + self - 10) as char,
+            36..=61 => (b'a' + self - 36) as char,
             _ => '?',
         }
     }
@@ -62,28 +62,33 @@ impl DisplayCell for bool {
 
 impl<C: DisplayCell> fmt::Display for NdTreeSlice<C, Dim2D> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_with(DisplayCell::cell_char))
+    }
+}
+
+impl<C: CellType> NdTreeSlice<C, Dim2D> {
+    /// Returns a string displaying the cells of this slice, using the given
+    /// function to get the char for each cell state (such as
+    /// `RuleInfo::symbol()`).
+    ///
+    /// Panics if the slice is larger than 256x256.
+    pub fn to_string_with(&self, mut cell_char: impl FnMut(&C) -> char) -> String {
         if self.root.layer > 8 {
             panic!("Cannot display node larger than 256x256");
         }
         let rect = self.rect();
-        let mut line = String::with_capacity(self.root.len().to_usize().unwrap() * 2);
+        let mut ret = String::with_capacity(self.root.len().to_usize().unwrap().pow(2) * 2);
         for y in rect.axis_range(Y).rev() {
-            line.clear();
             for x in rect.axis_range(X) {
-                line.push(self[NdVec([x, y.clone()])].cell_char());
-                line.push(' ');
+                ret.push(cell_char(
+                    &self.root.get_cell(&(NdVec([x, y.clone()]) - &self.offset)),
+                ));
+                ret.push(' ');
             }
-            line.pop();
-            writeln!(f, "{}", line)?;
+            ret.pop();
+            ret.push('\n');
         }
-        Ok(())
-    }
-}
-
-impl<C: CellType, D: Dim> Index<BigVec<D>> for NdTreeSlice<C, D> {
-    type Output = C;
-    fn index(&self, pos: BigVec<D>) -> &C {
-        &self.root[&(pos - &self.offset)]
+        ret
     }
 }
 
@@ -113,31 +118,30 @@ impl<C: CellType, D: Dim> NdTreeSlice<C, D> {
         self.rect().size()
     }
 
-    /// Returns a reference to the cell value at the given position, if it is
-    /// within the bounds of the slice.
-    pub fn get_cell_ref(&self, pos: &BigVec<D>) -> Option<&C> {
+    /// Returns the cell value at the given position, if it is within the bounds
+    /// of the slice.
+    pub fn get_cell(&self, pos: &BigVec<D>) -> Option<C> {
         if self.rect().contains(pos) {
-            Some(self.root.get_cell_ref(&(pos - &self.offset)))
+            Some(self.root.get_cell(&(pos - &self.offset)))
         } else {
             None
         }
     }
-    /// Returns the cell value at the given position, if it is within the bounds
-    /// of the slice.
-    pub fn get_cell(&self, pos: &BigVec<D>) -> Option<C> {
-        self.get_cell_ref(pos).map(|cell| cell.clone())
-    }
 
     /// Returns an NdTreeSlice of the root node's branch with the given branch
     /// index.
-    pub fn get_branch(&self, branch_idx: ByteVec<D>) -> NdTreeSliceBranch<C, D> {
-        match &self.root[branch_idx.clone()] {
+    pub fn get_branch(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        branch_idx: ByteVec<D>,
+    ) -> NdTreeSliceBranch<C, D> {
+        match self.root.get_branch(cache, branch_idx.clone()) {
             NdTreeBranch::Leaf(cell_state) => NdTreeSliceBranch::Leaf(
-                *cell_state,
+                cell_state,
                 self.rect().min() + &branch_idx.branch_offset(1),
             ),
             NdTreeBranch::Node(node) => NdTreeSliceBranch::Node(Self {
-                root: node.clone(),
+                root: node,
                 offset: &self.offset + &branch_idx.branch_offset(self.root.layer),
             }),
         }
@@ -1,5 +1,6 @@
 use noisy_float::prelude::R64;
 use num::BigInt;
+use std::iter::FromIterator;
 
 use super::*;
 
@@ -11,7 +12,7 @@ use super::*;
 /// application). Similar to ndarray's Dimension trait, this trait should not
 /// and cannot be implemented outside of this crate.
 pub trait Dim:
-    DimFor<BigInt> + DimFor<R64> + DimFor<isize> + DimFor<usize> + DimFor<u8> + Send
+    DimFor<BigInt> + DimFor<R64> + DimFor<isize> + DimFor<usize> + DimFor<u8> + Send + Sync + 'static
 {
     /// The number of dimensions (number of axes).
     const NDIM: usize;
@@ -19,6 +20,19 @@ pub trait Dim:
     /// The number of branches for each node in an NdTree of this
     /// dimensionality.
     const TREE_BRANCHES: usize = 1 << Self::NDIM;
+    /// The array type used for the branches of each node in an NdTree of this
+    /// dimensionality, which always has `TREE_BRANCHES` elements.
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync>: Debug
+        + Clone
+        + Eq
+        + Hash
+        + Send
+        + Sync
+        + Deref<Target = [T]>
+        + DerefMut
+        + FromIterator<T>
+        + IntoIterator<Item = T>
+        + FixedArray<T>;
 
     /// Returns a Vector of the axes of this many dimensions.
     fn axes() -> &'static [Axis] {
@@ -52,21 +66,99 @@ pub struct Dim6D;
 
 impl Dim for Dim1D {
     const NDIM: usize = 1;
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync> = InlineArray<T, 2>;
 }
 impl Dim for Dim2D {
     const NDIM: usize = 2;
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync> = InlineArray<T, 4>;
 }
 impl Dim for Dim3D {
     const NDIM: usize = 3;
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync> = InlineArray<T, 8>;
 }
 impl Dim for Dim4D {
     const NDIM: usize = 4;
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync> = InlineArray<T, 16>;
 }
 impl Dim for Dim5D {
     const NDIM: usize = 5;
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync> = InlineArray<T, 32>;
 }
 impl Dim for Dim6D {
     const NDIM: usize = 6;
+    type Branches<T: Debug + Clone + Eq + Hash + Send + Sync> = InlineArray<T, 64>;
+}
+
+/// A fixed-size array stored inline, which can be used as a slice.
+///
+/// This is used for the branches of NdTree nodes so that each node does not
+/// need a separate allocation for them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InlineArray<T, const N: usize>(pub [T; N]);
+impl<T, const N: usize> Deref for InlineArray<T, N> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+impl<T, const N: usize> DerefMut for InlineArray<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+/// A trait for arrays whose length is known from their type.
+pub trait FixedArray<T>: Sized {
+    /// Constructs an array by calling a function with each index, stopping at
+    /// the first error.
+    fn try_from_fn<E>(f: impl FnMut(usize) -> Result<T, E>) -> Result<Self, E>;
+}
+impl<T, const N: usize> FixedArray<T> for InlineArray<T, N> {
+    fn try_from_fn<E>(mut f: impl FnMut(usize) -> Result<T, E>) -> Result<Self, E> {
+        let mut error = None;
+        let elements: [Option<T>; N] = std::array::from_fn(|i| match error {
+            Some(_) => None,
+            None => f(i).map_err(|e| error = Some(e)).ok(),
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(Self(elements.map(Option::unwrap))),
+        }
+    }
+}
+impl<T, const N: usize> FromIterator<T> for InlineArray<T, N> {
+    /// Collects exactly `N` elements into an array.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the iterator does not yield exactly `N`
+    /// elements.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let ret = std::array::from_fn(|_| {
+            iter.next()
+                .unwrap_or_else(|| panic!("Expected {} elements for array", N))
+        });
+        assert!(
+            iter.next().is_none(),
+            "Expected {} elements for array; got more",
+            N
+        );
+        Self(ret)
+    }
+}
+impl<T, const N: usize> IntoIterator for InlineArray<T, N> {
+    type Item = T;
+    type IntoIter = std::array::IntoIter<T, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter(self.0)
+    }
+}
+impl<'a, T, const N: usize> IntoIterator for &'a InlineArray<T, N> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
 }
 
 /// A trait providing an array type to create a generic N-length array.
@@ -79,30 +171,30 @@ pub trait DimFor<T: Default + Clone + Eq>:
     /// The pure Dim type associated with this DimFor (i.e. Self)
     type Dim: Dim;
     /// The array type used for vectors.
-    type Array: Debug + Default + Clone + Eq + Hash + Send + AsRef<[T]> + AsMut<[T]>;
+    type Array: Debug + Default + Clone + Eq + Hash + Send + Sync + AsRef<[T]> + AsMut<[T]>;
 }
 
-impl<T: Debug + Default + Clone + Eq + Hash + Send> DimFor<T> for Dim1D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim1D {
     type Dim = Dim1D;
     type Array = [T; 1];
 }
-impl<T: Debug + Default + Clone + Eq + Hash + Send> DimFor<T> for Dim2D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim2D {
     type Dim = Dim2D;
     type Array = [T; 2];
 }
-impl<T: Debug + Default + Clone + Eq + Hash + Send> DimFor<T> for Dim3D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim3D {
     type Dim = Dim3D;
     type Array = [T; 3];
 }
-impl<T: Debug + Default + Clone + Eq + Hash + Send> DimFor<T> for Dim4D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim4D {
     type Dim = Dim4D;
     type Array = [T; 4];
 }
-impl<T: Debug + Default + Clone + Eq + Hash + Send> DimFor<T> for Dim5D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim5D {
     type Dim = Dim5D;
     type Array = [T; 5];
 }
-impl<T: Debug + Default + Clone + Eq + Hash + Send> DimFor<T> for Dim6D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim6D {
     type Dim = Dim6D;
     type Array = [T; 6];
 }
@@ -119,3 +211,33 @@ mod private {
     impl Sealed for Dim5D {}
     impl Sealed for Dim6D {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests constructing an InlineArray from an iterator and from a fallible
+    /// function.
+    #[test]
+    fn test_inline_array() {
+        let array: InlineArray<usize, 4> = (10..14).collect();
+        assert_eq!([10, 11, 12, 13], array.0);
+        assert_eq!(&[11, 12], &array[1..3]);
+
+        let mut calls = 0;
+        let result = InlineArray::<usize, 4>::try_from_fn(|i| {
+            calls += 1;
+            if i == 1 {
+                Err("error")
+            } else {
+                Ok(i)
+            }
+        });
+        assert_eq!(Err("error"), result);
+        assert_eq!(2, calls);
+        assert_eq!(
+            Ok(InlineArray([0, 2, 4, 6])),
+            InlineArray::<usize, 4>::try_from_fn(|i| Ok::<_, ()>(i * 2))
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Grid topologies, which describe how the edges of a bounded grid are
+//! connected.
+//!
+//! These use the same notation as Golly's bounded grids, described here:
+//! http://golly.sourceforge.net/Help/bounded.html
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::*;
+
+/// The shape of a grid, and how its edges are connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology<D: Dim> {
+    /// How the edges of the grid are connected.
+    pub kind: TopologyKind,
+    /// The length of the grid along each axis, or zero for axes along which
+    /// the grid is infinite.
+    ///
+    /// As in Golly, the grid is centered on the origin, rounding up and to
+    /// the left (which is down in NDCell, since Golly has Y increasing
+    /// downwards).
+    pub size: UVec<D>,
+    /// For a torus, an axis along which cells are shifted when they cross the
+    /// other edges of the grid, and the amount by which they are shifted.
+    ///
+    /// This is stored as in Golly, where a cell crossing an edge in the
+    /// positive direction (which is down for the Y axis) is shifted in the
+    /// positive direction.
+    pub shift: Option<(Axis, isize)>,
+}
+
+/// The way that the edges of a bounded grid are connected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TopologyKind {
+    /// The edges are not connected, and cells beyond them are always in the
+    /// default state.
+    Plane,
+    /// Opposite edges are joined, so cells that cross an edge appear at the
+    /// opposite edge.
+    Torus,
+    /// Opposite edges are joined, but cells that cross the edges
+    /// perpendicular to the given axis are reflected along the other axis.
+    /// For example, `KleinBottle(Y)` has the top and bottom edges twisted.
+    KleinBottle(Axis),
+    /// Opposite edges are joined, and cells that cross any edge are reflected
+    /// along the other axis.
+    CrossSurface,
+}
+
+/// The topology of a 1D grid.
+pub type Topology1D = Topology<Dim1D>;
+/// The topology of a 2D grid.
+pub type Topology2D = Topology<Dim2D>;
+/// The topology of a 3D grid.
+pub type Topology3D = Topology<Dim3D>;
+/// The topology of a 4D grid.
+pub type Topology4D = Topology<Dim4D>;
+/// The topology of a 5D grid.
+pub type Topology5D = Topology<Dim5D>;
+/// The topology of a 6D grid.
+pub type Topology6D = Topology<Dim6D>;
+
+impl<D: Dim> Default for Topology<D> {
+    fn default() -> Self {
+        Self::infinite()
+    }
+}
+
+impl<D: Dim> Topology<D> {
+    /// Returns the topology of an unbounded grid.
+    pub fn infinite() -> Self {
+        Self::new(TopologyKind::Plane, UVec::origin())
+    }
+    /// Returns a topology of the given kind and size, with no shift.
+    pub fn new(kind: TopologyKind, size: UVec<D>) -> Self {
+        Self {
+            kind,
+            size,
+            shift: None,
+        }
+    }
+
+    /// Returns true if the grid is infinite along every axis.
+    pub fn is_infinite(&self) -> bool {
+        !D::axes().iter().any(|&ax| self.is_bounded(ax))
+    }
+    /// Returns true if the grid is bounded along the given axis.
+    pub fn is_bounded(&self, axis: Axis) -> bool {
+        self.size[axis] != 0
+    }
+
+    /// Returns the minimum coordinate of the grid along the given axis, if
+    /// the grid is bounded along that axis.
+    fn min(&self, axis: Axis) -> isize {
+        let len = self.size[axis] as isize;
+        if axis == Y {
+            len / 2 - (len - 1)
+        } else {
+            -(len / 2)
+        }
+    }
+    /// Returns the rectangle of cells in the grid. Along axes where the grid
+    /// is infinite, the rectangle has the same range as `pattern_rect`.
+    pub fn rect(&self, pattern_rect: &IRect<D>) -> IRect<D> {
+        let mut min = pattern_rect.min();
+        let mut max = pattern_rect.max();
+        for &ax in D::axes() {
+            if self.is_bounded(ax) {
+                min[ax] = self.min(ax);
+                max[ax] = self.min(ax) + self.size[ax] as isize - 1;
+            }
+        }
+        NdRect::span(min, max)
+    }
+
+    /// Returns the position of the cell in the grid that is the same as the
+    /// cell at the given position, or None if the position is beyond the
+    /// edges of a plane.
+    pub fn wrap_pos(&self, pos: &IVec<D>) -> Option<IVec<D>> {
+        let mut pos = pos.clone();
+        // Shifts and reflections may move a cell beyond another edge, so
+        // repeat until the cell is inside the grid.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &ax in D::axes() {
+                if !self.is_bounded(ax) {
+                    continue;
+                }
+                let len = self.size[ax] as isize;
+                let crossings = (pos[ax] - self.min(ax)).div_euclid(len);
+                if crossings == 0 {
+                    continue;
+                }
+                if self.kind == TopologyKind::Plane {
+                    return None;
+                }
+                changed = true;
+                pos[ax] -= crossings * len;
+                if let Some((shift_axis, amount)) = self.shift {
+                    if shift_axis != ax {
+                        pos[shift_axis] +=
+                            self.golly_sign(ax) * self.golly_sign(shift_axis) * crossings * amount;
+                    }
+                }
+                if crossings % 2 != 0 {
+                    for &other in D::axes() {
+                        if other != ax && self.is_flipped(ax) {
+                            let other_len = self.size[other] as isize;
+                            pos[other] = 2 * self.min(other) + other_len - 1 - pos[other];
+                        }
+                    }
+                }
+            }
+        }
+        Some(pos)
+    }
+    /// Returns true if cells that cross the edges perpendicular to the given
+    /// axis are reflected along the other axes.
+    fn is_flipped(&self, axis: Axis) -> bool {
+        match self.kind {
+            TopologyKind::KleinBottle(twisted_axis) => axis == twisted_axis,
+            TopologyKind::CrossSurface => true,
+            _ => false,
+        }
+    }
+    /// Returns -1 for the Y axis, which increases downwards in Golly, or 1 for
+    /// any other axis.
+    fn golly_sign(&self, axis: Axis) -> isize {
+        if axis == Y {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// Returns the maximum number of generations that can be simulated at
+    /// once in this topology using a rule with the given radius, or None if
+    /// there is no limit.
+    pub fn max_step_size(&self, radius: usize) -> Option<usize> {
+        if self.is_infinite() {
+            return None;
+        }
+        match self.kind {
+            // Every cell in a torus sees the same thing as the cell at the
+            // corresponding position in an infinite repeating grid, so many
+            // generations can be simulated at once. The limit only controls
+            // how much of that infinite grid to construct.
+            TopologyKind::Torus if radius > 0 => {
+                let min_len = D::axes()
+                    .iter()
+                    .map(|&ax| self.size[ax])
+                    .filter(|&len| len != 0)
+                    .min()
+                    .unwrap();
+                Some(std::cmp::max(1, min_len / radius))
+            }
+            TopologyKind::Torus => None,
+            // Otherwise the edges must be handled every generation.
+            _ => Some(1),
+        }
+    }
+}
+
+impl<D: Dim> fmt::Display for Topology<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            TopologyKind::Plane => 'P',
+            TopologyKind::Torus => 'T',
+            TopologyKind::KleinBottle(_) => 'K',
+            TopologyKind::CrossSurface => 'C',
+        };
+        write!(f, "{}", kind)?;
+        for &ax in D::axes() {
+            if ax != X {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", self.size[ax])?;
+            // An asterisk after the width twists the top and bottom edges.
+            match self.kind {
+                TopologyKind::KleinBottle(twisted_axis) if twisted_axis != ax => write!(f, "*")?,
+                _ => (),
+            }
+            match self.shift {
+                Some((shift_axis, amount)) if shift_axis == ax => write!(f, "{:+}", amount)?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for Topology<D> {
+    type Error = String;
+
+    /// Parses a topology using Golly's bounded grid notation (without the
+    /// leading colon), such as `T100,80` or `K40*,30`.
+    ///
+    /// A single size may be given for a square grid; otherwise there must be
+    /// one size for each axis.
+    fn try_from(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let mut chars = s.chars();
+        let kind = match chars.next().map(|ch| ch.to_ascii_uppercase()) {
+            Some('P') => TopologyKind::Plane,
+            Some('T') => TopologyKind::Torus,
+            // The twisted axis is filled in later.
+            Some('K') => TopologyKind::KleinBottle(Y),
+            Some('C') => TopologyKind::CrossSurface,
+            Some('S') => Err("Sphere topology is not supported")?,
+            _ => Err(format!("Invalid topology: {:?}", s))?,
+        };
+        let mut sizes: Vec<&str> = chars.as_str().split(',').map(str::trim).collect();
+        if sizes.len() == 1 {
+            sizes = vec![sizes[0]; D::NDIM];
+        }
+        if sizes.len() != D::NDIM {
+            Err(format!("Expected {} sizes in topology", D::NDIM))?;
+        }
+
+        let mut ret = Self::new(kind, UVec::origin());
+        let mut twisted_axes = vec![];
+        for (&ax, size) in D::axes().iter().zip(sizes) {
+            // Split off the shift, if there is one.
+            let (size, shift) = match size.find(['+', '-']) {
+                Some(i) => {
+                    let shift = size[i..]
+                        .trim_start_matches('+')
+                        .parse()
+                        .map_err(|_| format!("Invalid shift in topology: {:?}", s))?;
+                    (&size[..i], Some(shift))
+                }
+                None => (size, None),
+            };
+            let size = match size.strip_suffix('*') {
+                Some(size) => {
+                    twisted_axes.push(ax);
+                    size
+                }
+                None => size,
+            };
+            ret.size[ax] = size
+                .parse()
+                .map_err(|_| format!("Invalid size in topology: {:?}", s))?;
+            if let Some(shift) = shift {
+                if ret.shift.is_some() {
+                    Err("Topology cannot have more than one shift")?;
+                }
+                ret.shift = Some((ax, shift));
+            }
+        }
+
+        match kind {
+            TopologyKind::Plane | TopologyKind::CrossSurface if !twisted_axes.is_empty() => {
+                Err("Only a Klein bottle can have a twisted edge")?
+            }
+            TopologyKind::KleinBottle(_) => {
+                if D::NDIM != 2 {
+                    Err("Klein bottles must be 2D")?;
+                }
+                // An asterisk after the width twists the top and bottom edges,
+                // and an asterisk after the height twists the left and right
+                // edges. Without an asterisk, twist the left and right edges.
+                ret.kind = match twisted_axes.as_slice() {
+                    [] | [Y] => TopologyKind::KleinBottle(X),
+                    [X] => TopologyKind::KleinBottle(Y),
+                    _ => Err("Klein bottle must have only one twisted edge")?,
+                };
+            }
+            TopologyKind::CrossSurface if D::NDIM != 2 => Err("Cross-surfaces must be 2D")?,
+            _ => (),
+        }
+        if kind != TopologyKind::Plane
+            && kind != TopologyKind::Torus
+            && D::axes().iter().any(|&ax| !ret.is_bounded(ax))
+        {
+            Err("Only a plane or torus can be infinite along an axis")?;
+        }
+        if ret.shift.is_some() {
+            if kind != TopologyKind::Torus || D::NDIM != 2 {
+                Err("Only a 2D torus can have a shift")?;
+            }
+            if D::axes().iter().any(|&ax| !ret.is_bounded(ax)) {
+                Err("Only a bounded torus can have a shift")?;
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests parsing and displaying Golly's bounded grid notation.
+    #[test]
+    fn test_topology_parse() {
+        for s in &[
+            "P100,80", "T100,0", "T0,100", "T10+3,20", "T10,20-1", "K10*,20", "C30,30",
+        ] {
+            let topology = Topology::<Dim2D>::try_from(*s).unwrap();
+            assert_eq!(*s, topology.to_string());
+        }
+        assert_eq!(
+            "K10,20*",
+            Topology::<Dim2D>::try_from("K10,20").unwrap().to_string()
+        );
+        assert_eq!(
+            "T10,10,10",
+            Topology::<Dim3D>::try_from("t10").unwrap().to_string()
+        );
+        for s in &[
+            "X10,10",
+            "T10,10,10",
+            "S10",
+            "K10*,20*",
+            "K10,0",
+            "T0+1,10",
+            "P10*,10",
+        ] {
+            assert!(Topology::<Dim2D>::try_from(*s).is_err());
+        }
+    }
+
+    /// Tests mapping positions beyond the edges of various grids back into
+    /// the grid.
+    #[test]
+    fn test_topology_wrap_pos() {
+        // This grid spans from (-2, -1) to (2, 2). (Remember that Y is flipped
+        // compared to Golly.)
+        let topology = |s| Topology::<Dim2D>::try_from(s).unwrap();
+        assert_eq!(
+            NdRect::span(NdVec([-2, -1]), NdVec([2, 2])),
+            topology("P5,4").rect(&NdRect::single_cell(NdVec::origin()))
+        );
+        assert_eq!(None, topology("P5,4").wrap_pos(&NdVec([3, 0])));
+        assert_eq!(
+            Some(NdVec([-2, -1])),
+            topology("T5,4").wrap_pos(&NdVec([3, 3]))
+        );
+        // Moving off the bottom of the grid shifts the cell right, so moving
+        // off the top shifts it left.
+        assert_eq!(
+            Some(NdVec([-1, -1])),
+            topology("T5+1,4").wrap_pos(&NdVec([0, 3]))
+        );
+        // Moving off the right of the grid shifts the cell down.
+        assert_eq!(
+            Some(NdVec([-2, -1])),
+            topology("T5,4+1").wrap_pos(&NdVec([3, 0]))
+        );
+        // Moving off the left or right of a Klein bottle flips the cell
+        // vertically.
+        assert_eq!(
+            Some(NdVec([-2, 1])),
+            topology("K5,4*").wrap_pos(&NdVec([3, 0]))
+        );
+        assert_eq!(
+            Some(NdVec([0, -1])),
+            topology("K5,4*").wrap_pos(&NdVec([0, 3]))
+        );
+        // Moving off the corner of a cross-surface flips both axes.
+        assert_eq!(
+            Some(NdVec([2, -1])),
+            topology("C5,4").wrap_pos(&NdVec([3, -2]))
+        );
+        // Only the bounded axis of a tube wraps.
+        assert_eq!(
+            Some(NdVec([-2, 100])),
+            topology("T5,0").wrap_pos(&NdVec([3, 100]))
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use proptest::prelude::*;
+
+use super::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 64,
+        ..Default::default()
+    })]
+
+    /// Tests that a pattern on a periodic background evolves the same as on a
+    /// large finite copy of that background.
+    #[test]
+    fn test_agar_life(
+        tile_cells in prop::collection::vec(0..2u8, 16),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(4)), 0..10),
+        step_size in 1..8usize,
+    ) {
+        let tile = NdArray::from_fn(NdVec([4, 4]), |pos| {
+            tile_cells[(pos[X] + 4 * pos[Y]) as usize]
+        });
+        let mut tree = NdTree::with_background_tile(&tile);
+        // Information cannot travel from the edges of this to the cells that
+        // are checked within the number of generations simulated.
+        let mut finite_tree = NdTree::new();
+        for pos in IRect2D::moore(32).iter() {
+            finite_tree.set_cell(&pos.convert(), tile[&pos.mod_floor(&4)]);
+        }
+        for pos in cells_to_set {
+            tree.set_cell(&pos.convert(), 1);
+            finite_tree.set_cell(&pos.convert(), 1);
+        }
+        Simulation::from(rule::LIFE).step(&mut tree, &step_size.into());
+        Simulation::from(rule::LIFE).step(&mut finite_tree, &step_size.into());
+        for pos in IRect2D::moore(16).iter() {
+            let pos: BigVec2D = pos.convert();
+            assert_eq!(finite_tree.get_cell(&pos), tree.get_cell(&pos));
+        }
+        // Far away from the pattern, only the background matters.
+        let far_away: BigVec2D = NdVec::big([1, -1]) << 80;
+        for pos in IRect2D::centered(NdVec([18, 18]), 2).iter() {
+            let pos: BigVec2D = pos.convert();
+            assert_eq!(finite_tree.get_cell(&pos), tree.get_cell(&(&pos + &far_away)));
+        }
+    }
+}
+
+/// Tests simple agars in Conway's Game of Life.
+#[test]
+fn test_life_agars() {
+    let mut sim = Simulation::from(rule::LIFE);
+
+    // Zebra stripes are stable, and a single live cell between two stripes
+    // dies of overcrowding without disturbing them.
+    let stripes = NdArray::from_fn(NdVec([1, 2]), |pos| (pos[Y] == 0) as u8);
+    let mut tree = NdTree::with_background_tile(&stripes);
+    tree.set_cell(&NdVec::big([0, 1]), 1);
+    assert_eq!(BigInt::from(1), tree.get_population());
+    sim.step(&mut tree, &BigInt::from(1));
+    assert_eq!(BigInt::from(0), tree.get_population());
+    assert_eq!(1, tree.get_cell(&NdVec::big([5, 0])));
+    assert_eq!(0, tree.get_cell(&NdVec::big([5, 1])));
+
+    // Every cell in a checkerboard has four live neighbors, so the live cells
+    // die and the dead cells stay dead.
+    let checkerboard = NdArray::from_fn(NdVec([2, 2]), |pos| ((pos[X] + pos[Y]) % 2) as u8);
+    let mut tree = NdTree::with_background_tile(&checkerboard);
+    assert_eq!(1, tree.get_cell(&NdVec::big([1, 0])));
+    sim.step(&mut tree, &BigInt::from(1));
+    assert_eq!(Some(0), tree.get_background().leaf());
+    assert_eq!(BigInt::from(0), tree.get_population());
+}
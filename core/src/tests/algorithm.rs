@@ -0,0 +1,175 @@
+use proptest::prelude::*;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use super::*;
+
+/// Asserts that two trees have the same cells at the given positions.
+fn assert_same_cells<D: Dim>(
+    expected: &NdTree<u8, D>,
+    actual: &NdTree<u8, D>,
+    positions: impl IntoIterator<Item = IVec<D>>,
+) {
+    for pos in positions {
+        let pos: BigVec<D> = pos.convert();
+        assert_eq!(
+            expected.get_cell(&pos),
+            actual.get_cell(&pos),
+            "at {:?}",
+            pos
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        ..Default::default()
+    })]
+
+    /// Tests that the brute-force algorithm gives the same results as
+    /// HashLife for a variety of 2D rules and topologies.
+    #[test]
+    fn test_brute_force_2d(
+        rule in prop::sample::select(vec![
+            "B3/S23", "B36/S23", "B03/S23", "B0123478/S34678", "R2,C0,S2-3,B3-4,NM",
+        ]),
+        topology in prop::sample::select(vec!["", "T7,6", "K7,6*", "P0,8"]),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(4)), 0..30),
+        step_size in 1..8usize,
+    ) {
+        // With a B0 rule, a grid that is bounded along only some axes has a
+        // different background near its edges, which can't be represented.
+        prop_assume!(topology != "P0,8" || !rule.starts_with("B0"));
+        let rule = rule::from_rulestring::<Dim2D>(rule).unwrap();
+        let topology = if topology.is_empty() {
+            Topology2D::infinite()
+        } else {
+            Topology2D::try_from(topology).unwrap()
+        };
+        let mut tree = NdTree::new();
+        for pos in &cells_to_set {
+            tree.set_cell(&pos.convert(), 1);
+        }
+        let mut expected = tree.clone();
+        Simulation::new(rule.clone())
+            .with_topology(topology.clone())
+            .step(&mut expected, &step_size.into());
+        BruteForce::new(rule)
+            .with_topology(topology.clone())
+            .step(&mut tree, &step_size.into());
+        // Cells beyond the edges of the grid are meaningless.
+        let rect = IRect2D::moore(4 + 2 * step_size as isize + 1);
+        assert_same_cells(
+            &expected,
+            &tree,
+            rect.iter().filter(|pos| topology.wrap_pos(pos).as_ref() == Some(pos)),
+        );
+    }
+
+    /// Tests that the brute-force algorithm gives the same results as
+    /// HashLife on a periodic background.
+    #[test]
+    fn test_brute_force_agar(
+        tile_cells in prop::collection::vec(0..2u8, 8),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(4)), 0..10),
+        step_size in 1..8usize,
+    ) {
+        let tile = NdArray::from_fn(NdVec([4, 2]), |pos| {
+            tile_cells[(pos[X] + 4 * pos[Y]) as usize]
+        });
+        let mut tree = NdTree::with_background_tile(&tile);
+        for pos in cells_to_set {
+            tree.set_cell(&pos.convert(), 1);
+        }
+        let mut expected = tree.clone();
+        Simulation::from(rule::LIFE).step(&mut expected, &step_size.into());
+        BruteForce::from(rule::LIFE).step(&mut tree, &step_size.into());
+        assert_same_cells(&expected, &tree, IRect2D::moore(16).iter());
+        assert_eq!(expected.get_population(), tree.get_population());
+    }
+
+    /// Tests that the brute-force algorithm gives the same results as
+    /// HashLife in 3D.
+    #[test]
+    fn test_brute_force_3d(
+        cells_to_set in prop::collection::vec(any_with::<IVec3D>(Some(3)), 0..30),
+        step_size in 1..4usize,
+    ) {
+        let rule = rule::Totalistic3D::try_from("3D:B5/S4567").unwrap();
+        let mut tree = NdTree::new();
+        for pos in &cells_to_set {
+            tree.set_cell(&pos.convert(), 1);
+        }
+        let mut expected = tree.clone();
+        Simulation::from(rule.clone()).step(&mut expected, &step_size.into());
+        BruteForce::from(rule).step(&mut tree, &step_size.into());
+        assert_same_cells(&expected, &tree, IRect3D::moore(3 + step_size as isize).iter());
+        assert_eq!(expected.get_population(), tree.get_population());
+    }
+}
+
+/// Tests switching between algorithms while simulating a pattern.
+#[test]
+fn test_switch_algorithm() {
+    let mut automaton: Automaton2D =
+        RleEncode::from_rle("x = 3, y = 3, rule = B3/S23:T8,8\nbo$2bo$3o!").unwrap();
+    let mut expected = automaton.tree.clone();
+    assert_eq!(AlgorithmKind::HashLife, automaton.get_algorithm());
+    automaton.set_memory_limit(Some(1 << 20));
+
+    automaton.set_algorithm(AlgorithmKind::BruteForce);
+    assert_eq!(AlgorithmKind::BruteForce, automaton.get_algorithm());
+    assert_eq!("T8,8", automaton.sim.lock().unwrap().topology().to_string());
+    assert_eq!(Some(1 << 20), automaton.get_memory_limit());
+    assert_eq!(expected, automaton.tree);
+    automaton.step(&BigInt::from(7));
+
+    automaton.set_memory_limit(None);
+    automaton.set_algorithm(AlgorithmKind::HashLife);
+    assert_eq!(AlgorithmKind::HashLife, automaton.get_algorithm());
+    assert_eq!(None, automaton.get_memory_limit());
+    automaton.step(&BigInt::from(25));
+
+    // The glider has returned to the same place on the torus.
+    assert_eq!(BigInt::from(32), automaton.generations);
+    Simulation::from(rule::LIFE)
+        .with_topology(Topology2D::try_from("T8,8").unwrap())
+        .step(&mut expected, &BigInt::from(32));
+    assert_eq!(
+        get_non_default_set(&expected.slice),
+        get_non_default_set(&automaton.tree.slice)
+    );
+}
+
+/// Tests that the brute-force algorithm only copies the cells near the
+/// pattern, wherever it is, and returns an error for patterns that are too
+/// large to copy.
+#[test]
+fn test_brute_force_far_away() {
+    let far_away: BigVec2D = NdVec::big([1, -1]) << 100;
+    let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+    let mut tree = NdTree::new();
+    for pos in &glider {
+        tree.set_cell(&(NdVec::big(*pos) + &far_away), 1);
+    }
+    let mut sim = BruteForce::from(rule::LIFE);
+    sim.step(&mut tree, &BigInt::from(4));
+    let expected: HashSet<BigVec2D> = glider
+        .iter()
+        .map(|&pos| NdVec::big(pos) + &far_away + NdVec::big([1, 1]))
+        .collect();
+    assert_eq!(expected, get_non_default_set(&tree.slice));
+
+    // A block this far from the glider does not fit in an array.
+    for pos in IRect2D::moore(1)
+        .iter()
+        .filter(|pos| pos[X] >= 0 && pos[Y] >= 0)
+    {
+        tree.set_cell(&pos.convert(), 1);
+    }
+    let original = tree.clone();
+    let result = sim.try_step(&mut tree, &BigInt::from(1), &StepControl::new());
+    assert_eq!(Err(StepError::PatternTooLarge), result);
+    assert_eq!(original, tree);
+}
@@ -0,0 +1,143 @@
+use proptest::prelude::*;
+
+use super::*;
+
+/// Returns a strategy for lists of cell positions with up to 3 coordinates in
+/// the range `-max..=max`.
+fn cell_positions(max: isize, count: usize) -> impl Strategy<Value = Vec<Vec<isize>>> {
+    prop::collection::vec(prop::collection::vec(-max..=max, 3), 0..count)
+}
+
+/// Simulates the given cells with a random Life-like rule using HashLife
+/// (which simulates Life-like rules using bitwise operations) and using
+/// NaivePattern, and asserts that the results are the same.
+fn assert_bitwise_matches_naive<D: Dim>(
+    birth: &[usize],
+    survival: &[usize],
+    cells_to_set: &[Vec<isize>],
+    step_size: usize,
+) {
+    let max_count = 3_usize.pow(D::NDIM as u32) - 1;
+    let birth: Vec<usize> = birth.iter().map(|&n| n % (max_count + 1)).collect();
+    let survival: Vec<usize> = survival.iter().map(|&n| n % (max_count + 1)).collect();
+    let rule =
+        rule::NdTotalistic::<D>::new(2, rule::Neighborhood::Moore(1), false, &birth, &survival)
+            .unwrap();
+    assert!(Rule::<u8, D>::as_life_like(&rule).is_some());
+
+    let mut tree = NdTree::new();
+    for pos in cells_to_set {
+        let pos: IVec<D> = NdVec::from_fn(|ax| pos[ax as usize]);
+        tree.set_cell(&pos.convert(), 1);
+    }
+    let mut expected = NaivePattern::from(&tree);
+    expected.step_generations(&rule, step_size);
+    Simulation::from(rule).step(&mut tree, &step_size.into());
+    assert_eq!(expected, NaivePattern::from(&tree));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        ..Default::default()
+    })]
+
+    /// Tests that HashLife with bitwise operations matches the naive
+    /// simulation for random Life-like rules in 1D, 2D, and 3D.
+    #[test]
+    fn test_bitwise_life_like(
+        ndim in 1..=3usize,
+        birth in prop::collection::vec(0..100usize, 0..6),
+        survival in prop::collection::vec(0..100usize, 0..6),
+        cells_to_set in cell_positions(12, 60),
+        step_size in 1..40usize,
+    ) {
+        match ndim {
+            1 => assert_bitwise_matches_naive::<Dim1D>(&birth, &survival, &cells_to_set, step_size),
+            2 => assert_bitwise_matches_naive::<Dim2D>(&birth, &survival, &cells_to_set, step_size),
+            _ => assert_bitwise_matches_naive::<Dim3D>(&birth, &survival, &cells_to_set, step_size % 6 + 1),
+        }
+    }
+
+    /// Tests that the bitboard of a small node matches its cells, and that
+    /// the node can be rebuilt from its bitboard.
+    #[test]
+    fn test_node_bits(
+        bits_2d in any::<u64>(),
+        bits_3d in any::<u64>(),
+    ) {
+        fn check<D: Dim>(layer: usize, bits: u64) {
+            let cache = NdTreeCache::<u8, D>::new();
+            let len = 1 << layer;
+            let node = cache.get_small_node_from_cell_fn(layer, NdVec::origin(), &mut |pos| {
+                let mut idx = 0;
+                for &ax in D::axes().iter().rev() {
+                    idx = idx * len + pos[ax] as usize;
+                }
+                (bits >> idx & 1) as u8
+            });
+            assert_eq!(Some(bits), node.bits());
+            assert!(Arc::ptr_eq(&node, &cache.get_node_from_bits(layer, bits, 1)));
+        }
+        check::<Dim2D>(3, bits_2d);
+        check::<Dim3D>(2, bits_3d);
+        check::<Dim1D>(6, bits_2d);
+        check::<Dim2D>(2, bits_2d & 0xFFFF);
+    }
+
+    /// Tests that small nodes with only one non-default state are leaf chunks
+    /// with the same branches and inner node as if they were built from their
+    /// cells, and that adding another state turns them back into branches.
+    #[test]
+    fn test_leaf_chunks(
+        bits_2d in any::<u64>(),
+        bits_3d in any::<u64>(),
+        x in 0..8isize,
+        y in 0..8isize,
+    ) {
+        fn check<D: Dim>(layer: usize, bits: u64, pos: IVec<D>) {
+            let cache = NdTreeCache::<u8, D>::new();
+            let len = 1 << layer;
+            let bit = |pos: &IVec<D>| {
+                let mut idx = 0;
+                for &ax in D::axes().iter().rev() {
+                    idx = idx * len + pos[ax] as usize;
+                }
+                1 << idx
+            };
+            let cell = |pos: IVec<D>| if bits & bit(&pos) != 0 { 3 } else { 0 };
+            let node = cache.get_node_from_bits(layer, bits, 3);
+            assert!(node.branches().is_none());
+            assert_eq!(Some(&LeafChunk::new(layer, bits, 3)), node.leaf_chunk());
+            for (branch_idx, branch) in node.branch_iter(&cache) {
+                let offset: IVec<D> = branch_idx.branch_offset(layer);
+                let expected = cache.get_small_node_from_cell_fn(layer - 1, offset.clone(), &mut |pos| cell(pos));
+                assert_eq!(NdTreeBranch::Node(expected), branch);
+            }
+            let inner_offset = IVec::repeat(len as isize / 4);
+            let expected = cache.get_small_node_from_cell_fn(layer - 1, inner_offset, &mut |pos| cell(pos));
+            assert!(Arc::ptr_eq(&expected, &node.get_inner_node(&cache)));
+
+            let with_other_state = node.set_cell(&cache, &pos, 2);
+            assert_eq!(2, with_other_state.get_cell(&pos));
+            if bits & !bit(&pos) != 0 {
+                assert!(with_other_state.leaf_chunk().is_none());
+            }
+            let restored = with_other_state.set_cell(&cache, &pos, cell(pos.clone()));
+            assert!(Arc::ptr_eq(&node, &restored));
+        }
+        check::<Dim2D>(3, bits_2d, NdVec([x, y]));
+        check::<Dim3D>(2, bits_3d, NdVec([x % 4, y % 4, (x + y) % 4]));
+        check::<Dim1D>(6, bits_2d, NdVec([x * 8 + y]));
+    }
+}
+
+/// Tests that nodes with more than 64 cells do not have a bitboard.
+#[test]
+fn test_large_node_bits() {
+    let cache = NdTreeCache::<u8, Dim2D>::new();
+    assert!(NdTreeNode::<u8, Dim2D>::has_bits_at_layer(3));
+    assert!(!NdTreeNode::<u8, Dim2D>::has_bits_at_layer(4));
+    assert_eq!(None, cache.get_filled_node(4, 1).bits());
+    assert_eq!(Some(u64::MAX), cache.get_filled_node(3, 1).bits());
+}
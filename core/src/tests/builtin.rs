@@ -0,0 +1,67 @@
+use super::*;
+
+/// Tests an electron traveling along a wire in 2D and 3D Wireworld.
+#[test]
+fn test_wireworld() {
+    fn test_wire<D: rule::DimRules>() {
+        let mut tree = NdTree::new();
+        let mut sim = Simulation::new(rule::by_name::<D>("Wireworld").unwrap());
+        let pos = |x: isize| {
+            let mut ret = BigVec::<D>::origin();
+            ret[X] = x.into();
+            ret
+        };
+        for x in 0..20 {
+            tree.set_cell(&pos(x), 3);
+        }
+        tree.set_cell(&pos(0), 2);
+        tree.set_cell(&pos(1), 1);
+        sim.step(&mut tree, &10.into());
+        assert_eq!(2, tree.get_cell(&pos(10)));
+        assert_eq!(1, tree.get_cell(&pos(11)));
+        assert_eq!(BigInt::from(20), tree.get_root().population().to_bigint());
+    }
+    test_wire::<Dim2D>();
+    test_wire::<Dim3D>();
+}
+
+/// Tests that a Langton's loop reproduces.
+#[test]
+fn test_langtons_loops() {
+    let mut automaton = Automaton2D::from_rle(
+        "x = 15, y = 10, rule = Langtons-Loops
+.8B$BAG.AD.ADB$B.6B.B$BGB4.BAB$BAB4.BAB$B.B4.BAB$BGB4.BAB$BA6BA5B$B.GA
+.GA.G5AB$.13B!",
+    )
+    .unwrap();
+    assert_eq!(BigInt::from(86), automaton.get_population());
+    automaton.step(&151.into());
+    assert_eq!(BigInt::from(171), automaton.get_population());
+    automaton.step(&449.into());
+    assert_eq!(BigInt::from(1235), automaton.get_population());
+}
+
+/// Tests Rule 110 against a few generations computed by hand.
+#[test]
+fn test_rule_110() {
+    let mut tree = NdTree::new();
+    let mut sim = Simulation::new(rule::by_name::<Dim1D>("Rule 110").unwrap());
+    tree.set_cell(&NdVec::big([0]), 1);
+    let mut expected = vec![0];
+    for _ in 0..5 {
+        sim.step(&mut tree, &1.into());
+        // Rule 110 grows to the left.
+        let leftmost = expected[0] - 1;
+        let old = expected.clone();
+        expected = (leftmost..=0)
+            .filter(|x| {
+                let [l, c, r] = [x - 1, *x, x + 1].map(|x| old.contains(&x));
+                110 >> ((l as u8) << 2 | (c as u8) << 1 | r as u8) & 1 != 0
+            })
+            .collect();
+        assert_eq!(
+            make_cell_coords_set(expected.iter().map(|&x| NdVec([x])).collect()),
+            get_non_default_set(&tree.slice)
+        );
+    }
+}
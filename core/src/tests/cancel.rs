@@ -0,0 +1,110 @@
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+fn make_soup() -> NdTree<u8, Dim2D> {
+    let mut tree = NdTree::new();
+    for pos in IRect2D::moore(16).iter() {
+        if (pos[X] * 5 + pos[Y] * 3 + pos[X] * pos[Y]).rem_euclid(7) < 3 {
+            tree.set_cell(&pos.convert(), 1);
+        }
+    }
+    tree
+}
+
+/// Tests that a step that is cancelled before it starts does nothing.
+#[test]
+fn test_cancel_before_step() {
+    let mut tree = make_soup();
+    let original = tree.clone();
+    let control = StepControl::new();
+    control.cancel();
+    for algorithm in AlgorithmKind::ALL {
+        let mut sim = algorithm.new_algorithm(Arc::new(rule::LIFE), Topology2D::infinite());
+        let result = sim.try_step(&mut tree, &BigInt::from(16), &control);
+        assert_eq!(Err(StepError::Cancelled), result);
+        assert_eq!(original, tree);
+    }
+
+    let mut automaton = Automaton2D {
+        tree: make_soup(),
+        ..Default::default()
+    };
+    automaton.set_sim(Simulation::from(rule::LIFE));
+    let result = automaton.try_step(&BigInt::from(16), &control);
+    assert_eq!(Err(StepError::Cancelled), result);
+    assert_eq!(original, automaton.tree);
+    assert_eq!(&BigInt::from(0), automaton.get_generation_count());
+}
+
+/// Tests that a HashLife step cancelled partway through leaves the tree
+/// unchanged and the cache consistent, and that progress is reported.
+#[test]
+fn test_cancel_during_step() {
+    let step_size = BigInt::from(256);
+    let mut tree = make_soup();
+    let original = tree.clone();
+    let mut expected = make_soup();
+    Simulation::from(rule::LIFE).step(&mut expected, &step_size);
+
+    // Cancel the step from within the progress callback.
+    let reports = Arc::new(Mutex::new(vec![]));
+    let control = StepControl::new();
+    let control = {
+        let reports = reports.clone();
+        let handle = control.clone();
+        control.with_progress_callback(move |progress| {
+            reports.lock().unwrap().push(progress);
+            handle.cancel();
+        })
+    };
+    let mut sim = Simulation::from(rule::LIFE);
+    let result = sim.try_step(&mut tree, &step_size, &control);
+    assert_eq!(Err(StepError::Cancelled), result);
+    assert_eq!(original, tree);
+    let reports = reports.lock().unwrap();
+    assert!(!reports.is_empty());
+    assert!(reports[0].completed > 0);
+    assert!(reports[0].estimated_total > 0);
+    assert!(reports[0].fraction() < 1.0);
+
+    // The cached results from the cancelled step give the right answer.
+    sim.step(&mut tree, &step_size);
+    assert_eq!(
+        get_non_default_set(&expected.slice),
+        get_non_default_set(&tree.slice),
+    );
+}
+
+/// Tests that the brute-force algorithm reports progress each generation and
+/// can be cancelled partway through.
+#[test]
+fn test_cancel_brute_force() {
+    let mut tree = make_soup();
+    let original = tree.clone();
+    let reports = Arc::new(Mutex::new(vec![]));
+    let control = StepControl::new();
+    let control = {
+        let reports = reports.clone();
+        let handle = control.clone();
+        control.with_progress_callback(move |progress| {
+            reports.lock().unwrap().push(progress);
+            if progress.completed == 3 {
+                handle.cancel();
+            }
+        })
+    };
+    let result = BruteForce::from(rule::LIFE).try_step(&mut tree, &BigInt::from(10), &control);
+    assert_eq!(Err(StepError::Cancelled), result);
+    assert_eq!(original, tree);
+    assert_eq!(
+        vec![1, 2, 3],
+        reports
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|progress| progress.completed)
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(10, reports.lock().unwrap()[0].estimated_total);
+}
@@ -1,30 +1,5 @@
-use std::collections::HashSet;
-
 use super::*;
 
-fn get_non_default_set<C: CellType, D: Dim>(slice: &NdTreeSlice<C, D>) -> HashSet<BigVec<D>> {
-    let mut ret = HashSet::new();
-    for (branch_idx, branch) in slice.root.branch_iter() {
-        let branch_offset = &slice.offset + slice.root.branch_offset(branch_idx);
-        match branch {
-            NdTreeBranch::Leaf(cell_state) => {
-                if *cell_state != C::default() {
-                    ret.insert(branch_offset);
-                }
-            }
-            NdTreeBranch::Node(node) => ret.extend(get_non_default_set(&NdTreeSlice {
-                root: node.clone(),
-                offset: branch_offset,
-            })),
-        }
-    }
-    ret
-}
-
-fn make_cell_coords_set<D: Dim>(coords_vec: Vec<IVec<D>>) -> HashSet<BigVec<D>> {
-    coords_vec.iter().map(NdVec::convert).collect()
-}
-
 #[test]
 fn test_cgol() {
     let mut grid = NdTree::new();
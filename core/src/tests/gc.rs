@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use super::*;
+
+fn make_soup() -> NdTree<u8, Dim2D> {
+    let mut tree = NdTree::new();
+    for pos in IRect2D::moore(8).iter() {
+        if (pos[X] * 5 + pos[Y] * 3 + pos[X] * pos[Y]).rem_euclid(7) < 3 {
+            tree.set_cell(&pos.convert(), 1);
+        }
+    }
+    tree
+}
+
+/// Tests that garbage collection removes unused nodes without changing the
+/// simulation.
+#[test]
+fn test_collect_garbage() {
+    let mut tree = make_soup();
+    let mut expected_tree = make_soup();
+    let mut sim = Simulation::from(rule::LIFE).with_memory_limit(None);
+    let mut expected_sim = Simulation::from(rule::LIFE).with_memory_limit(None);
+    for _ in 0..20 {
+        sim.step(&mut tree, &BigInt::from(3));
+        expected_sim.step(&mut expected_tree, &BigInt::from(3));
+    }
+    let stats_before = sim.memory_stats(&tree.cache);
+    assert_eq!(0, stats_before.collections);
+
+    // Keep an old version of the tree, like an undo history entry would.
+    let history_entry = tree.clone();
+    sim.step(&mut tree, &BigInt::from(3));
+    expected_sim.step(&mut expected_tree, &BigInt::from(3));
+
+    sim.collect_garbage(&tree.cache);
+    let stats_after = sim.memory_stats(&tree.cache);
+    assert_eq!(1, stats_after.collections);
+    assert!(stats_after.node_count < stats_before.node_count);
+    assert!(stats_after.result_count < stats_before.result_count);
+    assert!(stats_after.estimated_bytes < stats_before.estimated_bytes);
+
+    // Nodes in the history entry are still in the cache.
+    let old_root = history_entry.get_root();
+    let new_root = tree.cache.get_node(old_root.branches().unwrap().clone());
+    assert!(Arc::ptr_eq(old_root, &new_root));
+
+    // The simulation continues as before.
+    for _ in 0..10 {
+        sim.step(&mut tree, &BigInt::from(3));
+        expected_sim.step(&mut expected_tree, &BigInt::from(3));
+    }
+    assert_eq!(
+        get_non_default_set(&expected_tree.slice),
+        get_non_default_set(&tree.slice),
+    );
+}
+
+/// Tests that garbage is collected automatically when the memory limit is
+/// exceeded.
+#[test]
+fn test_memory_limit() {
+    let mut tree = make_soup();
+    let mut expected_tree = make_soup();
+    let memory_limit = 64 * 1024;
+    let mut sim = Simulation::from(rule::LIFE).with_memory_limit(Some(memory_limit));
+    let mut expected_sim = Simulation::from(rule::LIFE).with_memory_limit(None);
+    for _ in 0..50 {
+        sim.step(&mut tree, &BigInt::from(2));
+        expected_sim.step(&mut expected_tree, &BigInt::from(2));
+        assert!(sim.memory_stats(&tree.cache).estimated_bytes <= memory_limit);
+    }
+    assert_ne!(0, sim.memory_stats(&tree.cache).collections);
+    assert_eq!(
+        0,
+        expected_sim.memory_stats(&expected_tree.cache).collections
+    );
+    assert_eq!(
+        get_non_default_set(&expected_tree.slice),
+        get_non_default_set(&tree.slice),
+    );
+}
+
+/// Tests that results cached while simulating with one step size are reused
+/// with a different step size.
+#[test]
+fn test_reuse_results_across_step_sizes() {
+    let original = make_soup();
+    let mut tree = original.clone();
+    let mut sim = Simulation::from(rule::LIFE).with_memory_limit(None);
+    sim.step(&mut original.clone(), &BigInt::from(64));
+    let results_after_64 = sim.memory_stats(&tree.cache).result_count;
+
+    // 100 = 64 + 32 + 4, and the first 64 generations are already cached.
+    let mut fresh_tree = make_soup();
+    let mut fresh_sim = Simulation::from(rule::LIFE).with_memory_limit(None);
+    fresh_sim.step(&mut fresh_tree, &BigInt::from(100));
+    sim.step(&mut tree, &BigInt::from(100));
+    let new_results = sim.memory_stats(&tree.cache).result_count - results_after_64;
+    let fresh_results = fresh_sim.memory_stats(&fresh_tree.cache).result_count;
+    assert!(new_results < fresh_results);
+    assert_eq!(
+        get_non_default_set(&fresh_tree.slice),
+        get_non_default_set(&tree.slice),
+    );
+
+    // Stepping by 64 again computes nothing new.
+    let results_before = sim.memory_stats(&tree.cache).result_count;
+    sim.step(&mut original.clone(), &BigInt::from(64));
+    assert_eq!(results_before, sim.memory_stats(&tree.cache).result_count);
+}
+
+/// Tests that a node looked up from a shared cache while garbage is being
+/// collected is kept in the cache.
+#[test]
+fn test_collect_garbage_shared_cache() {
+    let tree = make_soup();
+    let cache = tree.cache.clone();
+    // The root node is no longer in use once the tree is dropped, but its
+    // branches are.
+    let branches = tree.get_root().branches().unwrap().clone();
+    drop(tree);
+
+    let mut lookup = None;
+    cache.collect_garbage(|_, _| {
+        if lookup.is_none() {
+            // Look up the root node from another thread in the middle of the
+            // collection.
+            let cache = cache.clone();
+            let branches = branches.clone();
+            lookup = Some(std::thread::spawn(move || cache.get_node(branches)));
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    });
+    let root = lookup.unwrap().join().unwrap();
+    assert!(Arc::ptr_eq(&root, &cache.get_node(branches)));
+}
@@ -0,0 +1,203 @@
+use num::ToPrimitive;
+use proptest::prelude::*;
+use std::convert::TryFrom;
+
+use super::*;
+
+/// Returns a strategy that generates arbitrary Hensel notation for a set of
+/// neighborhood configurations, excluding 0.
+fn hensel_conditions() -> impl Strategy<Value = String> {
+    let letters = ["ce", "cekain", "cekainyqjr", "cekainyqjrtwz"];
+    let counts: Vec<_> = (1..=8)
+        .map(|count: usize| {
+            let letters: Vec<char> = match std::cmp::min(count, 8 - count) {
+                0 => vec![],
+                i => letters[i - 1].chars().collect(),
+            };
+            let len = letters.len();
+            (any::<bool>(), prop::sample::subsequence(letters, 0..=len)).prop_map(
+                move |(present, letters)| match present {
+                    false => String::new(),
+                    true => format!("{}{}", count, letters.into_iter().collect::<String>()),
+                },
+            )
+        })
+        .collect();
+    counts.prop_map(|conditions| conditions.concat())
+}
+
+proptest! {
+    /// Tests that the isotropic rule matches the 2D Life rule.
+    #[test]
+    fn test_isotropic_life(
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(8)), 0..40),
+        step_size in 1..20usize,
+    ) {
+        let mut tree_1 = NdTree::new();
+        for pos in cells_to_set {
+            tree_1.set_cell(&pos.convert(), 1);
+        }
+        let mut tree_2 = tree_1.clone();
+        Simulation::from(rule::LIFE).step(&mut tree_1, &step_size.into());
+        Simulation::from(rule::Isotropic2D::try_from("B3/S23").unwrap())
+            .step(&mut tree_2, &step_size.into());
+        assert_eq!(
+            get_non_default_set(&tree_1.slice),
+            get_non_default_set(&tree_2.slice),
+        );
+    }
+
+    /// Tests that isotropic rules are invariant under rotation and
+    /// reflection.
+    #[test]
+    fn test_isotropic_symmetry(
+        birth in hensel_conditions(),
+        survival in hensel_conditions(),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(4)), 0..20),
+        step_size in 1..4usize,
+    ) {
+        let rulestring = format!("B{}/S{}", birth, survival);
+        let rule = Arc::new(rule::Isotropic2D::try_from(&*rulestring).unwrap());
+        let transforms: [fn(IVec2D) -> IVec2D; 3] = [
+            |NdVec([x, y])| NdVec([-y, x]),
+            |NdVec([x, y])| NdVec([-x, y]),
+            |NdVec([x, y])| NdVec([y, x]),
+        ];
+        for transform in &transforms {
+            let mut tree_1 = NdTree::new();
+            let mut tree_2 = NdTree::new();
+            for pos in &cells_to_set {
+                tree_1.set_cell(&pos.convert(), 1);
+                tree_2.set_cell(&transform(*pos).convert(), 1);
+            }
+            Simulation::new(rule.clone()).step(&mut tree_1, &step_size.into());
+            Simulation::new(rule.clone()).step(&mut tree_2, &step_size.into());
+            let expected: HashSet<_> = get_non_default_set(&tree_1.slice)
+                .into_iter()
+                .map(|pos| transform(NdVec([pos[X].to_isize().unwrap(), pos[Y].to_isize().unwrap()])))
+                .map(|pos| pos.convert())
+                .collect();
+            assert_eq!(expected, get_non_default_set(&tree_2.slice), "{}", rulestring);
+        }
+    }
+}
+
+/// Tests Hensel notation against the configurations drawn for each letter on
+/// LifeWiki, using the blinker and beacon oscillators from Life.
+///
+/// The middle cell of a blinker survives with two opposite neighbors (2i) and
+/// each end is born with a whole side of neighbors (3i). The inner corners of
+/// a beacon are born with three neighbors around a corner (3a).
+#[test]
+fn test_hensel_oscillators() {
+    let blinker = [
+        vec![[-1, 0], [0, 0], [1, 0]],
+        vec![[0, -1], [0, 0], [0, 1]],
+        vec![[-1, 0], [0, 0], [1, 0]],
+    ];
+    let beacon = [
+        vec![
+            [0, 0],
+            [1, 0],
+            [0, 1],
+            [1, 1],
+            [2, 2],
+            [3, 2],
+            [2, 3],
+            [3, 3],
+        ],
+        vec![[0, 0], [1, 0], [0, 1], [2, 3], [3, 2], [3, 3]],
+        vec![
+            [0, 0],
+            [1, 0],
+            [0, 1],
+            [1, 1],
+            [2, 2],
+            [3, 2],
+            [2, 3],
+            [3, 3],
+        ],
+    ];
+    let life = "B3cekainyqjr/S2cekain3cekainyqjr";
+    assert_hensel_generations(life, &blinker);
+    assert_hensel_generations(life, &beacon);
+    assert_hensel_generations(
+        "B3/S2-i3",
+        &[blinker[0].clone(), vec![[0, -1], [0, 1]], vec![]],
+    );
+    assert_hensel_generations("B3/S2i3", &blinker);
+    assert_hensel_generations("B3-i/S23", &[blinker[0].clone(), vec![[0, 0]], vec![]]);
+    assert_hensel_generations(
+        "B3-a/S23",
+        &[beacon[0].clone(), beacon[1].clone(), beacon[1].clone()],
+    );
+    assert_hensel_generations("B3a/S23", &beacon);
+}
+
+/// Asserts that the given isotropic rule produces each of the given sets of
+/// live cells in successive generations, starting from the first one.
+fn assert_hensel_generations(rulestring: &str, generations: &[Vec<[isize; 2]>]) {
+    let mut sim = Simulation::from(rule::Isotropic2D::try_from(rulestring).unwrap());
+    let mut tree = NdTree::new();
+    for &pos in &generations[0] {
+        tree.set_cell(&NdVec(pos).convert(), 1);
+    }
+    for (i, cells) in generations.iter().enumerate().skip(1) {
+        sim.step(&mut tree, &1.into());
+        let expected = make_cell_coords_set(cells.iter().map(|&pos| NdVec(pos)).collect());
+        assert_eq!(
+            expected,
+            get_non_default_set(&tree.slice),
+            "{} generation {}",
+            rulestring,
+            i
+        );
+    }
+}
+
+/// Tests each 4-neighbor letter of Hensel notation against the configuration
+/// drawn for it on LifeWiki: a dead cell with exactly that configuration of
+/// neighbors is born under `B4x` for its own letter `x` and no other.
+#[test]
+fn test_hensel_4_neighbor_letters() {
+    // Rows are from north to south, and the middle cell is the one being born.
+    let configurations = [
+        ('c', ["o.o", "...", "o.o"]),
+        ('e', [".o.", "o.o", ".o."]),
+        ('k', [".oo", "o..", "..o"]),
+        ('a', [".oo", "..o", "..o"]),
+        ('i', [".oo", "...", ".oo"]),
+        ('n', ["ooo", "...", "..o"]),
+        ('y', [".oo", "...", "o.o"]),
+        ('q', [".oo", "..o", "o.."]),
+        ('j', [".oo", "o..", ".o."]),
+        ('r', [".oo", "..o", ".o."]),
+        ('t', ["ooo", "...", ".o."]),
+        ('w', [".oo", "o..", "o.."]),
+        ('z', [".oo", "...", "oo."]),
+    ];
+    for &(letter, rows) in &configurations {
+        let mut tree = NdTree::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == 'o' {
+                    let pos = NdVec([x as isize - 1, 1 - y as isize]);
+                    tree.set_cell(&pos.convert(), 1);
+                }
+            }
+        }
+        for &(other_letter, _) in &configurations {
+            let rulestring = format!("B4{}/S", other_letter);
+            let mut tree = tree.clone();
+            Simulation::from(rule::Isotropic2D::try_from(&*rulestring).unwrap())
+                .step(&mut tree, &1.into());
+            assert_eq!(
+                letter == other_letter,
+                tree.get_cell(&NdVec::origin()) == 1,
+                "4{} under {}",
+                letter,
+                rulestring,
+            );
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+/// Tests that a pattern using more states than its rule describes is still
+/// simulated correctly, without being stored in the rule's lookup table.
+#[test]
+fn test_lookup_table_extra_states() {
+    let mut tree = NdTree::<u8, Dim2D>::new();
+    let cells = [(0, 0, 1), (1, 0, 2), (3, 2, 3), (-4, 5, 1)];
+    for &(x, y, state) in &cells {
+        tree.set_cell(&NdVec::big([x, y]), state);
+    }
+    let mut sim = Simulation::new(Arc::new(DummyRule));
+    // Step twice so that the second step uses any results stored in the
+    // table.
+    for _ in 0..2 {
+        sim.step(&mut tree, &BigInt::from(4));
+        for &(x, y, state) in &cells {
+            assert_eq!(state, tree.get_cell(&NdVec::big([x, y])));
+        }
+    }
+}
+
+/// Tests that results from the lookup table match those computed directly,
+/// using the same rule on the same pattern many times.
+#[test]
+fn test_lookup_table_reuse() {
+    let rule = rule::from_rulestring::<Dim1D>("W110").unwrap();
+    let mut tree = NdTree::<u8, Dim1D>::new();
+    tree.set_cell(&NdVec::big([0]), 1);
+    let mut expected = NaivePattern::from(&tree);
+    expected.step_generations(&*rule, 50);
+    let mut sim = Simulation::new(rule);
+    for _ in 0..50 {
+        sim.step(&mut tree, &BigInt::from(1));
+    }
+    assert_eq!(expected, NaivePattern::from(&tree));
+}
+
+/// Tests that a 3D rule using the 3x3x3 neighborhood is simulated correctly
+/// using its lookup table.
+#[test]
+fn test_lookup_table_3d() {
+    let rule: Arc<dyn Rule<u8, Dim3D>> = Arc::new(
+        rule::NdTotalistic::new(2, rule::Neighborhood::VonNeumann(1), false, &[1], &[1, 2])
+            .unwrap(),
+    );
+    assert!(rule.as_life_like().is_none());
+    let mut tree = NdTree::<u8, Dim3D>::new();
+    for pos in IRect3D::moore(3).iter() {
+        if (pos[X] * 7 + pos[Y] * 3 + pos[Z] * 5).rem_euclid(4) == 0 {
+            tree.set_cell(&pos.convert(), 1);
+        }
+    }
+    let mut expected = NaivePattern::from(&tree);
+    expected.step_generations(&*rule, 8);
+    let mut sim = Simulation::new(rule);
+    for _ in 0..8 {
+        sim.step(&mut tree, &BigInt::from(1));
+    }
+    assert_eq!(expected, NaivePattern::from(&tree));
+}
+
+/// Tests that simulations using the same rule share its lookup table.
+#[test]
+fn test_lookup_table_shared() {
+    let rule = rule::from_rulestring::<Dim1D>("W30").unwrap();
+    let mut tree = NdTree::<u8, Dim1D>::new();
+    tree.set_cell(&NdVec::big([0]), 1);
+    Simulation::new(rule.clone()).step(&mut tree, &BigInt::from(8));
+
+    // Nothing else is in memory for these simulations, so this only counts
+    // the lookup table.
+    let empty_cache = NdTreeCache::new();
+    let same_rule = Simulation::new(rule);
+    assert_ne!(0, same_rule.memory_stats(&empty_cache).estimated_bytes);
+    let new_rule = Simulation::new(rule::from_rulestring::<Dim1D>("W30").unwrap());
+    assert_eq!(0, new_rule.memory_stats(&empty_cache).estimated_bytes);
+}
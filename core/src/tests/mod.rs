@@ -1,3 +1,52 @@
+use std::collections::HashSet;
+
 use super::*;
 
+mod agar;
+mod algorithm;
+mod bitwise;
+mod builtin;
+mod cancel;
 mod cgol;
+mod gc;
+mod isotropic;
+mod lookup;
+mod naive;
+mod rule_tree;
+mod table;
+mod topology;
+mod totalistic;
+mod wolfram;
+
+fn get_non_default_set<C: CellType, D: Dim>(slice: &NdTreeSlice<C, D>) -> HashSet<BigVec<D>> {
+    let mut ret = HashSet::new();
+    // A leaf chunk has no branches to recurse into, so check each of its cells.
+    if slice.root.leaf_chunk().is_some() {
+        for pos in slice.rect().iter() {
+            if slice.get_cell(&pos) != Some(C::default()) {
+                ret.insert(pos);
+            }
+        }
+        return ret;
+    }
+    for (array_idx, branch) in slice.root.branches().unwrap().iter().enumerate() {
+        let branch_offset =
+            &slice.offset + slice.root.branch_offset(ByteVec::from_array_idx(array_idx));
+        match branch {
+            NdTreeBranch::Leaf(cell_state) => {
+                if *cell_state != C::default() {
+                    ret.insert(branch_offset);
+                }
+            }
+            NdTreeBranch::Node(node) => ret.extend(get_non_default_set(&NdTreeSlice {
+                root: node.clone(),
+                offset: branch_offset,
+            })),
+        }
+    }
+    ret
+}
+
+fn make_cell_coords_set<D: Dim>(coords_vec: Vec<IVec<D>>) -> HashSet<BigVec<D>> {
+    coords_vec.iter().map(NdVec::convert).collect()
+}
@@ -0,0 +1,159 @@
+use proptest::prelude::*;
+use std::convert::TryFrom;
+
+use super::*;
+
+/// Simulates the given cells using HashLife and using NaivePattern, and
+/// asserts that the results are the same.
+fn assert_matches_naive<D: Dim>(
+    sim_rule: Arc<dyn Rule<u8, D>>,
+    naive_rule: &dyn Rule<u8, D>,
+    cells_to_set: &[Vec<isize>],
+    step_size: usize,
+) {
+    let mut tree = NdTree::new();
+    for pos in cells_to_set {
+        let pos: IVec<D> = NdVec::from_fn(|ax| pos[ax as usize]);
+        tree.set_cell(&pos.convert(), 1);
+    }
+    let mut expected = NaivePattern::from(&tree);
+    expected.step_generations(naive_rule, step_size);
+    Simulation::new(sim_rule).step(&mut tree, &step_size.into());
+    assert_eq!(expected, NaivePattern::from(&tree));
+}
+
+/// Constructs an outer-totalistic rule from arbitrary numbers, wrapping the
+/// neighbor counts to fit the neighborhood.
+fn make_totalistic<D: Dim>(
+    states: usize,
+    von_neumann: bool,
+    radius: usize,
+    birth: &[usize],
+    survival: &[usize],
+) -> rule::NdTotalistic<D> {
+    let neighborhood = if von_neumann {
+        rule::Neighborhood::VonNeumann(radius)
+    } else {
+        rule::Neighborhood::Moore(radius)
+    };
+    let max_count = neighborhood.neighbors().len();
+    let birth: Vec<usize> = birth.iter().map(|&n| n % (max_count + 1)).collect();
+    let survival: Vec<usize> = survival.iter().map(|&n| n % (max_count + 1)).collect();
+    rule::NdTotalistic::new(states, neighborhood, false, &birth, &survival).unwrap()
+}
+
+/// Returns a strategy for lists of cell positions with up to 4 coordinates
+/// in the range `-max..=max`.
+fn cell_positions(max: isize, count: usize) -> impl Strategy<Value = Vec<Vec<isize>>> {
+    prop::collection::vec(prop::collection::vec(-max..=max, 4), 0..count)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        ..Default::default()
+    })]
+
+    /// Tests that HashLife matches the naive simulation for random
+    /// outer-totalistic rules in 1D, 2D, 3D, and 4D.
+    #[test]
+    fn test_random_totalistic(
+        ndim in 1..=4usize,
+        states in 2..5usize,
+        von_neumann in any::<bool>(),
+        radius in 1..=2usize,
+        birth in prop::collection::vec(1..1000usize, 0..6),
+        survival in prop::collection::vec(0..1000usize, 0..6),
+        cells_to_set in cell_positions(4, 20),
+        step_size in 1..6usize,
+    ) {
+        // Large neighborhoods in many dimensions are very slow to simulate
+        // naively.
+        let radius = if ndim > 2 { 1 } else { radius };
+        macro_rules! check {
+            ($dim:ty) => {{
+                let rule = make_totalistic::<$dim>(states, von_neumann, radius, &birth, &survival);
+                assert_matches_naive(Arc::new(rule.clone()), &rule, &cells_to_set, step_size);
+            }};
+        }
+        match ndim {
+            1 => check!(Dim1D),
+            2 => check!(Dim2D),
+            3 => check!(Dim3D),
+            _ => check!(Dim4D),
+        }
+    }
+
+    /// Tests that HashLife matches the naive simulation for rules with "B0"
+    /// behavior, whose background changes.
+    #[test]
+    fn test_random_b0(
+        survival in prop::collection::vec(0..9usize, 0..6),
+        birth in prop::collection::vec(1..9usize, 0..6),
+        cells_to_set in cell_positions(6, 40),
+        step_size in 1..10usize,
+    ) {
+        let mut birth = birth;
+        birth.push(0);
+        let rule = make_totalistic::<Dim2D>(2, false, 1, &birth, &survival);
+        assert_matches_naive(Arc::new(rule.clone()), &rule, &cells_to_set, step_size);
+    }
+
+    /// Tests that HashLife matches the naive simulation for random 1D rules
+    /// given by Wolfram codes.
+    #[test]
+    fn test_random_wolfram(
+        radius in 1..=3usize,
+        code in any::<u64>(),
+        cells_to_set in cell_positions(8, 12),
+        step_size in 1..20usize,
+    ) {
+        // Keep only the bits that fit the neighborhood, without B0.
+        let bits = 1u64.checked_shl(1 << (2 * radius + 1)).map_or(code, |limit| code % limit);
+        let rulestring = format!("R{},W{}", radius, bits & !1);
+        let rule = rule::Wolfram1D::try_from(&*rulestring).unwrap();
+        let rule: Arc<dyn Rule<u8, Dim1D>> = Arc::new(rule);
+        assert_matches_naive(rule.clone(), &*rule, &cells_to_set, step_size);
+    }
+
+    /// Tests that the 2-state 2D Moore-neighborhood rule parses its
+    /// rulestring correctly, by comparing it to a naive simulation of the
+    /// generic outer-totalistic rule.
+    #[test]
+    fn test_moore_totalistic_2d(
+        birth in prop::collection::btree_set(1..9usize, 0..5),
+        survival in prop::collection::btree_set(0..9usize, 0..5),
+        cells_to_set in cell_positions(6, 40),
+        step_size in 1..10usize,
+    ) {
+        let digits = |counts: &std::collections::BTreeSet<usize>| -> String {
+            counts.iter().map(usize::to_string).collect()
+        };
+        let rulestring = format!("B{}/S{}", digits(&birth), digits(&survival));
+        let rule = rule::MooreTotalistic2D::try_from(&*rulestring).unwrap();
+        assert_eq!(rulestring, rule.to_string());
+        let birth: Vec<usize> = birth.into_iter().collect();
+        let survival: Vec<usize> = survival.into_iter().collect();
+        let naive_rule = make_totalistic::<Dim2D>(2, false, 1, &birth, &survival);
+        assert_matches_naive(Arc::new(rule), &naive_rule, &cells_to_set, step_size);
+    }
+}
+
+/// Tests that NaivePattern simulates a glider correctly.
+#[test]
+fn test_naive_glider() {
+    let mut pattern = NaivePattern::<u8, Dim2D>::default();
+    for &(x, y) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+        pattern.set_cell(NdVec([x, y]), 1);
+    }
+    let initial = pattern.clone();
+    pattern.step_generations(&rule::LIFE, 4);
+    assert_eq!(5, pattern.cells.len());
+    for (pos, &cell_state) in &initial.cells {
+        assert_eq!(cell_state, pattern.get_cell(&(pos + NdVec([1, 1]))));
+    }
+    assert_eq!(
+        Some(NdRect::span(NdVec([1, 1]), NdVec([3, 3]))),
+        pattern.bounding_rect()
+    );
+}
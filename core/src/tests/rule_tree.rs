@@ -0,0 +1,141 @@
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+use super::*;
+
+/// Builds the text of a Golly rule file with a `@TREE` section for the given
+/// transition function, which takes the states of the cells in the order that
+/// they are checked in the tree.
+fn make_rule_tree(
+    num_states: usize,
+    num_neighbors: usize,
+    f: impl Fn(&[usize]) -> usize,
+) -> String {
+    struct Builder<F> {
+        num_states: usize,
+        num_cells: usize,
+        f: F,
+        nodes: Vec<String>,
+        indices: HashMap<String, usize>,
+    }
+    impl<F: Fn(&[usize]) -> usize> Builder<F> {
+        /// Returns the index of the node for the given states of the first
+        /// few cells (or the next state, if all the cells are given).
+        fn node(&mut self, prefix: &mut Vec<usize>) -> usize {
+            if prefix.len() == self.num_cells {
+                return (self.f)(prefix);
+            }
+            let mut line = (self.num_cells - prefix.len()).to_string();
+            for state in 0..self.num_states {
+                prefix.push(state);
+                line.push_str(&format!(" {}", self.node(prefix)));
+                prefix.pop();
+            }
+            if let Some(&idx) = self.indices.get(&line) {
+                return idx;
+            }
+            self.nodes.push(line.clone());
+            self.indices.insert(line, self.nodes.len() - 1);
+            self.nodes.len() - 1
+        }
+    }
+    let mut builder = Builder {
+        num_states,
+        num_cells: num_neighbors + 1,
+        f,
+        nodes: vec![],
+        indices: HashMap::new(),
+    };
+    builder.node(&mut vec![]);
+    format!(
+        "@RULE Test\n@TREE\nnum_states={}\nnum_neighbors={}\nnum_nodes={}\n{}\n",
+        num_states,
+        num_neighbors,
+        builder.nodes.len(),
+        builder.nodes.join("\n"),
+    )
+}
+
+proptest! {
+    /// Tests that a rule tree for Life matches the built-in Life rule.
+    #[test]
+    fn test_rule_tree_life(
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(8)), 0..40),
+        step_size in 1..20usize,
+    ) {
+        let rule_file = make_rule_tree(2, 8, |cells| {
+            match (cells[8], cells[..8].iter().sum()) {
+                (_, 3) | (1, 2) => 1,
+                _ => 0,
+            }
+        });
+        let mut tree_1 = NdTree::new();
+        for pos in cells_to_set {
+            tree_1.set_cell(&pos.convert(), 1);
+        }
+        let mut tree_2 = tree_1.clone();
+        Simulation::from(rule::LIFE).step(&mut tree_1, &step_size.into());
+        Simulation::new(rule_file::load_rule(&rule_file).unwrap())
+            .step(&mut tree_2, &step_size.into());
+        assert_eq!(
+            get_non_default_set(&tree_1.slice),
+            get_non_default_set(&tree_2.slice),
+        );
+    }
+
+    /// Tests that a rule tree and a rule table for the same arbitrary
+    /// von Neumann neighborhood rule produce the same results.
+    #[test]
+    fn test_rule_tree_vs_table(
+        mut outputs in prop::collection::vec(0..3usize, 243),
+        cells_to_set in prop::collection::vec((any_with::<IVec2D>(Some(6)), 1..3u8), 0..30),
+        step_size in 1..8usize,
+    ) {
+        // Keep empty space empty.
+        outputs[0] = 0;
+        // Index the outputs by the cells in the order C, N, E, S, W.
+        let index = |c: usize, n: usize, e: usize, s: usize, w: usize| {
+            (((c * 3 + n) * 3 + e) * 3 + s) * 3 + w
+        };
+        let tree_file = make_rule_tree(3, 4, |cells| {
+            let (n, w, e, s, c) = (cells[0], cells[1], cells[2], cells[3], cells[4]);
+            outputs[index(c, n, e, s, w)]
+        });
+        let mut table_file = "@RULE Test\n@TABLE\nn_states:3\nneighborhood:vonNeumann\n".to_owned();
+        for (i, output) in outputs.iter().enumerate() {
+            let mut config = String::new();
+            let mut i = i;
+            for _ in 0..5 {
+                config.insert(0, std::char::from_digit((i % 3) as u32, 10).unwrap());
+                i /= 3;
+            }
+            table_file.push_str(&format!("{}{}\n", config, output));
+        }
+
+        let mut tree_1 = NdTree::new();
+        for (pos, state) in cells_to_set {
+            tree_1.set_cell(&pos.convert(), state);
+        }
+        let mut tree_2 = tree_1.clone();
+        Simulation::new(rule_file::load_rule(&tree_file).unwrap())
+            .step(&mut tree_1, &step_size.into());
+        Simulation::new(rule_file::load_rule(&table_file).unwrap())
+            .step(&mut tree_2, &step_size.into());
+        assert_eq!(tree_1.slice, tree_2.slice);
+    }
+}
+
+/// Tests that invalid rule trees are rejected with line numbers.
+#[test]
+fn test_rule_tree_errors() {
+    let load = |s: &str| rule_file::load_rule::<Dim2D>(s).map(|_| ());
+    let header = "@RULE Test\n@TREE\nnum_states=2\nnum_neighbors=4\n";
+    assert!(load(&format!("{}num_nodes=1\n5 0 0\n", header)).is_err());
+    let e = load(&format!("{}num_nodes=2\n1 0 1\n2 0 2\n", header)).unwrap_err();
+    assert!(e.contains("--> 7:"), "{}", e);
+    let e = load(&format!("{}num_nodes=2\n1 0 1\n1 0 2\n", header)).unwrap_err();
+    assert!(e.contains("--> 7:") && e.contains("out of range"), "{}", e);
+    let e = load(&format!("{}num_nodes=1\n1 0 1 1\n", header)).unwrap_err();
+    assert!(e.contains("--> 6:") && e.contains("children"), "{}", e);
+    assert!(rule_file::load_rule::<Dim3D>(&make_rule_tree(2, 4, |_| 0)).is_err());
+}
@@ -0,0 +1,92 @@
+use proptest::prelude::*;
+
+use super::*;
+
+/// Conway's Game of Life as a Golly rule table.
+const LIFE_TABLE: &str = "@RULE LifeTable
+A description of the rule, which should be ignored.
+
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+var i={a}
+# Birth
+0,1,1,1,0,0,0,0,0,1
+# Survival
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+# Death
+a,b,c,d,e,f,g,h,i,0
+
+@COLORS
+1 255 255 255
+";
+
+proptest! {
+    /// Tests that a rule table for Life matches the built-in Life rule.
+    #[test]
+    fn test_table_life(
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(8)), 0..40),
+        step_size in 1..20usize,
+    ) {
+        let mut tree_1 = NdTree::new();
+        for pos in cells_to_set {
+            tree_1.set_cell(&pos.convert(), 1);
+        }
+        let mut tree_2 = tree_1.clone();
+        Simulation::from(rule::LIFE).step(&mut tree_1, &step_size.into());
+        Simulation::new(rule_file::load_rule(LIFE_TABLE).unwrap())
+            .step(&mut tree_2, &step_size.into());
+        assert_eq!(
+            get_non_default_set(&tree_1.slice),
+            get_non_default_set(&tree_2.slice),
+        );
+    }
+}
+
+/// Tests bound and unbound variables, symmetries, and that the first
+/// matching transition takes precedence.
+#[test]
+fn test_table_variables() {
+    let rule = rule_file::load_rule::<Dim1D>(
+        "@RULE Test
+@TABLE
+n_states:4
+neighborhood:oneDimensional
+symmetries:reflect
+var a={1,2}
+var b={1,2}
+var c={a,3}
+# Bound: a dead cell between two equal cells copies them.
+0,a,a,a
+# Unbound: a dead cell between two different cells becomes 3.
+0,a,b,3
+# Only applies when one neighbor is 3, since the lines above take
+# precedence otherwise.
+0,c,1,2
+# A live cell next to a 3 becomes 2.
+1,3,0,2
+",
+    )
+    .unwrap();
+    let mut sim = Simulation::new(rule);
+    let mut tree = NdTree::new();
+    for (x, state) in [1, 0, 1, 0, 2, 0, 1, 0, 3, 0, 0, 0, 1, 3]
+        .iter()
+        .enumerate()
+    {
+        tree.set_cell(&NdVec::big([x as isize]), *state);
+    }
+    sim.step(&mut tree, &1.into());
+    let cells: Vec<u8> = (0..14).map(|x| tree.get_cell(&NdVec::big([x]))).collect();
+    assert_eq!(vec![1, 1, 1, 3, 2, 3, 1, 2, 3, 0, 0, 0, 2, 3], cells);
+}
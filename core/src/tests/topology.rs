@@ -0,0 +1,220 @@
+use proptest::prelude::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use super::*;
+
+/// Returns the cell in the grid that is joined to a position at most one cell
+/// beyond its edges, or None if there is no such cell. This follows Golly's
+/// rules for joining the edges of a bounded grid, independently of
+/// `Topology::wrap_pos()`.
+fn join_edges(topology: &Topology2D, pos: &IVec2D) -> Option<IVec2D> {
+    let mut pos = *pos;
+    let w = topology.size[X] as isize;
+    let h = topology.size[Y] as isize;
+    // As in Golly, the grid is centered on the origin, rounding up and to the
+    // left. Golly has Y increasing downwards.
+    let (left, right) = (-(w / 2), w - 1 - w / 2);
+    let (bottom, top) = (h / 2 - (h - 1), h / 2);
+    let (twist_x, twist_y) = match topology.kind {
+        TopologyKind::Plane => {
+            let inside = (w == 0 || (left..=right).contains(&pos[X]))
+                && (h == 0 || (bottom..=top).contains(&pos[Y]));
+            return if inside { Some(pos) } else { None };
+        }
+        TopologyKind::Torus => (false, false),
+        TopologyKind::KleinBottle(ax) => (ax == X, ax == Y),
+        TopologyKind::CrossSurface => (true, true),
+    };
+    let (shift_x, shift_y) = match topology.shift {
+        Some((X, shift)) => (shift, 0),
+        Some((Y, shift)) => (0, shift),
+        _ => (0, 0),
+    };
+    // Shifting a cell across one edge may move it beyond another, so cross
+    // the left and right edges again afterwards.
+    for &ax in &[X, Y, X] {
+        if ax == X && w != 0 {
+            // Crossing the left or right edge.
+            let sign = if pos[X] > right {
+                1
+            } else if pos[X] < left {
+                -1
+            } else {
+                continue;
+            };
+            pos[X] -= sign * w;
+            // Golly's shifts are downwards, which is -Y in NDCell.
+            pos[Y] -= sign * shift_y;
+            if twist_x {
+                pos[Y] = bottom + top - pos[Y];
+            }
+        }
+        if ax == Y && h != 0 {
+            // Crossing the top or bottom edge. Crossing the bottom edge is
+            // moving downwards in Golly.
+            let sign = if pos[Y] < bottom {
+                1
+            } else if pos[Y] > top {
+                -1
+            } else {
+                continue;
+            };
+            pos[Y] += sign * h;
+            pos[X] += sign * shift_x;
+            if twist_y {
+                pos[X] = left + right - pos[X];
+            }
+        }
+    }
+    Some(pos)
+}
+
+/// Simulates Conway's Game of Life on a grid with the given topology one cell
+/// at a time, checking only the given rectangle.
+fn naive_life_step(
+    topology: &Topology2D,
+    rect: &IRect2D,
+    cells: &HashMap<IVec2D, u8>,
+) -> HashMap<IVec2D, u8> {
+    let get_cell = |pos: &IVec2D| {
+        join_edges(topology, pos)
+            .and_then(|pos| cells.get(&pos).copied())
+            .unwrap_or(0)
+    };
+    topology
+        .rect(rect)
+        .iter()
+        .map(|pos| {
+            let neighbors: u8 = IRect2D::moore(1)
+                .iter()
+                .filter(|offset| !offset.is_zero())
+                .map(|offset| get_cell(&(pos + offset)))
+                .sum();
+            let next = match (get_cell(&pos), neighbors) {
+                (_, 3) | (1, 2) => 1,
+                _ => 0,
+            };
+            (pos, next)
+        })
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 64,
+        ..Default::default()
+    })]
+
+    /// Tests that Conway's Game of Life in a bounded grid gives the same
+    /// result as simulating each cell individually.
+    #[test]
+    fn test_bounded_life(
+        topology in prop::sample::select(vec![
+            "P7,6", "T7,6", "T7+2,6", "T7,6-1", "K7,6*", "K7*,6", "C7,6", "T9,0", "P0,8",
+        ]),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(4)), 0..30),
+        step_size in 1..8usize,
+    ) {
+        let topology = Topology2D::try_from(topology).unwrap();
+        let mut tree = NdTree::new();
+        for pos in &cells_to_set {
+            tree.set_cell(&pos.convert(), 1);
+        }
+        let mut sim = Simulation::from(rule::LIFE).with_topology(topology.clone());
+        sim.step(&mut tree, &step_size.into());
+
+        // The pattern can only travel `step_size` cells along infinite axes.
+        let rect = IRect2D::moore(4 + step_size as isize + 1);
+        let mut cells: HashMap<IVec2D, u8> = topology
+            .rect(&rect)
+            .iter()
+            .map(|pos| (pos, 0))
+            .collect();
+        // Cells outside of the grid are cleared.
+        for pos in &cells_to_set {
+            if cells.contains_key(pos) {
+                cells.insert(*pos, 1);
+            }
+        }
+        for _ in 0..step_size {
+            cells = naive_life_step(&topology, &rect, &cells);
+        }
+        for (pos, &cell_state) in &cells {
+            assert_eq!(cell_state, tree.get_cell(&pos.convert()), "{} at {:?}", topology, pos);
+        }
+        // Nothing is left outside of the grid.
+        assert_eq!(
+            BigInt::from(cells.values().filter(|&&cell_state| cell_state != 0).count()),
+            tree.get_population()
+        );
+    }
+}
+
+/// Tests that a glider on a torus returns to the same place.
+#[test]
+fn test_torus_glider() {
+    let mut automaton: Automaton2D =
+        RleEncode::from_rle("x = 3, y = 3, rule = B3/S23:T8,8\nbo$2bo$3o!").unwrap();
+    assert_eq!("T8,8", automaton.sim.lock().unwrap().topology().to_string());
+    let initial_cells = get_non_default_set(&automaton.tree.slice);
+    for _ in 0..4 {
+        automaton.step(&BigInt::from(8));
+        assert_eq!(BigInt::from(5), automaton.get_population());
+    }
+    assert_eq!(initial_cells, get_non_default_set(&automaton.tree.slice));
+
+    // The topology is preserved when saving and loading the pattern.
+    let exported = automaton.to_rle();
+    assert!(exported.contains("rule = B3/S23:T8,8"), "{}", exported);
+    let mut reimported: Automaton2D = RleEncode::from_rle(&exported).unwrap();
+    let initial_cells = get_non_default_set(&reimported.tree.slice);
+    reimported.step(&BigInt::from(32));
+    assert_eq!(initial_cells, get_non_default_set(&reimported.tree.slice));
+}
+
+/// Tests gliders crossing shifted and twisted edges, with the results worked
+/// out from Golly's rules for bounded grids.
+#[test]
+fn test_golly_bounded_gliders() {
+    // Each glider moves down and to the right, and positions are of its
+    // top-left corner in Golly's coordinates.
+    let fixtures = [
+        // The glider crosses the bottom edge, which shifts it 3 cells to the
+        // right, and then the right edge: (16, 16) -> (19, -4) -> (-1, -4).
+        ("T10+3,20", "-2,-2", 72, "-1,-4", "bo$2bo$3o!"),
+        // The left and right edges are twisted, so the glider is reflected
+        // vertically when it crosses the right edge: (5, 5) -> (-5, -8).
+        ("K10,20*", "-2,-2", 28, "-5,-8", "3o$2bo$bo!"),
+        // The top and bottom edges are twisted, so the glider is reflected
+        // horizontally when it crosses the bottom edge after crossing the
+        // right edge: (10, 10) -> (0, 10) -> (-3, -10).
+        ("K10*,20", "-2,-2", 48, "-3,-10", "bo$o$3o!"),
+        // Every edge is twisted, so the glider is reflected horizontally when
+        // it crosses the bottom edge: (1, 5) -> (-4, -5).
+        ("C20,10", "-6,-2", 28, "-4,-5", "bo$o$3o!"),
+    ];
+    for &(topology, start, generations, end, expected) in &fixtures {
+        for &algorithm in AlgorithmKind::ALL {
+            let rle = format!(
+                "#CXRLE Pos={}\nx = 3, y = 3, rule = B3/S23:{}\nbo$2bo$3o!",
+                start, topology,
+            );
+            let mut automaton: Automaton2D = RleEncode::from_rle(&rle).unwrap();
+            automaton.set_algorithm(algorithm);
+            automaton.step(&BigInt::from(generations));
+            let rle = format!(
+                "#CXRLE Pos={}\nx = 3, y = 3, rule = B3/S23\n{}",
+                end, expected
+            );
+            let expected: Automaton2D = RleEncode::from_rle(&rle).unwrap();
+            assert_eq!(
+                get_non_default_set(&expected.tree.slice),
+                get_non_default_set(&automaton.tree.slice),
+                "{} using {}",
+                topology,
+                algorithm,
+            );
+        }
+    }
+}
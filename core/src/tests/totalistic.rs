@@ -0,0 +1,275 @@
+use proptest::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+use super::*;
+
+proptest! {
+    /// Tests that the generic totalistic rule matches the 2D Life rule.
+    #[test]
+    fn test_totalistic_life(
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(8)), 0..40),
+        step_size in 1..20usize,
+    ) {
+        let mut tree_1 = NdTree::new();
+        for pos in cells_to_set {
+            tree_1.set_cell(&pos.convert(), 1);
+        }
+        let mut tree_2 = tree_1.clone();
+        Simulation::from(rule::LIFE).step(&mut tree_1, &step_size.into());
+        Simulation::from(rule::Totalistic2D::try_from("B3/S23").unwrap())
+            .step(&mut tree_2, &step_size.into());
+        assert_eq!(
+            get_non_default_set(&tree_1.slice),
+            get_non_default_set(&tree_2.slice),
+        );
+    }
+
+    /// Tests that Larger than Life rules, which count neighbors using running
+    /// totals, match a naive simulation that checks every neighbor.
+    #[test]
+    fn test_larger_than_life(
+        radius in 2..5isize,
+        neighborhood in prop::sample::select(vec!['M', 'N', 'H', '+', 'C']),
+        include_middle in any::<bool>(),
+        birth in (1..8usize, 0..8usize),
+        survival in (0..8usize, 0..8usize),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(6)), 0..60),
+        generations in 1..4usize,
+    ) {
+        // The smallest neighborhood here has 8 cells.
+        let birth = birth.0..=std::cmp::min(birth.0 + birth.1, 8);
+        let survival = survival.0..=std::cmp::min(survival.0 + survival.1, 8);
+        let rulestring = format!(
+            "R{},C0,M{},S{}..{},B{}..{},N{}",
+            radius,
+            include_middle as u8,
+            survival.start(),
+            survival.end(),
+            birth.start(),
+            birth.end(),
+            neighborhood,
+        );
+        let mut sim = Simulation::from(rule::Totalistic2D::try_from(&*rulestring).unwrap());
+        let mut tree = NdTree::new();
+        let mut expected: HashSet<IVec2D> = cells_to_set.into_iter().collect();
+        for pos in &expected {
+            tree.set_cell(&pos.convert(), 1);
+        }
+        for _ in 0..generations {
+            sim.step(&mut tree, &1.into());
+            expected = naive_ltl_step(
+                &expected,
+                radius,
+                neighborhood,
+                include_middle,
+                &birth,
+                &survival,
+            );
+        }
+        assert_eq!(
+            make_cell_coords_set(expected.into_iter().collect()),
+            get_non_default_set(&tree.slice),
+        );
+    }
+}
+
+proptest! {
+    /// Tests that a "B0" rule matches its complement (which has no B0 if the
+    /// original rule has no S8) with the complementary pattern.
+    #[test]
+    fn test_b0_complement(
+        birth in prop::collection::vec(any::<bool>(), 8),
+        survival in prop::collection::vec(any::<bool>(), 9),
+        cells_to_set in prop::collection::vec(any_with::<IVec2D>(Some(6)), 0..40),
+        step_size in 1..10usize,
+    ) {
+        // Birth on 0 neighbors is always included.
+        let birth: Vec<bool> = Some(true).into_iter().chain(birth).collect();
+        // A dead cell in the complement is born if the original's live cell
+        // with the opposite number of neighbors dies, and vice versa.
+        let complement_birth: Vec<bool> = (0..=8).map(|n| !survival[8 - n]).collect();
+        let complement_survival: Vec<bool> = (0..=8).map(|n| !birth[8 - n]).collect();
+        let rulestring = |birth: &[bool], survival: &[bool]| {
+            let digits = |counts: &[bool]| -> String {
+                (0..=8).filter(|&n| counts[n]).map(|n| n.to_string()).collect()
+            };
+            format!("B{}/S{}", digits(birth), digits(survival))
+        };
+        let mut sim = Simulation::from(
+            rule::Totalistic2D::try_from(&*rulestring(&birth, &survival)).unwrap(),
+        );
+        let mut complement_sim = Simulation::from(
+            rule::Totalistic2D::try_from(&*rulestring(&complement_birth, &complement_survival))
+                .unwrap(),
+        );
+
+        let mut tree = NdTree::new();
+        let mut complement_tree = NdTree::with_background(1);
+        for pos in cells_to_set {
+            tree.set_cell(&pos.convert(), 1);
+            complement_tree.set_cell(&pos.convert(), 0);
+        }
+        sim.step(&mut tree, &step_size.into());
+        complement_sim.step(&mut complement_tree, &step_size.into());
+        assert_eq!(
+            tree.get_background().leaf().map(|state| 1 - state),
+            complement_tree.get_background().leaf(),
+        );
+        assert_eq!(tree.get_population(), complement_tree.get_population());
+        for pos in IRect2D::moore(16).iter() {
+            let pos: BigVec2D = pos.convert();
+            assert_eq!(1 - tree.get_cell(&pos), complement_tree.get_cell(&pos));
+        }
+    }
+}
+
+/// Computes the next generation of a 2-state 2D Larger than Life rule by
+/// checking every neighbor of every cell.
+fn naive_ltl_step(
+    live: &HashSet<IVec2D>,
+    radius: isize,
+    neighborhood: char,
+    include_middle: bool,
+    birth: &RangeInclusive<usize>,
+    survival: &RangeInclusive<usize>,
+) -> HashSet<IVec2D> {
+    let offsets: Vec<IVec2D> = Rect2D::moore(radius)
+        .iter()
+        .filter(|pos| match neighborhood {
+            'M' => true,
+            'N' => pos[X].abs() + pos[Y].abs() <= radius,
+            'H' => (pos[X] + pos[Y]).abs() <= radius,
+            '+' => pos[X] == 0 || pos[Y] == 0,
+            'C' => pos[X] * pos[X] + pos[Y] * pos[Y] <= radius * radius + radius,
+            _ => unreachable!(),
+        })
+        .collect();
+    let candidates: HashSet<IVec2D> = live
+        .iter()
+        .flat_map(|pos| offsets.iter().map(move |offset| *pos + *offset))
+        .collect();
+    candidates
+        .into_iter()
+        .filter(|pos| {
+            let count = offsets
+                .iter()
+                .filter(|offset| !offset.is_zero() || include_middle)
+                .filter(|&offset| live.contains(&(*pos + *offset)))
+                .count();
+            if live.contains(pos) {
+                survival.contains(&count)
+            } else {
+                birth.contains(&count)
+            }
+        })
+        .collect()
+}
+
+/// Tests a 1D range-2 totalistic rule.
+#[test]
+fn test_totalistic_1d() {
+    let mut tree = NdTree::new();
+    let mut sim = Simulation::from(rule::Totalistic1D::try_from("R2,C0,S,B1,NM").unwrap());
+    tree.set_cell(&NdVec::big([0]), 1);
+    sim.step(&mut tree, &1.into());
+    assert_eq!(
+        make_cell_coords_set(vec![NdVec([-2]), NdVec([-1]), NdVec([1]), NdVec([2])]),
+        get_non_default_set(&tree.slice)
+    );
+    sim.step(&mut tree, &1.into());
+    assert_eq!(
+        make_cell_coords_set(vec![NdVec([-4]), NdVec([4])]),
+        get_non_default_set(&tree.slice)
+    );
+}
+
+/// Tests Brian's Brain, a 2D "Generations" rule.
+#[test]
+fn test_generations() {
+    let mut tree = NdTree::new();
+    let mut sim = Simulation::from(rule::Totalistic2D::try_from("B2/S/C3").unwrap());
+    let assert_cells = |tree: &NdTree<u8, Dim2D>, live: &[[isize; 2]], dying: &[[isize; 2]]| {
+        let mut expected = HashMap::new();
+        expected.extend(live.iter().map(|&pos| (NdVec(pos).convert(), 1)));
+        expected.extend(dying.iter().map(|&pos| (NdVec(pos).convert(), 2)));
+        let actual: HashMap<BigVec2D, u8> = get_non_default_set(&tree.slice)
+            .into_iter()
+            .map(|pos| {
+                let state = tree.get_cell(&pos);
+                (pos, state)
+            })
+            .collect();
+        assert_eq!(expected, actual);
+    };
+    tree.set_cell(&NdVec::big([0, 0]), 1);
+    tree.set_cell(&NdVec::big([1, 0]), 1);
+    sim.step(&mut tree, &1.into());
+    assert_cells(
+        &tree,
+        &[[0, 1], [1, 1], [0, -1], [1, -1]],
+        &[[0, 0], [1, 0]],
+    );
+    sim.step(&mut tree, &1.into());
+    assert_cells(
+        &tree,
+        &[[0, 2], [1, 2], [0, -2], [1, -2], [-1, 0], [2, 0]],
+        &[[0, 1], [1, 1], [0, -1], [1, -1]],
+    );
+}
+
+/// Tests "B0" rules, whose background changes.
+#[test]
+fn test_b0() {
+    // With S8, the background stays alive.
+    let mut tree = NdTree::new();
+    let mut sim = Simulation::from(rule::Totalistic2D::try_from("B0123478/S34678").unwrap());
+    tree.set_cell(&NdVec::big([0, 0]), 1);
+    assert_eq!(BigInt::from(1), tree.get_population());
+    sim.step(&mut tree, &1.into());
+    assert_eq!(Some(1), tree.get_background().leaf());
+    assert_eq!(1, tree.get_cell(&NdVec::big([100, -100])));
+    assert_eq!(1, tree.get_cell(&NdVec::big([1, 1])));
+    // The cell that was alive had no live neighbors, so it died.
+    assert_eq!(0, tree.get_cell(&NdVec::big([0, 0])));
+    assert_eq!(BigInt::from(1), tree.get_population());
+    sim.step(&mut tree, &1.into());
+    assert_eq!(Some(1), tree.get_background().leaf());
+    assert_eq!(BigInt::from(0), tree.get_population());
+
+    // Without S8, the background alternates.
+    let mut sim = Simulation::from(rule::Totalistic2D::try_from("B03/S23").unwrap());
+    let mut tree = NdTree::new();
+    sim.step(&mut tree, &1.into());
+    assert_eq!(Some(1), tree.get_background().leaf());
+    sim.step(&mut tree, &1.into());
+    assert_eq!(Some(0), tree.get_background().leaf());
+    sim.step(&mut tree, &1001.into());
+    assert_eq!(Some(1), tree.get_background().leaf());
+    assert_eq!(0, sim.advance_background(0, &BigInt::from(1_000_000)));
+}
+
+/// Tests that a large step in 3D, which simulates many branches in parallel,
+/// gives the same result as many small steps.
+#[test]
+fn test_3d_large_step() {
+    let rule = rule::Totalistic3D::try_from("3D:B5/S4567").unwrap();
+    let mut tree_1 = NdTree::new();
+    for pos in IRect3D::moore(4).iter() {
+        if (pos[X] * 7 + pos[Y] * 3 + pos[Z] * 5).rem_euclid(4) == 0 {
+            tree_1.set_cell(&pos.convert(), 1);
+        }
+    }
+    let mut tree_2 = tree_1.clone();
+    Simulation::from(rule.clone()).step(&mut tree_1, &BigInt::from(16));
+    let mut sim = Simulation::from(rule);
+    for _ in 0..16 {
+        sim.step(&mut tree_2, &BigInt::from(1));
+    }
+    assert_ne!(BigInt::from(0), tree_1.get_population());
+    assert_eq!(
+        get_non_default_set(&tree_1.slice),
+        get_non_default_set(&tree_2.slice),
+    );
+}
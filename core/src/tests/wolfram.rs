@@ -0,0 +1,64 @@
+use proptest::prelude::*;
+use std::convert::TryFrom;
+
+use super::*;
+
+/// Tests the center column of Rule 30.
+#[test]
+fn test_rule_30() {
+    let mut tree = NdTree::new();
+    let mut sim = Simulation::new(rule::from_rulestring::<Dim1D>("W30").unwrap());
+    tree.set_cell(&NdVec::big([0]), 1);
+    let mut center_column = vec![];
+    for _ in 0..20 {
+        center_column.push(tree.get_cell(&NdVec::big([0])));
+        sim.step(&mut tree, &1.into());
+    }
+    assert_eq!(
+        vec![1, 1, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 1, 1, 0, 0, 1],
+        center_column
+    );
+}
+
+/// Tests simulating a 1D rule for an astronomically large number of
+/// generations.
+#[test]
+fn test_wolfram_big_step() {
+    let mut tree = NdTree::new();
+    // Rule 170 shifts everything left by one cell each generation.
+    let mut sim = Simulation::from(rule::Wolfram1D::elementary(170));
+    tree.set_cell(&NdVec::big([0]), 1);
+    tree.set_cell(&NdVec::big([2]), 1);
+    let step_size = BigInt::from(1) << 100;
+    sim.step(&mut tree, &step_size);
+    let pos = -step_size;
+    assert_eq!(1, tree.get_cell(&NdVec([pos.clone()])));
+    assert_eq!(0, tree.get_cell(&NdVec([pos.clone() + 1])));
+    assert_eq!(1, tree.get_cell(&NdVec([pos + 2])));
+    assert_eq!(BigInt::from(2), tree.get_root().population().to_bigint());
+}
+
+proptest! {
+    /// Tests that a totalistic Wolfram code gives the same results as the
+    /// equivalent general code, using Rule 150 (the XOR of all three cells in
+    /// the neighborhood).
+    #[test]
+    fn test_wolfram_totalistic(
+        cells_to_set in prop::collection::vec(-20..=20_isize, 0..20),
+        step_size in 1..40_usize,
+    ) {
+        let mut tree_1 = NdTree::new();
+        for x in cells_to_set {
+            tree_1.set_cell(&NdVec::big([x]), 1);
+        }
+        let mut tree_2 = tree_1.clone();
+        Simulation::from(rule::Wolfram1D::try_from("W150").unwrap())
+            .step(&mut tree_1, &step_size.into());
+        Simulation::from(rule::Wolfram1D::try_from("K2,R1,T10").unwrap())
+            .step(&mut tree_2, &step_size.into());
+        assert_eq!(
+            get_non_default_set(&tree_1.slice),
+            get_non_default_set(&tree_2.slice),
+        );
+    }
+}
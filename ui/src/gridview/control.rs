@@ -1,6 +1,6 @@
 use num::BigInt;
 
-use ndcell_core::{BigVec2D, FVec2D};
+use ndcell_core::{AlgorithmKind, BigVec2D, FVec2D};
 
 // TODO: Document all these commands!
 
@@ -23,6 +23,7 @@ pub enum SimCommand {
     StopRunning,
     ToggleRunning,
     Cancel,
+    SetAlgorithm(AlgorithmKind),
 }
 impl From<SimCommand> for Command {
     fn from(c: SimCommand) -> Self {
@@ -75,6 +76,7 @@ pub enum ClipboardCommand {
     CopyRle,
     CopyCxrle,
     Paste,
+    PasteAgar,
 }
 impl From<ClipboardCommand> for Command {
     fn from(c: ClipboardCommand) -> Self {
@@ -70,6 +70,13 @@ pub trait GridViewTrait: Sized + NdSimulate + History {
             SimCommand::Cancel => {
                 self.stop_running();
             }
+            SimCommand::SetAlgorithm(algorithm) => {
+                // The worker has its own copy of the automaton, which would
+                // otherwise be stepped with the old algorithm and replace this
+                // one.
+                self.stop_running();
+                self.set_algorithm(algorithm);
+            }
         }
     }
 
@@ -164,3 +171,34 @@ impl GridView {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use super::*;
+
+    /// Steps the simulation by one generation and waits for the worker to
+    /// finish.
+    fn step_and_wait(gridview: &mut GridView2D, config: &Config) {
+        gridview.do_sim_command(SimCommand::Step(1.into()), config);
+        while gridview.is_waiting {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            gridview.do_frame(config);
+        }
+    }
+
+    /// Tests that switching algorithms after stepping is not undone by the
+    /// next step.
+    #[test]
+    fn test_set_algorithm_after_step() {
+        let config = Config::default();
+        let mut gridview = GridView2D::from(Automaton2D::default());
+        step_and_wait(&mut gridview, &config);
+        gridview.do_sim_command(SimCommand::SetAlgorithm(AlgorithmKind::BruteForce), &config);
+        assert_eq!(AlgorithmKind::BruteForce, gridview.get_algorithm());
+        step_and_wait(&mut gridview, &config);
+        assert_eq!(AlgorithmKind::BruteForce, gridview.get_algorithm());
+        assert_eq!(&BigInt::from(2), gridview.get_generation_count());
+    }
+}
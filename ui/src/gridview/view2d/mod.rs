@@ -162,13 +162,22 @@ impl GridViewTrait for GridView2D {
                                     .map_err(|_| "Unable to access clipboard contents".to_owned())
                                     .and_then(|s| rle::RleEncode::from_rle(&s));
                                 match result {
-                                    Ok(mut new_automaton) => {
-                                        new_automaton.set_sim(Simulation::from(rule::LIFE));
-                                        *self = Self::from(new_automaton)
-                                    }
+                                    Ok(new_automaton) => self.set_pasted_automaton(new_automaton),
                                     Err(msg) => warn!("Failed to load RLE from clipboard: {}", msg),
                                 }
                             }
+                            ClipboardCommand::PasteAgar => {
+                                self.record();
+                                let result: Result<Automaton2D, _> = clipboard_get()
+                                    .map_err(|_| "Unable to access clipboard contents".to_owned())
+                                    .and_then(|s| rle::RleEncode::from_agar_rle(&s));
+                                match result {
+                                    Ok(new_automaton) => self.set_pasted_automaton(new_automaton),
+                                    Err(msg) => {
+                                        warn!("Failed to load agar from clipboard: {}", msg)
+                                    }
+                                }
+                            }
                         }
                     }
                 }
@@ -325,12 +334,31 @@ impl GridView2D {
     pub fn get_cell(&self, pos: &BigVec2D) -> u8 {
         self.automaton.get_projected_tree().get_cell(pos)
     }
+    /// Returns the progress of the simulation step currently being computed,
+    /// if any.
+    pub fn get_sim_progress(&self) -> Option<StepProgress> {
+        if !self.is_waiting && !self.is_running {
+            return None;
+        }
+        self.worker.as_ref().map(Worker::get_progress)
+    }
     fn get_worker(&mut self) -> &mut Worker<ProjectedAutomaton2D> {
         if let None = self.worker {
             self.worker = Some(Worker::new(self.automaton.clone()));
         }
         self.worker.as_mut().unwrap()
     }
+    /// Replaces the automaton with one loaded from the clipboard, keeping the
+    /// undo history. The new automaton is moved to the current node cache so
+    /// that it shares nodes with the history entries.
+    fn set_pasted_automaton(&mut self, mut new_automaton: Automaton2D) {
+        if let Automaton::Automaton2D(automaton) = self.get_automaton() {
+            new_automaton.tree.set_cache(&automaton.tree.cache);
+        }
+        let undo_stack = std::mem::take(&mut self.undo_stack);
+        *self = Self::from(new_automaton);
+        self.undo_stack = undo_stack;
+    }
 }
 
 #[derive(Debug, Default)]
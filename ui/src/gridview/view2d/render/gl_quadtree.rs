@@ -26,13 +26,16 @@ impl<C: CellType> CachedGlQuadtree<C> {
         &mut self,
         node: NdCachedNode<C, Dim2D>,
         min_layer: usize,
+        node_cache: &NdTreeCache<C, Dim2D>,
         pixelator: impl FnMut(&NdTreeBranch<C, Dim2D>) -> [u8; 4],
     ) {
         // Only recompute quadtree if necessary.
         if self.current_min_layer == min_layer && Some(&node) == self.current_node.as_ref() {
             return;
         }
-        self.cached = Some(GlQuadtree::from_node(&node, min_layer, pixelator));
+        self.cached = Some(GlQuadtree::from_node(
+            &node, min_layer, node_cache, pixelator,
+        ));
         self.current_node = Some(node);
         self.current_min_layer = min_layer;
     }
@@ -40,9 +43,10 @@ impl<C: CellType> CachedGlQuadtree<C> {
         &mut self,
         node: NdCachedNode<C, Dim2D>,
         min_layer: usize,
+        node_cache: &NdTreeCache<C, Dim2D>,
         pixelator: impl FnMut(&NdTreeBranch<C, Dim2D>) -> [u8; 4],
     ) -> &GlQuadtree {
-        self.set_node(node, min_layer, pixelator);
+        self.set_node(node, min_layer, node_cache, pixelator);
         self.unwrap()
     }
     pub fn reset(&mut self) {
@@ -67,9 +71,10 @@ impl GlQuadtree {
     pub fn from_node<C: CellType>(
         node: &NdCachedNode<C, Dim2D>,
         min_layer: usize,
+        node_cache: &NdTreeCache<C, Dim2D>,
         mut pixelator: impl FnMut(&NdTreeBranch<C, Dim2D>) -> [u8; 4],
     ) -> Self {
-        let indexed_tree = IndexedNdTree::from_node(node, min_layer);
+        let indexed_tree = IndexedNdTree::from_node(node, min_layer, node_cache);
         let mut pixel_vec: Vec<u32> = indexed_tree
             .get_nodes()
             .iter()
@@ -1,7 +1,7 @@
 //! The data structures and routines related to rendering 2D square cells.
 //!
-//! Currently, only solid colors are supported, however I plan to add icons in
-//! the future.
+//! Cells are drawn using the colors given by the rule's `RuleInfo`, or its
+//! icons when zoomed in far enough for them to fit.
 //!
 //! Not including preliminary computations, there are four main stages to
 //! rendering:
@@ -39,9 +39,11 @@
 //! however, this is not guaranteed.
 
 use glium::index::PrimitiveType;
+use glium::texture::RawImage2d;
 use glium::{uniform, Surface as _};
 use noisy_float::prelude::r64;
 use num::{BigInt, ToPrimitive, Zero};
+use std::sync::Arc;
 
 use ndcell_core::space::*;
 
@@ -65,10 +67,6 @@ const GRID_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
 /// The color given to the highlighted cell. This will be configurable in the
 /// future.
 const GRID_HIGHLIGHT_COLOR: [f32; 4] = [0.0, 0.5, 1.0, 1.0];
-/// The color for dead cells. This will be configurable in the future.
-const DEAD_COLOR: (u8, u8, u8) = (0, 0, 0);
-/// The color for live cells. This will be configurable in the future.
-const LIVE_COLOR: (u8, u8, u8) = (255, 255, 255);
 
 /// The number of gridlines in each render batch.
 const GRIDLINE_BATCH_SIZE: usize = 256;
@@ -76,6 +74,10 @@ const GRIDLINE_BATCH_SIZE: usize = 256;
 #[derive(Default)]
 pub struct RenderCache {
     gl_quadtree: CachedGlQuadtree<u8>,
+    /// Information about the cell states of the automaton's rule, which cannot
+    /// change without creating a new GridView (and therefore a new
+    /// RenderCache).
+    rule_info: Option<RuleInfo>,
 }
 
 pub struct RenderInProgress<'a> {
@@ -89,6 +91,8 @@ pub struct RenderInProgress<'a> {
     render_cell_pixels: f64,
     /// A slice of the quadtree that encompasses all visible cells.
     quadtree_slice: NdTreeSlice<u8, Dim2D>,
+    /// The node cache of the quadtree.
+    node_cache: Arc<NdTreeCache<u8, Dim2D>>,
     /// The render cell position within quadtree_slice that is centered on the
     /// screen.
     pos: FVec2D,
@@ -134,6 +138,7 @@ impl<'a> RenderInProgress<'a> {
         // cell position relative to that node that will be in the center of the
         // screen.
         let quadtree_slice: NdTreeSlice<u8, Dim2D>;
+        let node_cache: Arc<NdTreeCache<u8, Dim2D>>;
         let visible_rect: IRect2D;
         let pos: FVec2D;
         {
@@ -155,10 +160,12 @@ impl<'a> RenderInProgress<'a> {
             }
 
             // Now fetch the NdTreeSlice containing all of the visible cells.
-            quadtree_slice = g.automaton.get_projected_tree().get_slice_containing(
+            let mut projected_tree = g.automaton.get_projected_tree();
+            quadtree_slice = projected_tree.get_slice_containing(
                 // Convert chunk coordinates into normal cell coordinates.
                 &global_visible_rect,
             );
+            node_cache = projected_tree.cache;
 
             // Subtract the slice offset from global_visible_rect and
             // global_chunk_visible_rect so that they are relative to the slice.
@@ -204,12 +211,19 @@ impl<'a> RenderInProgress<'a> {
             ];
         }
 
+        // Only fetch the rule info once, because it requires locking the
+        // simulation (which may be in use by the worker thread).
+        if cache.rule_info.is_none() {
+            cache.rule_info = Some(g.automaton.get_rule_info());
+        }
+
         Self {
             viewport,
             target,
             render_cell_layer,
             render_cell_pixels,
             quadtree_slice,
+            node_cache,
             pos,
             visible_rect,
             view_matrix,
@@ -221,10 +235,12 @@ impl<'a> RenderInProgress<'a> {
     pub fn draw_cells(&mut self) {
         let textures: &mut textures::TextureCache = &mut textures::CACHE.borrow_mut();
         // Steps #1: encode the quadtree as a 1D texture.
+        let rule_info = self.cache.rule_info.as_ref().unwrap();
         let gl_quadtree = self.cache.gl_quadtree.from_node(
             self.quadtree_slice.root.clone(),
             self.render_cell_layer,
-            Self::get_branch_pixel_color,
+            &self.node_cache,
+            |branch| Self::get_branch_pixel_color(rule_info, branch),
         );
         // Step #2: draw at 1 pixel per render cell, including only the cells
         // inside self.visible_rect.
@@ -263,6 +279,19 @@ impl<'a> RenderInProgress<'a> {
             glium::uniforms::MagnifySamplerFilter::Nearest,
         );
 
+        // If cells are large enough to show icons, draw them over the scaled
+        // texture instead.
+        if self.render_cell_layer == 0 && rule_info.has_icons() {
+            if let Some(image) = Self::make_icon_image(
+                rule_info,
+                &self.quadtree_slice.root,
+                self.visible_rect,
+                integer_scale_factor as usize,
+            ) {
+                scaled_cells_texture.write(entire_rect(&scaled_cells_fbo), image);
+            }
+        }
+
         // Step #4: render that onto the screen.
         let (target_w, target_h) = self.target.get_dimensions();
         let target_size = NdVec([r64(target_w as f64), r64(target_h as f64)]);
@@ -293,30 +322,86 @@ impl<'a> RenderInProgress<'a> {
         //     glium::uniforms::MagnifySamplerFilter::Linear,
         // );
     }
-    fn get_branch_pixel_color(branch: &NdTreeBranch<u8, Dim2D>) -> [u8; 4] {
+    fn get_branch_pixel_color(rule_info: &RuleInfo, branch: &NdTreeBranch<u8, Dim2D>) -> [u8; 4] {
         let ratio = match branch {
-            NdTreeBranch::Leaf(cell_state) => *cell_state as f64,
+            // Individual cells are drawn using the color of their state.
+            NdTreeBranch::Leaf(cell_state) => return rule_info.color(*cell_state),
             NdTreeBranch::Node(node) => {
-                if node.population.is_zero() {
+                if node.population().is_zero() {
                     0.0
                 } else if let Some(node_len) = node.len().to_f64() {
-                    let population = node.population.to_f64().unwrap();
+                    let population = node.population().to_f64();
                     (population / 2.0) / node_len.powf(2.0) + 0.5
                 } else {
                     1.0
                 }
             }
         };
-        let r = ((LIVE_COLOR.0 as f64).powf(2.0) * ratio
-            + (DEAD_COLOR.0 as f64).powf(2.0) * (1.0 - ratio))
-            .powf(0.5);
-        let g = ((LIVE_COLOR.1 as f64).powf(2.0) * ratio
-            + (DEAD_COLOR.1 as f64).powf(2.0) * (1.0 - ratio))
-            .powf(0.5);
-        let b = ((LIVE_COLOR.2 as f64).powf(2.0) * ratio
-            + (DEAD_COLOR.2 as f64).powf(2.0) * (1.0 - ratio))
-            .powf(0.5);
-        [r as u8, g as u8, b as u8, 255]
+        // Larger nodes are drawn by blending the colors of states #0 and #1.
+        let dead_color = rule_info.color(0);
+        let live_color = rule_info.color(1);
+        let mut ret = [0, 0, 0, 255];
+        for i in 0..3 {
+            ret[i] = ((live_color[i] as f64).powf(2.0) * ratio
+                + (dead_color[i] as f64).powf(2.0) * (1.0 - ratio))
+                .powf(0.5) as u8;
+        }
+        ret
+    }
+
+    /// Returns an image of the given rectangle of cells using each state's
+    /// icon, with each cell the given number of pixels wide, or `None` if no
+    /// icons fit in cells of that size.
+    fn make_icon_image(
+        rule_info: &RuleInfo,
+        node: &NdCachedNode<u8, Dim2D>,
+        rect: IRect2D,
+        cell_pixels: usize,
+    ) -> Option<RawImage2d<'static, u8>> {
+        let state_count = rule_info.state_count();
+        if (0..state_count).all(|state| rule_info.icon(state as u8, cell_pixels).is_none()) {
+            return None;
+        }
+        let image_w = rect.len(X) as usize * cell_pixels;
+        let image_h = rect.len(Y) as usize * cell_pixels;
+        // Icons are drawn over the color of state #0, like in Golly.
+        let background = rule_info.color(0);
+        let mut data = vec![0; image_w * image_h * 4];
+        for pos in rect.iter() {
+            let cell_state = node.get_cell(&pos.convert::<BigInt>());
+            let icon = rule_info.icon(cell_state, cell_pixels);
+            // Center the icon in the cell.
+            let margin = icon.map_or(0, |icon| (cell_pixels - icon.size) / 2);
+            let cell_x = (pos[X] - rect.min()[X]) as usize * cell_pixels;
+            let cell_y = (pos[Y] - rect.min()[Y]) as usize * cell_pixels;
+            for y in 0..cell_pixels {
+                for x in 0..cell_pixels {
+                    let color = match icon {
+                        None => rule_info.color(cell_state),
+                        Some(icon) => {
+                            // Icon rows go from top to bottom, but texture rows
+                            // go from bottom to top.
+                            let icon_x = x.checked_sub(margin).filter(|&x| x < icon.size);
+                            let icon_y = (cell_pixels - 1 - y)
+                                .checked_sub(margin)
+                                .filter(|&y| y < icon.size);
+                            match icon_x.zip(icon_y) {
+                                Some((icon_x, icon_y)) => {
+                                    blend(icon.pixels[icon_y * icon.size + icon_x], background)
+                                }
+                                None => background,
+                            }
+                        }
+                    };
+                    let i = ((cell_y + y) * image_w + cell_x + x) * 4;
+                    data[i..i + 4].copy_from_slice(&color);
+                }
+            }
+        }
+        Some(RawImage2d::from_raw_rgba(
+            data,
+            (image_w as u32, image_h as u32),
+        ))
     }
 
     pub fn with_gridlines_fbo(
@@ -548,6 +633,16 @@ impl<'a> RenderInProgress<'a> {
     }
 }
 
+/// Draws a color with transparency over an opaque color.
+fn blend(fg: [u8; 4], bg: [u8; 4]) -> [u8; 4] {
+    let alpha = fg[3] as u32;
+    let mut ret = [0, 0, 0, 255];
+    for i in 0..3 {
+        ret[i] = ((fg[i] as u32 * alpha + bg[i] as u32 * (255 - alpha)) / 255) as u8;
+    }
+    ret
+}
+
 fn entire_rect<S: glium::Surface>(surface: &S) -> glium::Rect {
     let (width, height) = surface.get_dimensions();
     glium::Rect {
@@ -1,10 +1,10 @@
-use log::trace;
+use log::{trace, warn};
 use num::BigInt;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use ndcell_core::NdSimulate;
+use ndcell_core::{NdSimulate, StepControl, StepError, StepProgress};
 
 pub enum WorkerRequest {
     Step(BigInt),
@@ -18,16 +18,29 @@ pub struct WorkerResult<T> {
 }
 
 /// A manager for worker threads that compute simulation results concurrently.
+///
+/// Dropping the Worker cancels the step in progress, if any.
 pub struct Worker<T: NdSimulate + Clone + Send> {
     requests_tx: mpsc::Sender<WorkerRequest>,
     results_rx: mpsc::Receiver<WorkerResult<T>>,
     request_count: usize,
+    control: StepControl,
+    progress: Arc<Mutex<StepProgress>>,
 }
 
 impl<T: 'static + NdSimulate + Clone + Send> Worker<T> {
     pub fn new(mut simulation: T) -> Self {
         let (requests_tx, requests_rx) = mpsc::channel();
         let (results_tx, results_rx) = mpsc::sync_channel(1);
+        let progress = Arc::new(Mutex::new(StepProgress::default()));
+        let control = {
+            let progress = progress.clone();
+            StepControl::new().with_progress_callback(move |new_progress| {
+                *progress.lock().unwrap() = new_progress;
+            })
+        };
+        let thread_control = control.clone();
+        let thread_progress = progress.clone();
         thread::spawn(move || loop {
             loop {
                 let continuous: bool;
@@ -48,13 +61,23 @@ impl<T: 'static + NdSimulate + Clone + Send> Worker<T> {
                 }
                 let mut record = true;
                 loop {
+                    *thread_progress.lock().unwrap() = StepProgress::default();
                     let t1 = Instant::now();
-                    simulation.step(&step_size);
+                    let step_result = simulation.try_step(&step_size, &thread_control);
+                    if step_result == Err(StepError::Cancelled) {
+                        trace!("Worker thread ending (step cancelled)");
+                        return;
+                    }
+                    if let Err(e) = step_result {
+                        // Send back the unchanged simulation so that the
+                        // request is still answered.
+                        warn!("Simulation step failed: {}", e);
+                    }
                     let t2 = Instant::now();
                     if results_tx
                         .send(WorkerResult {
                             result: simulation.clone(),
-                            record,
+                            record: record && step_result.is_ok(),
                             time: t2 - t1,
                         })
                         .is_err()
@@ -62,7 +85,7 @@ impl<T: 'static + NdSimulate + Clone + Send> Worker<T> {
                         trace!("Worker thread ending (results channel dropped)");
                         return;
                     }
-                    if !continuous {
+                    if !continuous || step_result.is_err() {
                         break;
                     }
                     record = false;
@@ -73,6 +96,8 @@ impl<T: 'static + NdSimulate + Clone + Send> Worker<T> {
             requests_tx,
             results_rx,
             request_count: 0,
+            control,
+            progress,
         }
     }
     pub fn request(&mut self, request: WorkerRequest) {
@@ -98,4 +123,16 @@ impl<T: 'static + NdSimulate + Clone + Send> Worker<T> {
     pub fn get_request_count(&self) -> usize {
         self.request_count
     }
+    /// Returns the progress of the step currently being computed.
+    pub fn get_progress(&self) -> StepProgress {
+        *self.progress.lock().unwrap()
+    }
+}
+
+impl<T: NdSimulate + Clone + Send> Drop for Worker<T> {
+    fn drop(&mut self) {
+        // Stop the worker thread as soon as possible rather than letting it
+        // finish a step that nobody is waiting for.
+        self.control.cancel();
+    }
 }
@@ -31,11 +31,12 @@ $26bobo!
 ";
 
 fn make_default_gridview() -> GridView {
-    let mut automaton = Automaton2D::from_rle(GOSPER_GLIDER_GUN_SYNTH_RLE).unwrap_or_else(|_| {
+    let automaton = Automaton2D::from_rle(GOSPER_GLIDER_GUN_SYNTH_RLE).unwrap_or_else(|_| {
         warn!("Unable to parse default pattern; using empty pattern instead");
-        Default::default()
+        let mut automaton = Automaton2D::default();
+        automaton.set_sim(Simulation::from(rule::LIFE));
+        automaton
     });
-    automaton.set_sim(Simulation::from(rule::LIFE));
     GridView::from(automaton)
 }
 
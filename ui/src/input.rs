@@ -266,6 +266,10 @@ impl<'a> FrameInProgress<'a> {
                         Some(VirtualKeyCode::C) => {
                             self.gridview.enqueue(ClipboardCommand::CopyCxrle)
                         }
+                        // Paste as a periodic background.
+                        Some(VirtualKeyCode::V) => {
+                            self.gridview.enqueue(ClipboardCommand::PasteAgar)
+                        }
                         _ => (),
                     },
                     _ => (),
@@ -53,6 +53,14 @@ impl MainWindow {
                         },
                         "SIMULATING",
                     );
+                    if let Some(progress) = view2d.get_sim_progress() {
+                        if progress.completed > 0 {
+                            let fraction = progress.fraction();
+                            ProgressBar::new(fraction as f32)
+                                .overlay_text(&ImString::new(format!("{:.0}%", fraction * 100.0)))
+                                .build(&ui);
+                        }
+                    }
                 } else {
                     ui.text("");
                 }
@@ -1,4 +1,4 @@
-use ndcell_core::NdSimulate;
+use ndcell_core::{AlgorithmKind, NdSimulate};
 
 use imgui::*;
 use num::ToPrimitive;
@@ -65,6 +65,19 @@ impl SimulationWindow {
                 ui.separator();
                 ui.spacing();
                 ui.spacing();
+                let old_algorithm = gridview.get_algorithm();
+                let mut algorithm = old_algorithm;
+                for &kind in AlgorithmKind::ALL {
+                    ui.radio_button(&ImString::new(kind.to_string()), &mut algorithm, kind);
+                }
+                if old_algorithm != algorithm {
+                    gridview.enqueue(SimCommand::SetAlgorithm(algorithm));
+                }
+                ui.spacing();
+                ui.spacing();
+                ui.separator();
+                ui.spacing();
+                ui.spacing();
                 ui.checkbox(im_str!("Breakpoint"), &mut config.sim.use_breakpoint);
                 if config.sim.use_breakpoint {
                     let old_breakpoint_gen_i32 =